@@ -0,0 +1,25 @@
+use crate::{parse_current_reading, CurrentReading, DeviceType};
+
+/// Bluetooth SIG company identifier of SAF Tehnika, the maker of Aranet.
+pub const MANUFACTURER_ID: u16 = 0x0702;
+
+/// Parse the current reading from the manufacturer specific advertisement
+/// data of an Aranet4.
+///
+/// The device only includes readings when "Smart Home Integration" is enabled
+/// in the app, otherwise this returns `None`. The layout is
+///
+/// | offset | size | content                                         |
+/// |--------|------|-------------------------------------------------|
+/// | 0      | 1    | flags, bit 5 set when integrations are enabled  |
+/// | 1      | 3    | firmware version as patch, minor, major         |
+/// | 4      | 4    | unknown                                         |
+/// | 8      | 13   | reading, as in [`CURRENT_READING_FULL`]         |
+/// | 21     | 1    | counter, incremented with each measurement      |
+///
+/// [`CURRENT_READING_FULL`]: crate::characteristics::CURRENT_READING_FULL
+#[must_use]
+pub fn parse_manufacturer_data(data: &[u8]) -> Option<CurrentReading> {
+    let mut reading = data.get(8..21)?;
+    parse_current_reading(DeviceType::Aranet4, &mut reading).ok()
+}
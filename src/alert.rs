@@ -0,0 +1,69 @@
+//! Alerts when the CO2 concentration crosses a threshold.
+
+use serde::Serialize;
+
+/// A change of the alert state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertEvent {
+    /// The concentration rose above the threshold.
+    Raised,
+    /// The concentration fell back below the clear level.
+    Cleared,
+}
+
+impl AlertEvent {
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Raised => "raised",
+            Self::Cleared => "cleared",
+        }
+    }
+}
+
+/// Alert with hysteresis, raised when the concentration exceeds `threshold`
+/// and only cleared once it drops below `clear`, so readings hovering around
+/// the threshold don't raise it repeatedly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Co2Alert {
+    threshold: u16,
+    clear:     u16,
+    raised:    bool,
+}
+
+impl Co2Alert {
+    /// Alert above `threshold` ppm, clearing below `clear` ppm. A `clear`
+    /// level above the threshold is lowered to the threshold.
+    #[must_use]
+    pub fn new(threshold: u16, clear: u16) -> Self {
+        Self {
+            threshold,
+            clear: clear.min(threshold),
+            raised: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn threshold(&self) -> u16 {
+        self.threshold
+    }
+
+    #[must_use]
+    pub const fn is_raised(&self) -> bool {
+        self.raised
+    }
+
+    /// Update with a new reading, returning the event if the state changed.
+    pub const fn update(&mut self, co2: u16) -> Option<AlertEvent> {
+        if !self.raised && co2 > self.threshold {
+            self.raised = true;
+            Some(AlertEvent::Raised)
+        } else if self.raised && co2 < self.clear {
+            self.raised = false;
+            Some(AlertEvent::Cleared)
+        } else {
+            None
+        }
+    }
+}
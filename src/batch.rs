@@ -0,0 +1,142 @@
+//! Sending items in batches from a background task, buffering them while the
+//! destination is unreachable. Shared by the HTTP integrations.
+
+use crate::{error::ErrorChain, retry::with_retry, HttpError};
+use std::{collections::VecDeque, error::Error, future::Future, panic, time::Duration};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+    time::{self, MissedTickBehavior},
+};
+use tracing::warn;
+
+/// How often the items of an incomplete batch are sent.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum number of items buffered while the destination is unreachable, the
+/// oldest are dropped beyond this.
+pub const BUFFER_CAP: usize = 10_000;
+
+/// Number of attempts made to send a batch before keeping it for the next
+/// flush.
+const SEND_ATTEMPTS: u32 = 3;
+
+/// Handle of a task sending items in batches.
+#[derive(Debug)]
+pub struct BatchSender<T> {
+    items: UnboundedSender<T>,
+    task:  JoinHandle<()>,
+}
+
+impl<T: Clone + Send + Sync + 'static> BatchSender<T> {
+    /// Spawn a task calling `send_batch` with up to `batch_size` items once
+    /// that many are buffered, and with the items of an incomplete batch every
+    /// [`FLUSH_INTERVAL`]. This must be called within a Tokio runtime.
+    ///
+    /// A failed batch is retried with backoff, then logged and kept for the
+    /// next flush. `destination` names where the items go in log messages.
+    pub fn spawn<F, Fut, E>(destination: String, batch_size: usize, send_batch: F) -> Self
+    where
+        F: Fn(Vec<T>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Error + Send + 'static,
+    {
+        let (items, receiver) = mpsc::unbounded_channel();
+        let batcher = Batcher {
+            destination,
+            batch_size: batch_size.max(1),
+            send_batch,
+        };
+        let task = tokio::spawn(batcher.run(receiver));
+        Self { items, task }
+    }
+
+    /// Buffer `item` for sending, failing if the task stopped.
+    pub fn send(&self, item: T) -> Result<(), HttpError> {
+        self.items.send(item).map_err(|_| HttpError::Stopped)
+    }
+
+    /// Send the buffered items, giving up after `timeout`.
+    pub async fn close(self, timeout: Duration) -> Result<(), HttpError> {
+        drop(self.items);
+        let joined = time::timeout(timeout, self.task)
+            .await
+            .map_err(|_| HttpError::Timeout(timeout))?;
+        if let Err(error) = joined {
+            if error.is_panic() {
+                panic::resume_unwind(error.into_panic());
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Batcher<F> {
+    destination: String,
+    batch_size:  usize,
+    send_batch:  F,
+}
+
+impl<F> Batcher<F> {
+    /// Buffer the received items and send them until the sender is dropped.
+    async fn run<T, Fut, E>(self, mut receiver: UnboundedReceiver<T>)
+    where
+        T: Clone + Send + Sync,
+        F: Fn(Vec<T>) -> Fut + Send + Sync,
+        Fut: Future<Output = Result<(), E>> + Send,
+        E: Error + Send + 'static,
+    {
+        let mut buffer = VecDeque::new();
+        let mut interval = time::interval(FLUSH_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                item = receiver.recv() => {
+                    let Some(item) = item else { break };
+                    if buffer.len() == BUFFER_CAP {
+                        warn!("Buffer of {} full, dropping the oldest item", self.destination);
+                        buffer.pop_front();
+                    }
+                    buffer.push_back(item);
+                    if buffer.len() >= self.batch_size {
+                        self.flush(&mut buffer).await;
+                    }
+                }
+                _ = interval.tick() => self.flush(&mut buffer).await,
+            }
+        }
+        self.flush(&mut buffer).await;
+        if !buffer.is_empty() {
+            warn!(
+                "Dropped {} items that could not be sent to {}",
+                buffer.len(),
+                self.destination
+            );
+        }
+    }
+
+    /// Send the buffered items in batches, keeping those that failed.
+    async fn flush<T, Fut, E>(&self, buffer: &mut VecDeque<T>)
+    where
+        T: Clone + Send + Sync,
+        F: Fn(Vec<T>) -> Fut + Send + Sync,
+        Fut: Future<Output = Result<(), E>> + Send,
+        E: Error + Send + 'static,
+    {
+        while !buffer.is_empty() {
+            let batch = buffer.len().min(self.batch_size);
+            let items = buffer.range(..batch).cloned().collect::<Vec<_>>();
+            if let Err(error) = with_retry(SEND_ATTEMPTS, || (self.send_batch)(items.clone())).await
+            {
+                warn!(
+                    "Failed to send {} items to {}, retrying later: {}",
+                    buffer.len(),
+                    self.destination,
+                    ErrorChain(&error)
+                );
+                return;
+            }
+            buffer.drain(..batch);
+        }
+    }
+}
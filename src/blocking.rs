@@ -0,0 +1,115 @@
+//! Synchronous wrapper of [`AranetClient`], for callers without an async
+//! runtime. Requires the `blocking` feature.
+//!
+//! ```no_run
+//! use cotracker::{blocking::BlockingClient, AranetClientBuilder, Sensor};
+//!
+//! # fn main() -> Result<(), cotracker::AranetError> {
+//! let client = BlockingClient::connect(AranetClientBuilder::new())?;
+//! println!("{}", client.read_once()?);
+//! println!("{:?}", client.history(Sensor::CO2)?.samples);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    history::{History, HistoryResult, TimedSamples},
+    AranetClient, AranetClientBuilder, AranetError, CurrentReading, DeviceInfo, Sensor,
+};
+use btleplug::{api::Peripheral, platform};
+use std::{collections::HashMap, future::Future};
+use tokio::runtime::{self, Runtime};
+
+/// An [`AranetClient`] whose methods block until done.
+///
+/// Holds a Tokio runtime with a single worker thread for the lifetime of the
+/// client, so background tasks of the bluetooth stack keep running between
+/// calls. Do not use it from within an async runtime, blocking there panics.
+#[derive(Debug)]
+pub struct BlockingClient<P: Peripheral = platform::Peripheral> {
+    runtime: Runtime,
+    client:  AranetClient<P>,
+}
+
+impl BlockingClient {
+    /// Find and connect to a device, see [`AranetClientBuilder::connect`].
+    pub fn connect(builder: AranetClientBuilder) -> Result<Self, AranetError> {
+        let runtime = new_runtime()?;
+        let client = runtime.block_on(builder.connect())?;
+        Ok(Self { runtime, client })
+    }
+}
+
+impl<P: Peripheral> BlockingClient<P> {
+    /// Wrap a client created within the runtime of [`Self::block_on`] or one
+    /// that is not bound to a runtime.
+    pub fn new(client: AranetClient<P>) -> Result<Self, AranetError> {
+        Ok(Self {
+            runtime: new_runtime()?,
+            client,
+        })
+    }
+
+    pub const fn client(&self) -> &AranetClient<P> {
+        &self.client
+    }
+
+    /// Run a future on the runtime of the client, for async methods of the
+    /// client not wrapped here.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Read the current reading, connecting first if the device disconnected.
+    pub fn read_once(&self) -> Result<CurrentReading, AranetError> {
+        self.block_on(async {
+            self.client.ensure_connected().await?;
+            self.client.current_reading().await
+        })
+    }
+
+    /// See [`AranetClient::device_info`].
+    pub fn device_info(&self) -> Result<DeviceInfo, AranetError> {
+        self.block_on(self.client.device_info())
+    }
+
+    /// See [`AranetClient::stored_reading_count`].
+    pub fn stored_reading_count(&self) -> Result<u16, AranetError> {
+        self.block_on(self.client.stored_reading_count())
+    }
+
+    /// Download the stored history of a sensor, see
+    /// [`AranetClient::read_history`].
+    pub fn history(&self, sensor: Sensor) -> Result<HistoryResult, AranetError> {
+        self.block_on(self.client.read_history(sensor))
+    }
+
+    /// Download the stored history of a sensor with timestamps, see
+    /// [`AranetClient::read_history_timed`].
+    pub fn history_timed(&self, sensor: Sensor) -> Result<TimedSamples, AranetError> {
+        self.block_on(self.client.read_history_timed(sensor))
+    }
+
+    /// Download the stored history of a sensor with its interval and the time
+    /// of the newest sample, see [`AranetClient::read_history_timeline`].
+    pub fn history_timeline(&self, sensor: Sensor) -> Result<History, AranetError> {
+        self.block_on(self.client.read_history_timeline(sensor))
+    }
+
+    /// See [`AranetClient::read_all_history`].
+    pub fn all_history(&self) -> Result<HashMap<Sensor, HistoryResult>, AranetError> {
+        self.block_on(self.client.read_all_history())
+    }
+
+    /// See [`AranetClient::set_interval`].
+    pub fn set_interval(&self, seconds: u16) -> Result<(), AranetError> {
+        self.block_on(self.client.set_interval(seconds))
+    }
+}
+
+fn new_runtime() -> Result<Runtime, AranetError> {
+    Ok(runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()?)
+}
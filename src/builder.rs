@@ -0,0 +1,162 @@
+//! Connecting to a device in one call.
+
+use crate::{
+    discovery::{scan, scan_filter, select_adapter, DiscoveredDevice, DEFAULT_NAME_PREFIX},
+    AranetClient, AranetError,
+};
+use btleplug::{
+    api::{BDAddr, Central, Peripheral, PeripheralProperties},
+    platform::{self, Manager},
+};
+use std::time::Duration;
+
+/// Finds and connects to a device, doing the adapter selection, scanning,
+/// connecting and service discovery the binary does.
+///
+/// ```no_run
+/// use cotracker::AranetClientBuilder;
+/// use std::time::Duration;
+///
+/// # async fn example() -> Result<(), cotracker::AranetError> {
+/// let client = AranetClientBuilder::new()
+///     .adapter("hci0")
+///     .name_prefix("Aranet4")
+///     .scan_timeout(Duration::from_secs(5))
+///     .connect()
+///     .await?;
+/// println!("{:?}", client.current_reading().await);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct AranetClientBuilder {
+    adapter:         Option<String>,
+    address:         Option<BDAddr>,
+    name_prefix:     String,
+    scan_timeout:    Duration,
+    connect_timeout: Duration,
+    timeout:         Duration,
+}
+
+impl Default for AranetClientBuilder {
+    fn default() -> Self {
+        Self {
+            adapter:         None,
+            address:         None,
+            name_prefix:     DEFAULT_NAME_PREFIX.to_owned(),
+            scan_timeout:    Self::DEFAULT_SCAN_TIMEOUT,
+            connect_timeout: AranetClient::<platform::Peripheral>::DEFAULT_CONNECT_TIMEOUT,
+            timeout:         AranetClient::<platform::Peripheral>::DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl AranetClientBuilder {
+    /// Default for [`Self::scan_timeout`].
+    pub const DEFAULT_SCAN_TIMEOUT: Duration = Duration::from_secs(2);
+
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index, name or address of the bluetooth adapter, see
+    /// [`select_adapter`](crate::discovery::select_adapter). Defaults to the
+    /// first adapter.
+    #[must_use]
+    pub fn adapter(mut self, adapter: impl Into<String>) -> Self {
+        self.adapter = Some(adapter.into());
+        self
+    }
+
+    /// Connect to the device with this address, regardless of its name.
+    #[must_use]
+    pub const fn address(mut self, address: BDAddr) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// Connect to the first device whose advertised name starts with this,
+    /// see [`DiscoveredDevice::from_properties`]. Ignored if an
+    /// [address](Self::address) is set.
+    #[must_use]
+    pub fn name_prefix(mut self, name_prefix: impl Into<String>) -> Self {
+        self.name_prefix = name_prefix.into();
+        self
+    }
+
+    /// How long to scan for the device.
+    #[must_use]
+    pub const fn scan_timeout(mut self, scan_timeout: Duration) -> Self {
+        self.scan_timeout = scan_timeout;
+        self
+    }
+
+    /// See [`AranetClient::with_connect_timeout`].
+    #[must_use]
+    pub const fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    /// See [`AranetClient::with_timeout`].
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Whether the scanned peripheral is the device to connect to.
+    fn matches(&self, props: &PeripheralProperties) -> bool {
+        self.address.map_or_else(
+            || DiscoveredDevice::from_properties(props, &self.name_prefix).is_some(),
+            |address| props.address == address,
+        )
+    }
+
+    /// Find the device, connect to it and detect its device type.
+    ///
+    /// Failures after the device was found are wrapped in
+    /// [`AranetError::Connect`].
+    pub async fn connect(self) -> Result<AranetClient<platform::Peripheral>, AranetError> {
+        let manager = Manager::new().await?;
+        let central = select_adapter(&manager, self.adapter.as_deref()).await?;
+
+        let not_found = || AranetError::DeviceNotFound {
+            address:     self.address,
+            name_prefix: Some(self.name_prefix.clone()),
+        };
+        let found = scan(&central, scan_filter(), self.scan_timeout, |props| {
+            self.matches(props)
+        })
+        .await?;
+        if !found {
+            return Err(not_found());
+        }
+        let mut p = None;
+        for peripheral in central.peripherals().await? {
+            let props = peripheral.properties().await?;
+            if props.is_some_and(|props| self.matches(&props)) {
+                p = Some(peripheral);
+                break;
+            }
+        }
+        let p = p.ok_or_else(not_found)?;
+        // Stopping the scan is an optimization, don't fail the connect over it.
+        central.stop_scan().await.ok();
+
+        let address = p.address();
+        let connect = async {
+            let client = AranetClient::new(p)
+                .with_timeout(self.timeout)
+                .with_connect_timeout(self.connect_timeout);
+            client.ensure_connected().await?;
+            let device_type = client.read_device_type().await?.unwrap_or_default();
+            Ok(client.with_device_type(device_type))
+        };
+        connect.await.map_err(|error| AranetError::Connect {
+            address,
+            source: Box::new(error),
+        })
+    }
+}
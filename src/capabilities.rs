@@ -0,0 +1,79 @@
+use crate::characteristics::{
+    COMMAND, CURRENT_READING_FULL, CURRENT_READING_NOTIFY, HISTORY_NOTIFIER, STORED_READINGS,
+};
+use btleplug::api::{CharPropFlags, Characteristic, Peripheral};
+use serde::Serialize;
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Formatter},
+};
+
+/// Features supported by a device, as detected from its discovered
+/// characteristics.
+///
+/// Older firmware lacks some characteristics, checking these first gives a
+/// clear error instead of a failing GATT write.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Capabilities {
+    /// Downloading the stored history.
+    pub history:        bool,
+    /// Forcing a CO2 calibration.
+    pub calibration:    bool,
+    /// Notifications of the current reading.
+    pub notify_current: bool,
+    /// Setting the measurement interval.
+    pub set_interval:   bool,
+}
+
+impl Capabilities {
+    /// Detect the capabilities of a peripheral. Services must be discovered
+    /// first, otherwise nothing is supported.
+    #[must_use]
+    pub fn detect<P: Peripheral>(peripheral: &P) -> Self {
+        Self::from_characteristics(&peripheral.characteristics())
+    }
+
+    #[must_use]
+    pub fn from_characteristics(characteristics: &BTreeSet<Characteristic>) -> Self {
+        let has = |expected: &Characteristic, properties: CharPropFlags| {
+            characteristics.iter().any(|characteristic| {
+                characteristic.uuid == expected.uuid
+                    && characteristic.properties.intersects(properties)
+            })
+        };
+        let command = has(
+            &COMMAND,
+            CharPropFlags::WRITE | CharPropFlags::WRITE_WITHOUT_RESPONSE,
+        );
+        Self {
+            history:        has(&STORED_READINGS, CharPropFlags::READ)
+                && has(&HISTORY_NOTIFIER, CharPropFlags::NOTIFY)
+                && command,
+            // Only the Aranet4 has a CO2 sensor to calibrate.
+            calibration:    command && has(&CURRENT_READING_FULL, CharPropFlags::READ),
+            notify_current: has(&CURRENT_READING_NOTIFY, CharPropFlags::NOTIFY),
+            set_interval:   command,
+        }
+    }
+}
+
+/// Lists the supported features, such as `history, set-interval`.
+impl Display for Capabilities {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let features = [
+            (self.history, "history"),
+            (self.calibration, "calibration"),
+            (self.notify_current, "notify"),
+            (self.set_interval, "set-interval"),
+        ]
+        .into_iter()
+        .filter_map(|(supported, name)| supported.then_some(name))
+        .collect::<Vec<_>>();
+        if features.is_empty() {
+            f.write_str("none")
+        } else {
+            f.write_str(&features.join(", "))
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! GATT characteristics exposed by Aranet devices.
+
+use btleplug::api::{CharPropFlags, Characteristic};
+use uuid::{uuid, Uuid};
+
+// Aranet BLE uuids.
+// See <https://github.com/Anrijs/Aranet4-Python/blob/master/docs/UUIDs.md>
+// See <https://github.com/stijnstijn/pyaranet4/blob/f144d504434aa0d597c4694f659244561c225e3c/pyaranet4/pyaranet4.py#L32>
+pub const ARANET4_SERVICE: Uuid = uuid!("f0cd1400-95da-4f4b-9ac8-aa55d312af0c");
+/// 16-bit service uuid assigned to SAF Tehnika, advertised by newer firmware
+/// instead of [`ARANET4_SERVICE`].
+pub const SAF_TEHNIKA_SERVICE: Uuid = uuid!("0000fce0-0000-1000-8000-00805f9b34fb");
+const BLUETOOTH_SERVICE: Uuid = uuid!("0000180a-0000-1000-8000-00805f9b34fb");
+const GENERIC_ACCESS_SERVICE: Uuid = uuid!("00001800-0000-1000-8000-00805f9b34fb");
+const BATTERY_SERVICE: Uuid = uuid!("0000180f-0000-1000-8000-00805f9b34fb");
+const CURRENT_TIME_SERVICE: Uuid = uuid!("00001805-0000-1000-8000-00805f9b34fb");
+
+pub const DEVICE_NAME: Characteristic = Characteristic {
+    service_uuid: GENERIC_ACCESS_SERVICE,
+    uuid:         uuid!("00002a00-0000-1000-8000-00805f9b34fb"),
+    properties:   CharPropFlags::READ,
+};
+
+pub const MODEL_NUMBER: Characteristic = Characteristic {
+    service_uuid: BLUETOOTH_SERVICE,
+    uuid:         uuid!("00002a24-0000-1000-8000-00805f9b34fb"),
+    properties:   CharPropFlags::READ,
+};
+
+pub const MANUFACTURER_NAME: Characteristic = Characteristic {
+    service_uuid: BLUETOOTH_SERVICE,
+    uuid:         uuid!("00002a29-0000-1000-8000-00805f9b34fb"),
+    properties:   CharPropFlags::READ,
+};
+
+pub const SERIAL_NUMBER: Characteristic = Characteristic {
+    service_uuid: BLUETOOTH_SERVICE,
+    uuid:         uuid!("00002a25-0000-1000-8000-00805f9b34fb"),
+    properties:   CharPropFlags::READ,
+};
+
+pub const FIRMWARE_REVISION: Characteristic = Characteristic {
+    service_uuid: BLUETOOTH_SERVICE,
+    uuid:         uuid!("00002a26-0000-1000-8000-00805f9b34fb"),
+    properties:   CharPropFlags::READ,
+};
+
+/// Battery charge in % as a single byte, from the standard BLE Battery
+/// Service.
+pub const BATTERY_LEVEL: Characteristic = Characteristic {
+    service_uuid: BATTERY_SERVICE,
+    uuid:         uuid!("00002a19-0000-1000-8000-00805f9b34fb"),
+    properties:   CharPropFlags::READ,
+};
+
+/// Clock of the device in local time from the standard BLE Current Time
+/// Service, see [`parse_current_time`](crate::parse_current_time). Only some
+/// firmware has it.
+pub const CURRENT_TIME: Characteristic = Characteristic {
+    service_uuid: CURRENT_TIME_SERVICE,
+    uuid:         uuid!("00002a2b-0000-1000-8000-00805f9b34fb"),
+    properties:   CharPropFlags::READ,
+};
+
+pub const CURRENT_READING_FULL: Characteristic = Characteristic {
+    service_uuid: ARANET4_SERVICE,
+    uuid:         uuid!("f0cd3001-95da-4f4b-9ac8-aa55d312af0c"),
+    properties:   CharPropFlags::READ,
+};
+
+/// Shorter current reading of the Aranet4 used by some firmware, with only the
+/// CO2 concentration, temperature, pressure and humidity.
+pub const CURRENT_READING: Characteristic = Characteristic {
+    service_uuid: ARANET4_SERVICE,
+    uuid:         uuid!("f0cd1503-95da-4f4b-9ac8-aa55d312af0c"),
+    properties:   CharPropFlags::READ,
+};
+
+/// Measurement interval in seconds as a `u16`.
+pub const INTERVAL: Characteristic = Characteristic {
+    service_uuid: ARANET4_SERVICE,
+    uuid:         uuid!("f0cd2002-95da-4f4b-9ac8-aa55d312af0c"),
+    properties:   CharPropFlags::READ,
+};
+
+/// Seconds since the last measurement as a `u16`.
+pub const SECONDS_SINCE_UPDATE: Characteristic = Characteristic {
+    service_uuid: ARANET4_SERVICE,
+    uuid:         uuid!("f0cd2004-95da-4f4b-9ac8-aa55d312af0c"),
+    properties:   CharPropFlags::READ,
+};
+
+/// [`CURRENT_READING_FULL`] as subscribed to for notifications when the
+/// reading changes. Older firmware does not support notifications on it.
+pub const CURRENT_READING_NOTIFY: Characteristic = Characteristic {
+    service_uuid: ARANET4_SERVICE,
+    uuid:         uuid!("f0cd3001-95da-4f4b-9ac8-aa55d312af0c"),
+    properties:   CharPropFlags::READ.union(CharPropFlags::NOTIFY),
+};
+
+/// Current reading of the Aranet2, which lacks the CO2 and pressure sensors.
+///
+/// The Aranet Radon Plus and Aranet Radiation use the same characteristic,
+/// each with its own layout, see [`radiation`](crate::radiation).
+pub const CURRENT_READING_ARANET2: Characteristic = Characteristic {
+    service_uuid: ARANET4_SERVICE,
+    uuid:         uuid!("f0cd3003-95da-4f4b-9ac8-aa55d312af0c"),
+    properties:   CharPropFlags::READ,
+};
+
+pub const STORED_READINGS: Characteristic = Characteristic {
+    service_uuid: ARANET4_SERVICE,
+    uuid:         uuid!("f0cd2001-95da-4f4b-9ac8-aa55d312af0c"),
+    properties:   CharPropFlags::READ,
+};
+
+pub const HISTORY_RANGE: Characteristic = Characteristic {
+    service_uuid: ARANET4_SERVICE,
+    uuid:         uuid!("f0cd1402-95da-4f4b-9ac8-aa55d312af0c"),
+    properties:   CharPropFlags::READ,
+};
+
+/// Accepts command packets such as setting the measurement interval. This is
+/// the same characteristic as [`HISTORY_RANGE`], history range requests are
+/// commands too.
+pub const COMMAND: Characteristic = Characteristic {
+    service_uuid: ARANET4_SERVICE,
+    uuid:         uuid!("f0cd1402-95da-4f4b-9ac8-aa55d312af0c"),
+    properties:   CharPropFlags::READ.union(CharPropFlags::WRITE),
+};
+
+pub const HISTORY_NOTIFIER: Characteristic = Characteristic {
+    service_uuid: ARANET4_SERVICE,
+    uuid:         uuid!("f0cd2003-95da-4f4b-9ac8-aa55d312af0c"),
+    properties:   CharPropFlags::READ.union(CharPropFlags::NOTIFY),
+};
@@ -0,0 +1,841 @@
+#[allow(clippy::wildcard_imports)]
+use crate::{
+    characteristics::*,
+    discovery::connect,
+    history::{
+        aranet_notifications, sample_index, History, HistoryChunk, HistoryChunks, HistoryDownload,
+        HistoryRange, HistoryResult, TimedSamples,
+    },
+    parse_current_reading, parse_current_time, parse_short_reading,
+    radiation::{parse_radiation_reading, parse_radon_reading, RadiationReading, RadonReading},
+    read_with_retry,
+    retry::with_retry,
+    AranetError, Capabilities, CurrentReading, DeviceInfo, DeviceType, PairingRequired, ParseError,
+    Sensor, CLOCK_DRIFT_WARNING, READ_ATTEMPTS,
+};
+use btleplug::api::{CharPropFlags, Characteristic, Peripheral, WriteType};
+use bytes::{Buf, BufMut};
+use chrono::{DateTime, Utc};
+use futures::{
+    future,
+    stream::{self, BoxStream, Stream, StreamExt},
+};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    slice,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time::{self, MissedTickBehavior};
+use tracing::{debug, trace, warn};
+
+type Progress = Arc<dyn Fn(&HistoryDownload) + Send + Sync>;
+
+/// Client for a connected Aranet4 device.
+///
+/// The peripheral must be connected and have its services discovered.
+#[derive(Clone)]
+pub struct AranetClient<P: Peripheral> {
+    peripheral:      P,
+    device_type:     DeviceType,
+    timeout:         Duration,
+    connect_timeout: Duration,
+    progress:        Option<Progress>,
+}
+
+impl<P: Peripheral> Debug for AranetClient<P> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AranetClient")
+            .field("peripheral", &self.peripheral)
+            .field("device_type", &self.device_type)
+            .field("timeout", &self.timeout)
+            .field("connect_timeout", &self.connect_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P: Peripheral> AranetClient<P> {
+    /// Number of times samples lost in transit are requested again.
+    pub const GAP_ATTEMPTS: usize = 3;
+
+    /// Number of attempts made to subscribe to history notifications and to
+    /// write a history request.
+    pub const SETUP_ATTEMPTS: u32 = 3;
+
+    /// Number of times a history request is written when no notification
+    /// arrives within the timeout, as some devices only start sending on a
+    /// repeated request.
+    pub const START_ATTEMPTS: usize = 3;
+
+    /// Default for [`Self::with_timeout`].
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Default for [`Self::with_connect_timeout`].
+    pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+    /// Wrap `peripheral` as an Aranet4 without talking to it, see
+    /// [`Self::from_peripheral`] to detect the device type.
+    pub const fn new(peripheral: P) -> Self {
+        Self {
+            peripheral,
+            device_type: DeviceType::Aranet4,
+            timeout: Self::DEFAULT_TIMEOUT,
+            connect_timeout: Self::DEFAULT_CONNECT_TIMEOUT,
+            progress: None,
+        }
+    }
+
+    /// Use a peripheral connected by the caller, for applications that do
+    /// their own scanning and connecting, and detect its device type.
+    ///
+    /// `peripheral` must be connected and have its services discovered, as
+    /// [`Self::capabilities`] and the reads depend on them. This does not
+    /// connect, it fails with [`btleplug::Error::NotConnected`] if the
+    /// peripheral is disconnected. Only [`Self::ensure_connected`] connects
+    /// again later.
+    pub async fn from_peripheral(peripheral: P) -> Result<Self, AranetError> {
+        if !peripheral.is_connected().await? {
+            return Err(btleplug::Error::NotConnected.into());
+        }
+        let client = Self::new(peripheral);
+        let device_type = client.read_device_type().await?.unwrap_or_default();
+        Ok(client.with_device_type(device_type))
+    }
+
+    /// Set the type of the device, see [`Self::read_device_type`]. Defaults to
+    /// [`DeviceType::Aranet4`].
+    #[must_use]
+    pub const fn with_device_type(mut self, device_type: DeviceType) -> Self {
+        self.device_type = device_type;
+        self
+    }
+
+    pub const fn device_type(&self) -> DeviceType {
+        self.device_type
+    }
+
+    /// Set how long to wait for the next history notification before giving
+    /// up on a download.
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set how long [`Self::ensure_connected`] waits for the connection.
+    #[must_use]
+    pub const fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub const fn connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    /// Call `progress` with the state of a history download whenever a
+    /// notification with samples arrives, for example to show a progress bar.
+    #[must_use]
+    pub fn with_progress(
+        mut self,
+        progress: impl Fn(&HistoryDownload) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Use `peripheral` instead, keeping all settings. Use this after
+    /// reconnecting to a device that was found again.
+    #[must_use]
+    pub fn with_peripheral(mut self, peripheral: P) -> Self {
+        self.peripheral = peripheral;
+        self
+    }
+
+    pub const fn peripheral(&self) -> &P {
+        &self.peripheral
+    }
+
+    async fn read_string(&self, characteristic: &Characteristic) -> Result<String, AranetError> {
+        let data = read_with_retry(&self.peripheral, characteristic, READ_ATTEMPTS).await?;
+        Ok(String::from_utf8_lossy(&data).into_owned())
+    }
+
+    pub async fn serial_number(&self) -> Result<String, AranetError> {
+        self.read_string(&SERIAL_NUMBER).await
+    }
+
+    /// Firmware version string, such as `v1.4.19`.
+    pub async fn firmware_version(&self) -> Result<String, AranetError> {
+        self.read_string(&FIRMWARE_REVISION).await
+    }
+
+    /// Read the identification, state and clock of the device, warning when
+    /// the clock is off by more than [`CLOCK_DRIFT_WARNING`].
+    ///
+    /// The device type is detected from the model, so this works on a client
+    /// created without [`Self::with_device_type`].
+    pub async fn device_info(&self) -> Result<DeviceInfo, AranetError> {
+        let clock = self.device_clock().await?;
+        if let Some(clock) = clock {
+            let drift = (clock - Utc::now()).abs();
+            if drift.to_std().unwrap_or_default() > CLOCK_DRIFT_WARNING {
+                warn!(
+                    "Clock of {} is off by {}s, set it in the Aranet app",
+                    self.peripheral.address(),
+                    drift.num_seconds()
+                );
+            }
+        }
+        let model = self.read_string(&MODEL_NUMBER).await?;
+        let device_type = DeviceType::from_name(&model).unwrap_or(self.device_type);
+        let reading = self
+            .clone()
+            .with_device_type(device_type)
+            .current_reading()
+            .await?;
+        Ok(DeviceInfo {
+            name: self.read_string(&DEVICE_NAME).await?,
+            model,
+            manufacturer: self.read_string(&MANUFACTURER_NAME).await?,
+            serial: self.serial_number().await?,
+            firmware: self.firmware_version().await?,
+            battery: reading.battery_percent(),
+            interval: reading.interval,
+            stored: self.stored_reading_count().await?,
+            capabilities: self.capabilities(),
+            clock,
+        })
+    }
+
+    /// Read the clock of the device, `None` if it has no [`CURRENT_TIME`]
+    /// characteristic or the clock is not set.
+    pub async fn device_clock(&self) -> Result<Option<DateTime<Utc>>, AranetError> {
+        if !self
+            .peripheral
+            .characteristics()
+            .iter()
+            .any(|characteristic| characteristic.uuid == CURRENT_TIME.uuid)
+        {
+            return Ok(None);
+        }
+        let data = read_with_retry(&self.peripheral, &CURRENT_TIME, READ_ATTEMPTS).await?;
+        Ok(parse_current_time(&mut &data[..])?)
+    }
+
+    /// Detect the type of the device from its model number.
+    pub async fn read_device_type(&self) -> Result<Option<DeviceType>, AranetError> {
+        let model = self.read_string(&MODEL_NUMBER).await?;
+        Ok(DeviceType::from_name(&model))
+    }
+
+    /// Read the current reading.
+    ///
+    /// Aranet4 firmware without [`CURRENT_READING_FULL`] but with the shorter
+    /// [`CURRENT_READING`] is detected from the discovered services. The fields
+    /// missing from that are then read from the [`BATTERY_LEVEL`],
+    /// [`INTERVAL`] and [`SECONDS_SINCE_UPDATE`] characteristics, the status is
+    /// unknown.
+    pub async fn current_reading(&self) -> Result<CurrentReading, AranetError> {
+        let characteristic = match self.device_type {
+            DeviceType::Aranet4 if self.has_short_reading_only() => {
+                return self.short_reading().await;
+            }
+            DeviceType::Aranet4 => &CURRENT_READING_FULL,
+            DeviceType::Aranet2 | DeviceType::AranetRadon | DeviceType::AranetRadiation => {
+                &CURRENT_READING_ARANET2
+            }
+        };
+        let data = read_with_retry(&self.peripheral, characteristic, READ_ATTEMPTS).await?;
+        parse_current_reading(self.device_type, &mut &data[..])
+    }
+
+    /// Read the current reading of an Aranet Radon Plus, including the radon
+    /// concentration missing from [`Self::current_reading`].
+    pub async fn radon_reading(&self) -> Result<RadonReading, AranetError> {
+        let data = self.read_specialized(DeviceType::AranetRadon).await?;
+        Ok(parse_radon_reading(&mut &data[..])?)
+    }
+
+    /// Read the current reading of an Aranet Radiation.
+    pub async fn radiation_reading(&self) -> Result<RadiationReading, AranetError> {
+        let data = self.read_specialized(DeviceType::AranetRadiation).await?;
+        Ok(parse_radiation_reading(&mut &data[..])?)
+    }
+
+    async fn read_specialized(&self, expected: DeviceType) -> Result<Vec<u8>, AranetError> {
+        if self.device_type != expected {
+            return Err(AranetError::Unsupported(format!(
+                "{} is not an {expected}",
+                self.device_type
+            )));
+        }
+        Ok(read_with_retry(&self.peripheral, &CURRENT_READING_ARANET2, READ_ATTEMPTS).await?)
+    }
+
+    /// Whether the device has [`CURRENT_READING`] but not
+    /// [`CURRENT_READING_FULL`].
+    fn has_short_reading_only(&self) -> bool {
+        let characteristics = self.peripheral.characteristics();
+        let has = |expected: &Characteristic| {
+            characteristics
+                .iter()
+                .any(|characteristic| characteristic.uuid == expected.uuid)
+        };
+        has(&CURRENT_READING) && !has(&CURRENT_READING_FULL)
+    }
+
+    async fn short_reading(&self) -> Result<CurrentReading, AranetError> {
+        let data = read_with_retry(&self.peripheral, &CURRENT_READING, READ_ATTEMPTS).await?;
+        let mut reading = parse_short_reading(&mut &data[..])?;
+        reading.interval = self.read_u16(&INTERVAL).await?;
+        reading.age = self.read_u16(&SECONDS_SINCE_UPDATE).await?;
+        reading.battery = match self.battery_level().await {
+            Ok(battery) => battery,
+            Err(error) => {
+                warn!("Failed to read the battery level: {error}");
+                0
+            }
+        };
+        Ok(reading)
+    }
+
+    async fn read_u16(&self, characteristic: &Characteristic) -> Result<u16, AranetError> {
+        let data = read_with_retry(&self.peripheral, characteristic, READ_ATTEMPTS).await?;
+        let mut reader = &data[..];
+        ParseError::ensure_remaining(&reader, 2)?;
+        Ok(reader.get_u16_le())
+    }
+
+    /// Battery charge in % from the standard Battery Service, an alternative
+    /// to [`CurrentReading::battery`].
+    pub async fn battery_level(&self) -> Result<u8, AranetError> {
+        let data = read_with_retry(&self.peripheral, &BATTERY_LEVEL, READ_ATTEMPTS).await?;
+        let Some(&level) = data.first() else {
+            return Err(ParseError {
+                expected:  1,
+                remaining: 0,
+            }
+            .into());
+        };
+        Ok(level.min(100))
+    }
+
+    /// See [`PairingRequired::check`].
+    fn pairing(&self, error: btleplug::Error) -> AranetError {
+        PairingRequired::check(self.peripheral.address(), error)
+    }
+
+    /// Connect, again if the device disconnected, and discover its services.
+    pub async fn ensure_connected(&self) -> Result<(), AranetError> {
+        if !self.peripheral.is_connected().await? {
+            connect(&self.peripheral, self.connect_timeout).await?;
+        }
+        Ok(())
+    }
+
+    /// Stream of current readings, one every `period` starting immediately.
+    ///
+    /// When a read fails the device is reconnected if needed and the read
+    /// retried once. If that fails too the error is yielded, but the stream
+    /// continues with the next period.
+    pub fn reading_stream(
+        &self,
+        period: Duration,
+    ) -> impl Stream<Item = Result<CurrentReading, AranetError>> + Send + '_ {
+        let mut interval = time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        stream::unfold(interval, move |mut interval| async move {
+            interval.tick().await;
+            let result = match self.current_reading().await {
+                Ok(reading) => Ok(reading),
+                Err(_) => match self.ensure_connected().await {
+                    Ok(()) => self.current_reading().await,
+                    Err(error) => Err(error),
+                },
+            };
+            Some((result, interval))
+        })
+    }
+
+    /// Features supported by the device, detected from the discovered services.
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities::detect(&self.peripheral)
+    }
+
+    /// Stream of current readings as the device notifies them.
+    ///
+    /// Falls back to [`Self::reading_stream`] with `poll_period` if the device
+    /// does not support notifications on [`CURRENT_READING_NOTIFY`], such as
+    /// the Aranet2 and older firmware. Services must be discovered to detect
+    /// support.
+    pub async fn subscribe_current(
+        &self,
+        poll_period: Duration,
+    ) -> Result<BoxStream<'_, Result<CurrentReading, AranetError>>, AranetError> {
+        if self.device_type != DeviceType::Aranet4 || !self.capabilities().notify_current {
+            return Ok(self.reading_stream(poll_period).boxed());
+        }
+        self.peripheral.subscribe(&CURRENT_READING_NOTIFY).await?;
+        let notifications = self.peripheral.notifications().await?;
+        let device_type = self.device_type;
+        Ok(notifications
+            .filter(|notification| future::ready(notification.uuid == CURRENT_READING_NOTIFY.uuid))
+            .map(move |notification| {
+                parse_current_reading(device_type, &mut &notification.value[..])
+            })
+            .boxed())
+    }
+
+    /// Download the stored history of a sensor, oldest sample first.
+    ///
+    /// Samples that could not be downloaded are NaN and listed in
+    /// [`HistoryResult::missing`].
+    pub async fn read_history(&self, sensor: Sensor) -> Result<HistoryResult, AranetError> {
+        let mut download = self.begin_history(sensor).await?;
+        self.continue_history(&mut download).await?;
+        Ok(download.into_result())
+    }
+
+    /// Download the history samples after one-based index `last_index`,
+    /// oldest sample first.
+    ///
+    /// Indices shift once the history is full, use
+    /// [`Self::read_history_after`] for incremental syncs.
+    pub async fn read_history_since(
+        &self,
+        sensor: Sensor,
+        last_index: u16,
+    ) -> Result<HistoryResult, AranetError> {
+        let mut download = self
+            .begin_history_at(sensor, last_index.saturating_add(1))
+            .await?;
+        self.continue_history(&mut download).await?;
+        Ok(download.into_result())
+    }
+
+    /// Download the history samples measured after the sample measured at
+    /// `last_timestamp`, oldest sample first.
+    ///
+    /// Use this for incremental syncs, passing the time of the last sample
+    /// previously downloaded. Where to resume is found from the current
+    /// reading, see [`sample_index`]. If that sample is no longer stored, all
+    /// stored samples are downloaded.
+    pub async fn read_history_after(
+        &self,
+        sensor: Sensor,
+        last_timestamp: DateTime<Utc>,
+    ) -> Result<HistoryResult, AranetError> {
+        let reading = self.current_reading().await?;
+        let newest = Utc::now() - chrono::Duration::seconds(reading.age.into());
+        let interval = chrono::Duration::seconds(reading.interval.into());
+        let stored = self.stored_reading_count().await?;
+        let last_index = sample_index(newest, interval, last_timestamp, stored).unwrap_or(0);
+        self.read_history_since(sensor, last_index).await
+    }
+
+    /// Set the measurement interval. Supported intervals are 1, 2, 5 and 10
+    /// minutes.
+    ///
+    /// Fails without writing if the device does not support it, see
+    /// [`Capabilities::set_interval`].
+    pub async fn set_interval(&self, seconds: u16) -> Result<(), AranetError> {
+        let minutes = match seconds {
+            60 => 1,
+            120 => 2,
+            300 => 5,
+            600 => 10,
+            _ => {
+                return Err(AranetError::Unsupported(format!(
+                    "unsupported interval {seconds}s, must be one of 60, 120, 300 or 600"
+                )))
+            }
+        };
+        if !self.capabilities().set_interval {
+            return Err(AranetError::Unsupported(
+                "device does not support setting the measurement interval".to_owned(),
+            ));
+        }
+        // 90 <minutes>
+        let mut data = [0_u8; 2];
+        let mut writer = &mut data[..];
+        writer.put_u8(0x90); // set interval
+        writer.put_u8(minutes);
+        self.peripheral
+            .write(&COMMAND, &data, WriteType::WithResponse)
+            .await
+            .map_err(|error| self.pairing(error))?;
+        Ok(())
+    }
+
+    /// Force a CO2 calibration, taking the current concentration as the fresh
+    /// air baseline of about 400 ppm.
+    ///
+    /// This overwrites the calibration of the device. Only do this after the
+    /// device has been outdoors or next to an open window for several minutes.
+    /// Fails without writing if the device does not support it, see
+    /// [`Capabilities::calibration`].
+    pub async fn calibrate(&self) -> Result<(), AranetError> {
+        if !self.capabilities().calibration {
+            return Err(AranetError::Unsupported(
+                "device does not support CO2 calibration".to_owned(),
+            ));
+        }
+        // 94 01
+        let mut data = [0_u8; 2];
+        let mut writer = &mut data[..];
+        writer.put_u8(0x94); // calibrate
+        writer.put_u8(0x01); // co2
+        self.peripheral
+            .write(&COMMAND, &data, WriteType::WithResponse)
+            .await
+            .map_err(|error| self.pairing(error))?;
+        Ok(())
+    }
+
+    /// Start a history download by reading the number of stored samples.
+    ///
+    /// Fails if the device does not have this sensor.
+    pub async fn begin_history(&self, sensor: Sensor) -> Result<HistoryDownload, AranetError> {
+        self.begin_history_at(sensor, 1).await
+    }
+
+    /// Start a history download from one-based index `first_index`.
+    ///
+    /// Fails if the device does not have this sensor.
+    pub async fn begin_history_at(
+        &self,
+        sensor: Sensor,
+        first_index: u16,
+    ) -> Result<HistoryDownload, AranetError> {
+        if !self.device_type.supports(sensor) {
+            return Err(AranetError::Unsupported(format!(
+                "{} has no {} sensor",
+                self.device_type,
+                sensor.name()
+            )));
+        }
+        let num_samples = self.stored_reading_count().await?;
+        Ok(HistoryDownload::starting_at(
+            sensor,
+            num_samples,
+            first_index,
+        ))
+    }
+
+    /// Number of samples stored for each sensor, which is how many a full
+    /// history download of a sensor fetches.
+    pub async fn stored_reading_count(&self) -> Result<u16, AranetError> {
+        // This will trigger a pairing request.
+        let data = read_with_retry(&self.peripheral, &STORED_READINGS, READ_ATTEMPTS)
+            .await
+            .map_err(|error| self.pairing(error))?;
+        let mut reader = &data[..];
+        ParseError::ensure_remaining(&reader, 2)?;
+        let count = reader.get_u16_le();
+        debug!(count, "stored readings");
+        Ok(count)
+    }
+
+    /// Read back the last history request the device received, see
+    /// [`HistoryRange`], limited to the samples it stores.
+    ///
+    /// This is the range the device sends, requests such as `1..=u16::MAX`
+    /// are returned with the index of the newest sample as end.
+    pub async fn read_history_range(&self) -> Result<HistoryRange, AranetError> {
+        let data = read_with_retry(&self.peripheral, &HISTORY_RANGE, READ_ATTEMPTS).await?;
+        let range = HistoryRange::parse(&data)?;
+        Ok(range.clamp(self.stored_reading_count().await?))
+    }
+
+    /// Download the remaining samples of a history download.
+    ///
+    /// Once the device has sent all samples, ranges lost in transit are
+    /// requested again up to [`Self::GAP_ATTEMPTS`] times. Samples still
+    /// missing after that are reported by [`HistoryDownload::missing`].
+    ///
+    /// If this fails, for example because the device disconnected or no
+    /// notification arrived within the timeout, it can be called again,
+    /// possibly on a new connection, to resume from the last received sample.
+    /// Samples received so far are kept in `download`.
+    pub async fn continue_history(
+        &self,
+        download: &mut HistoryDownload,
+    ) -> Result<(), AranetError> {
+        if download.is_complete() {
+            return Ok(());
+        }
+        let mut notifications = self.history_notifications().await?;
+        self.download(&mut notifications, slice::from_mut(download), 0)
+            .await
+    }
+
+    /// Download the stored history of all sensors of the device, oldest
+    /// sample first.
+    ///
+    /// Unlike calling [`Self::read_history`] for each sensor, this reads the
+    /// number of stored samples and subscribes to notifications only once.
+    pub async fn read_all_history(&self) -> Result<HashMap<Sensor, HistoryResult>, AranetError> {
+        self.read_history_of(&Sensor::ALL).await
+    }
+
+    /// Download the stored history of `sensors` like
+    /// [`Self::read_all_history`]. Sensors the device does not have are
+    /// skipped.
+    pub async fn read_history_of(
+        &self,
+        sensors: &[Sensor],
+    ) -> Result<HashMap<Sensor, HistoryResult>, AranetError> {
+        let mut downloads = self.begin_history_of(sensors).await?;
+        self.continue_history_of(&mut downloads).await?;
+        Ok(downloads
+            .into_iter()
+            .map(|download| (download.sensor(), download.into_result()))
+            .collect())
+    }
+
+    /// Start history downloads of `sensors` by reading the number of stored
+    /// samples once. Sensors the device does not have are skipped.
+    pub async fn begin_history_of(
+        &self,
+        sensors: &[Sensor],
+    ) -> Result<Vec<HistoryDownload>, AranetError> {
+        let num_samples = self.stored_reading_count().await?;
+        Ok(Sensor::ALL
+            .into_iter()
+            .filter(|sensor| sensors.contains(sensor))
+            .filter(|&sensor| self.device_type.supports(sensor))
+            .map(|sensor| HistoryDownload::new(sensor, num_samples))
+            .collect())
+    }
+
+    /// Download the remaining samples of `downloads`, subscribing to
+    /// notifications only once.
+    ///
+    /// Like [`Self::continue_history`] this can be called again after a
+    /// failure to resume each download from its last received sample.
+    pub async fn continue_history_of(
+        &self,
+        downloads: &mut [HistoryDownload],
+    ) -> Result<(), AranetError> {
+        if downloads.iter().all(HistoryDownload::is_complete) {
+            return Ok(());
+        }
+        let mut notifications = self.history_notifications().await?;
+        for i in 0..downloads.len() {
+            if !downloads[i].is_complete() {
+                self.download(&mut notifications, downloads, i).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Subscribe to [`HISTORY_NOTIFIER`], retrying transient failures, and
+    /// return the history chunks the device sends, see
+    /// [`aranet_notifications`].
+    ///
+    /// The device only sends chunks after a history request was written to
+    /// [`HISTORY_RANGE`], for example with [`HistoryRange::to_bytes`].
+    pub async fn history_notifications(&self) -> Result<HistoryChunks, AranetError> {
+        with_retry(Self::SETUP_ATTEMPTS, || {
+            self.peripheral.subscribe(&HISTORY_NOTIFIER)
+        })
+        .await
+        .map_err(|error| self.pairing(error))?;
+        Ok(aranet_notifications(self.peripheral.notifications().await?).boxed())
+    }
+
+    /// Write type of history requests, with response only if the discovered
+    /// [`HISTORY_RANGE`] does not support writes without.
+    fn history_write_type(&self) -> WriteType {
+        let properties = self
+            .peripheral
+            .characteristics()
+            .into_iter()
+            .find(|characteristic| characteristic.uuid == HISTORY_RANGE.uuid)
+            .map_or(CharPropFlags::WRITE_WITHOUT_RESPONSE, |characteristic| {
+                characteristic.properties
+            });
+        if properties.contains(CharPropFlags::WRITE)
+            && !properties.contains(CharPropFlags::WRITE_WITHOUT_RESPONSE)
+        {
+            WriteType::WithResponse
+        } else {
+            WriteType::WithoutResponse
+        }
+    }
+
+    /// Download the remaining samples of `downloads[current]`.
+    ///
+    /// Notifications for the other downloads are stored in them as well. A
+    /// request that gets no notification is repeated with response, as some
+    /// backends drop writes without response.
+    async fn download(
+        &self,
+        notifications: &mut HistoryChunks,
+        downloads: &mut [HistoryDownload],
+        current: usize,
+    ) -> Result<(), AranetError> {
+        let sensor = downloads[current].sensor();
+        // One-based index of the last sample, which is the number stored.
+        let stored = downloads[current].first_index() + downloads[current].expected() - 1;
+        let stored = history_index(stored)?;
+        let mut write_type = self.history_write_type();
+        if !downloads[current].reached_end() {
+            let start = history_index(downloads[current].next_index())?;
+            self.request_history(sensor, start, u16::MAX, stored, write_type)
+                .await?;
+            let mut requests = 1;
+            let mut started = false;
+            while !downloads[current].reached_end() {
+                let download = &downloads[current];
+                let Ok(chunk) = time::timeout(self.timeout, notifications.next()).await else {
+                    if !started && requests < Self::START_ATTEMPTS {
+                        debug!(
+                            sensor = sensor.name(),
+                            requests, "no history notification, requesting again with response"
+                        );
+                        write_type = WriteType::WithResponse;
+                        self.request_history(sensor, start, u16::MAX, stored, write_type)
+                            .await?;
+                        requests += 1;
+                        continue;
+                    }
+                    return Err(AranetError::Timeout {
+                        operation: format!(
+                            "history download stalled, received {} of {} samples",
+                            download.received(),
+                            download.expected()
+                        ),
+                        timeout:   self.timeout,
+                    });
+                };
+                let Some(chunk) = chunk else {
+                    return Err(AranetError::InvalidData(format!(
+                        "notifications ended, received {} of {} samples",
+                        download.received(),
+                        download.expected()
+                    )));
+                };
+                started = true;
+                receive_chunk(downloads, &chunk?);
+                self.report_progress(&downloads[current]);
+            }
+        }
+
+        // Request samples lost in transit again.
+        for _ in 0..Self::GAP_ATTEMPTS {
+            let gaps = downloads[current].gaps();
+            if gaps.is_empty() {
+                break;
+            }
+            for gap in gaps {
+                let start = history_index(*gap.start())?;
+                let end = history_index(*gap.end())?;
+                self.request_history(sensor, start, end, stored, write_type)
+                    .await?;
+                while downloads[current].is_missing(&gap) {
+                    match time::timeout(self.timeout, notifications.next()).await {
+                        Ok(Some(chunk)) => {
+                            receive_chunk(downloads, &chunk?);
+                            self.report_progress(&downloads[current]);
+                        }
+                        _ => break,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn report_progress(&self, download: &HistoryDownload) {
+        if let Some(progress) = &self.progress {
+            progress(download);
+        }
+    }
+
+    /// Request history samples from one-based index `start` up to `end`,
+    /// limited to the `stored` samples of the device.
+    async fn request_history(
+        &self,
+        sensor: Sensor,
+        start: u16,
+        end: u16,
+        stored: u16,
+        write_type: WriteType,
+    ) -> Result<(), AranetError> {
+        let range = HistoryRange::new(sensor, start, end).clamp(stored);
+        debug!(
+            sensor = sensor.name(),
+            start = range.start,
+            end = range.end,
+            ?write_type,
+            "requesting history"
+        );
+        let data = range.to_bytes();
+        with_retry(Self::SETUP_ATTEMPTS, || {
+            self.peripheral.write(&HISTORY_RANGE, &data, write_type)
+        })
+        .await
+        .map_err(|error| self.pairing(error))?;
+        Ok(())
+    }
+
+    /// Download the stored history of a sensor together with the time each
+    /// sample was measured, oldest sample first. See
+    /// [`Self::read_history_timeline`].
+    pub async fn read_history_timed(&self, sensor: Sensor) -> Result<TimedSamples, AranetError> {
+        Ok(self.read_history_timeline(sensor).await?.into_timed())
+    }
+
+    /// Download the stored history of a sensor with its interval and the
+    /// time of the newest sample, oldest sample first.
+    ///
+    /// Timestamps are derived from the current reading: the newest sample was
+    /// measured `age` seconds ago and each earlier sample `interval` seconds
+    /// before its successor. Note that the newest sample is therefore not
+    /// `now - interval`, the time elapsed in the current, partial, interval is
+    /// `age`. If the device records a new measurement while the history is
+    /// downloading, the timestamps will be one interval early. The device does
+    /// not record when its interval was changed, so samples recorded with an
+    /// earlier interval get wrong timestamps too.
+    ///
+    /// Devices with a clock, see [`Self::device_clock`], are asked for the
+    /// time instead of using the host clock, so the timestamps match the ones
+    /// the device recorded even if the clocks differ.
+    pub async fn read_history_timeline(&self, sensor: Sensor) -> Result<History, AranetError> {
+        let reading = self.current_reading().await?;
+        let now = self.device_clock().await?.unwrap_or_else(Utc::now);
+        let history = self.read_history(sensor).await?;
+        Ok(History::new(&reading, now, history.samples))
+    }
+}
+
+/// Store the samples of a history chunk in the download for its sensor.
+///
+/// Chunks of sensors without a download are ignored, they can arrive after a
+/// reconnect.
+fn receive_chunk(downloads: &mut [HistoryDownload], chunk: &HistoryChunk) {
+    let Some(download) = downloads
+        .iter_mut()
+        .find(|download| download.sensor() == chunk.sensor)
+    else {
+        return;
+    };
+    trace!(
+        sensor = chunk.sensor.name(),
+        index = chunk.index,
+        length = chunk.len(),
+        "history notification"
+    );
+    for (index, &value) in chunk.indices().zip(&chunk.values) {
+        download.receive(index, value);
+    }
+}
+
+/// Convert a one-based history index to the `u16` of the protocol.
+fn history_index(index: usize) -> Result<u16, AranetError> {
+    u16::try_from(index)
+        .map_err(|_| AranetError::InvalidData(format!("history index {index} out of range")))
+}
@@ -0,0 +1,29 @@
+use crate::ParseError;
+use bytes::Buf;
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use std::time::Duration;
+
+/// Difference between the device clock and the host clock to warn about.
+pub const CLOCK_DRIFT_WARNING: Duration = Duration::from_mins(1);
+
+/// Parse the contents of the [`CURRENT_TIME`] characteristic.
+///
+/// The layout is `year: u16, month: u8, day: u8, hours: u8, minutes: u8,
+/// seconds: u8` followed by the day of the week and fractions of a second,
+/// which are ignored. The time is local time, taken to be in the time zone of
+/// the host. Returns `None` for an invalid date, such as all zeros for a clock
+/// that was never set.
+///
+/// [`CURRENT_TIME`]: crate::characteristics::CURRENT_TIME
+pub fn parse_current_time(buf: &mut impl Buf) -> Result<Option<DateTime<Utc>>, ParseError> {
+    ParseError::ensure_remaining(buf, 7)?;
+    let year = buf.get_u16_le();
+    let month = buf.get_u8();
+    let day = buf.get_u8();
+    let (hours, minutes, seconds) = (buf.get_u8(), buf.get_u8(), buf.get_u8());
+    let time = NaiveDate::from_ymd_opt(year.into(), month.into(), day.into())
+        .and_then(|date| date.and_hms_opt(hours.into(), minutes.into(), seconds.into()))
+        .and_then(|time| Local.from_local_datetime(&time).earliest())
+        .map(|time| time.with_timezone(&Utc));
+    Ok(time)
+}
@@ -0,0 +1,139 @@
+//! Defaults for the command line options, read from a TOML file.
+//!
+//! Keys are named after the options they set, for example
+//!
+//! ```toml
+//! adapter = "hci0"
+//! units = "f"
+//! scan-timeout = 5.0
+//! mqtt-broker = "mqtt://localhost:1883"
+//! co2-threshold = 1400
+//!
+//! [devices]
+//! "EB:12:34:56:78:9A" = "living_room"
+//! ```
+
+use crate::units::{PressureUnit, TemperatureUnit};
+use btleplug::api::BDAddr;
+use serde::{de, Deserialize, Deserializer};
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use thiserror::Error;
+
+/// Contents of a configuration file, every value optional.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    pub adapter:           Option<String>,
+    /// Prefix of the advertised name of the devices to use.
+    pub name_prefix:       Option<String>,
+    /// Weakest signal strength in dBm of devices to use.
+    pub min_rssi:          Option<i16>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub address:           Option<BDAddr>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub units:             Option<TemperatureUnit>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub pressure_units:    Option<PressureUnit>,
+    /// Output format, validated by the binary.
+    pub format:            Option<String>,
+    /// Seconds to scan for devices.
+    pub scan_timeout:      Option<f64>,
+    /// Seconds to wait for a connection to a device.
+    pub connect_timeout:   Option<f64>,
+    pub mqtt_broker:       Option<String>,
+    pub mqtt_topic_prefix: Option<String>,
+    pub influx_url:        Option<String>,
+    pub influx_org:        Option<String>,
+    pub influx_bucket:     Option<String>,
+    pub influx_token:      Option<String>,
+    /// URL readings are posted to as JSON.
+    pub http_sink:         Option<String>,
+    /// CO2 alert threshold of `watch` in ppm.
+    pub co2_threshold:     Option<u16>,
+    /// CO2 concentration in ppm to clear the alert of `watch` at.
+    pub co2_clear:         Option<u16>,
+    /// Battery charge in % to warn below.
+    pub low_battery:       Option<u8>,
+    /// Friendly names of devices by address, used in place of the address in
+    /// output, MQTT topics and metric labels.
+    #[serde(default, deserialize_with = "address_keys")]
+    pub devices:           BTreeMap<BDAddr, String>,
+}
+
+/// Failure of loading a configuration file.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    /// Reading the file at `path` failed.
+    #[error("failed to read {}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    /// The contents, of the file at `path` if loaded from one, are invalid.
+    #[error("{}", invalid_config(path.as_deref()))]
+    Invalid {
+        path:   Option<PathBuf>,
+        source: toml::de::Error,
+    },
+}
+
+fn invalid_config(path: Option<&Path>) -> String {
+    path.map_or_else(
+        || "invalid config".to_owned(),
+        |path| format!("invalid config file {}", path.display()),
+    )
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_owned(),
+            source,
+        })?;
+        toml::from_str(&contents).map_err(|source| ConfigError::Invalid {
+            path: Some(path.to_owned()),
+            source,
+        })
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, ConfigError> {
+        toml::from_str(contents).map_err(|source| ConfigError::Invalid { path: None, source })
+    }
+
+    /// Friendly name of the device with `address`, if configured.
+    #[must_use]
+    pub fn device_name(&self, address: BDAddr) -> Option<&str> {
+        self.devices.get(&address).map(String::as_str)
+    }
+}
+
+/// Deserialize an optional value from a string using its [`FromStr`]
+/// implementation, like the command line options are parsed.
+fn from_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map(Some).map_err(de::Error::custom)
+}
+
+/// Deserialize a table keyed by device address.
+fn address_keys<'de, D>(deserializer: D) -> Result<BTreeMap<BDAddr, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(address, name)| {
+            let address = address.parse().map_err(|error| {
+                <D::Error as de::Error>::custom(format!("invalid address {address}: {error}"))
+            })?;
+            Ok((address, name))
+        })
+        .collect()
+}
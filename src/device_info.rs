@@ -0,0 +1,78 @@
+use crate::Capabilities;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+
+/// Identification and state of a device, as read from its GATT
+/// characteristics.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DeviceInfo {
+    pub name:         String,
+    pub model:        String,
+    pub manufacturer: String,
+    pub serial:       String,
+    /// Firmware version, such as `v1.4.19`.
+    pub firmware:     String,
+    /// Battery charge in %.
+    pub battery:      u8,
+    /// Measurement interval in seconds.
+    pub interval:     u16,
+    /// Number of samples stored per sensor.
+    pub stored:       u16,
+    pub capabilities: Capabilities,
+    /// Time on the device clock when read, if the device has one.
+    pub clock:        Option<DateTime<Utc>>,
+}
+
+impl DeviceInfo {
+    /// Format all fields as a table with a row each.
+    #[must_use]
+    pub const fn table(&self) -> InfoTable<'_> {
+        InfoTable(self)
+    }
+}
+
+/// One line summary of the identification, see [`Self::table`] for all
+/// fields.
+impl Display for DeviceInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} by {}, serial {}, firmware {})",
+            self.name, self.model, self.manufacturer, self.serial, self.firmware
+        )
+    }
+}
+
+/// Human readable [`DeviceInfo`], see [`DeviceInfo::table`].
+#[derive(Clone, Copy, Debug)]
+pub struct InfoTable<'a>(&'a DeviceInfo);
+
+impl Display for InfoTable<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let info = self.0;
+        let mut rows = vec![
+            ("Device name", info.name.clone()),
+            ("Model", info.model.clone()),
+            ("Manufacturer", info.manufacturer.clone()),
+            ("Serial", info.serial.clone()),
+            ("Firmware", info.firmware.clone()),
+            ("Battery", format!("{} %", info.battery)),
+            ("Interval", format!("{} s", info.interval)),
+            ("Stored readings", info.stored.to_string()),
+            ("Capabilities", info.capabilities.to_string()),
+        ];
+        if let Some(clock) = info.clock {
+            rows.push(("Clock", clock.to_rfc3339_opts(SecondsFormat::Secs, true)));
+        }
+        let label_width = rows.iter().map(|(label, _)| label.len()).max();
+        let label_width = label_width.unwrap_or(0);
+        for (i, (label, value)) in rows.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{label:<label_width$}  {value}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use crate::Sensor;
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+
+/// The Aranet product a device is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize)]
+pub enum DeviceType {
+    /// CO2, temperature, humidity and pressure.
+    #[default]
+    Aranet4,
+    /// Temperature and humidity only.
+    Aranet2,
+    /// Radon, temperature, humidity and pressure, see
+    /// [`RadonReading`](crate::radiation::RadonReading).
+    AranetRadon,
+    /// Radiation dose rate and total dose, see
+    /// [`RadiationReading`](crate::radiation::RadiationReading).
+    AranetRadiation,
+}
+
+impl DeviceType {
+    /// Detect the device type from an advertised name or model number.
+    ///
+    /// The Aranet Radon Plus advertises itself as `AranetRn+` and the Aranet
+    /// Radiation as `Aranet☢`.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        const PREFIXES: [(&str, DeviceType); 6] = [
+            ("Aranet4", DeviceType::Aranet4),
+            ("Aranet2", DeviceType::Aranet2),
+            ("AranetRn+", DeviceType::AranetRadon),
+            ("Aranet Radon", DeviceType::AranetRadon),
+            ("Aranet☢", DeviceType::AranetRadiation),
+            ("Aranet Radiation", DeviceType::AranetRadiation),
+        ];
+        PREFIXES
+            .into_iter()
+            .find(|(prefix, _)| name.starts_with(prefix))
+            .map(|(_, device_type)| device_type)
+    }
+
+    /// Whether the device measures and records this sensor.
+    ///
+    /// History of the radon and radiation devices is not supported, so they
+    /// have none.
+    #[must_use]
+    pub const fn supports(self, sensor: Sensor) -> bool {
+        match self {
+            Self::Aranet4 => true,
+            Self::Aranet2 => matches!(sensor, Sensor::Temperature | Sensor::Humidity),
+            Self::AranetRadon | Self::AranetRadiation => false,
+        }
+    }
+
+    /// Whether the device has a [`CurrentReading`](crate::CurrentReading),
+    /// which the Aranet Radiation lacks.
+    #[must_use]
+    pub const fn has_current_reading(self) -> bool {
+        !matches!(self, Self::AranetRadiation)
+    }
+}
+
+impl Display for DeviceType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Aranet4 => write!(f, "Aranet4"),
+            Self::Aranet2 => write!(f, "Aranet2"),
+            Self::AranetRadon => write!(f, "Aranet Radon Plus"),
+            Self::AranetRadiation => write!(f, "Aranet Radiation"),
+        }
+    }
+}
@@ -0,0 +1,428 @@
+//! Finding Aranet devices among scanned peripherals.
+
+use crate::{
+    characteristics::{ARANET4_SERVICE, SAF_TEHNIKA_SERVICE},
+    AranetError, DeviceType,
+};
+use btleplug::api::{
+    BDAddr, Central, CentralEvent, Manager, Peripheral, PeripheralProperties, ScanFilter,
+};
+use futures::stream::{Stream, StreamExt};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+use tokio::time;
+use tracing::debug;
+
+/// Default for the prefix of the advertised name that identifies Aranet
+/// devices, matching all product lines. Earlier versions only matched
+/// `Aranet4`.
+pub const DEFAULT_NAME_PREFIX: &str = "Aranet";
+
+/// An Aranet device seen during a scan.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiscoveredDevice {
+    pub address:        BDAddr,
+    /// Advertised local name.
+    pub name:           String,
+    /// Friendly name configured by the user.
+    pub alias:          Option<String>,
+    /// Detected from the name, [`DeviceType::Aranet4`] if the name does not
+    /// tell. Once connected [`AranetClient::read_device_type`] reads the
+    /// model number instead.
+    ///
+    /// [`AranetClient::read_device_type`]: crate::AranetClient::read_device_type
+    pub device_type:    DeviceType,
+    /// Received signal strength in dBm.
+    pub rssi:           Option<i16>,
+    /// Advertised transmission power in dBm.
+    pub tx_power_level: Option<i16>,
+}
+
+impl DiscoveredDevice {
+    /// Returns `None` if the advertised name of the peripheral does not start
+    /// with `name_prefix`, such as [`DEFAULT_NAME_PREFIX`]. An empty prefix
+    /// matches every named peripheral, for devices renamed in the app.
+    #[must_use]
+    pub fn from_properties(props: &PeripheralProperties, name_prefix: &str) -> Option<Self> {
+        let name = props.local_name.as_ref()?;
+        if !name.starts_with(name_prefix) {
+            return None;
+        }
+        let device_type = DeviceType::from_name(name).unwrap_or_default();
+        Some(Self {
+            address: props.address,
+            name: name.clone(),
+            alias: None,
+            device_type,
+            rssi: props.rssi,
+            tx_power_level: props.tx_power_level,
+        })
+    }
+
+    /// Whether the signal is at least `min_rssi` dBm, always true without a
+    /// minimum or if the signal strength is unknown.
+    ///
+    /// RSSI is noisy and depends on obstacles and antenna orientation, so it
+    /// is only a rough proxy for how close a device is. Leave some margin
+    /// below the weakest signal of the devices to use.
+    #[must_use]
+    pub const fn has_signal(&self, min_rssi: Option<i16>) -> bool {
+        match (self.rssi, min_rssi) {
+            (Some(rssi), Some(min_rssi)) => rssi >= min_rssi,
+            _ => true,
+        }
+    }
+}
+
+impl Display for DiscoveredDevice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.address)?;
+        match &self.alias {
+            Some(alias) => write!(f, " {alias} ({})", self.name)?,
+            None => write!(f, " {}", self.name)?,
+        }
+        if let Some(rssi) = self.rssi {
+            write!(f, " rssi {rssi} dBm")?;
+        }
+        if let Some(tx_power_level) = self.tx_power_level {
+            write!(f, " tx power {tx_power_level} dBm")?;
+        }
+        Ok(())
+    }
+}
+
+/// Pick a bluetooth adapter by index, name or address, or the first one if
+/// `spec` is `None`.
+///
+/// Which identifiers there are depends on the platform:
+///
+/// - Linux: the adapter info reported by `BlueZ` is the id and modalias, such
+///   as `hci0 (usb:v1D6Bp0246d0540)`. The id, the modalias and the bluetooth
+///   address of the adapter, see [`adapter_addresses`], all match. The id
+///   depends on the order adapters were plugged in, the address is stable.
+/// - macOS, Windows and Android: there is only one adapter, whose info is the
+///   name of the platform API such as `CoreBluetooth`.
+///
+/// See [`adapter_matches`] for the matching. The error lists the available
+/// adapters with their address if none matches.
+pub async fn select_adapter<M: Manager + Sync>(
+    manager: &M,
+    spec: Option<&str>,
+) -> Result<M::Adapter, AranetError> {
+    let adapters = all_adapters(manager).await?;
+    let mut infos = Vec::with_capacity(adapters.len());
+    for adapter in &adapters {
+        infos.push(adapter.adapter_info().await.unwrap_or_default());
+    }
+    // Looked up only when needed, it takes a separate connection to `BlueZ`.
+    let addresses = match spec {
+        Some(spec) if spec.parse::<usize>().is_err() => Some(adapter_addresses().await),
+        _ => None,
+    };
+    let index = spec.map_or(Some(0), |spec| {
+        spec.parse::<usize>().ok().or_else(|| {
+            let addresses = addresses.as_ref()?;
+            infos
+                .iter()
+                .position(|info| adapter_matches(info, adapter_address(addresses, info), spec))
+        })
+    });
+    if let Some(adapter) = index.and_then(|index| adapters.into_iter().nth(index)) {
+        return Ok(adapter);
+    }
+    // List every adapter with its address, also when selecting by index.
+    let addresses = match addresses {
+        Some(addresses) => addresses,
+        None => adapter_addresses().await,
+    };
+    Err(AranetError::NoAdapter {
+        requested: spec.map(str::to_owned),
+        available: infos
+            .iter()
+            .map(|info| {
+                adapter_address(&addresses, info).map_or_else(
+                    || info.clone(),
+                    |address| format!("{info}, address {address}"),
+                )
+            })
+            .collect(),
+    })
+}
+
+/// The address in `addresses` of the adapter with `info`, by its first word.
+fn adapter_address(addresses: &BTreeMap<String, BDAddr>, info: &str) -> Option<BDAddr> {
+    info.split_whitespace()
+        .next()
+        .and_then(|id| addresses.get(id).copied())
+}
+
+/// Whether `spec` names the adapter with `info` and `address`.
+///
+/// It matches the whole info, a word of it ignoring case and parentheses, such
+/// as `hci0` or `usb:v1D6Bp0246d0540` of `hci0 (usb:v1D6Bp0246d0540)`, or the
+/// address.
+#[must_use]
+pub fn adapter_matches(info: &str, address: Option<BDAddr>, spec: &str) -> bool {
+    info == spec
+        || info
+            .split_whitespace()
+            .map(|word| word.trim_matches(|c| c == '(' || c == ')'))
+            .any(|word| word.eq_ignore_ascii_case(spec))
+        || address.is_some_and(|address| spec.parse() == Ok(address))
+}
+
+/// The bluetooth address of each adapter by its id, such as `hci0`.
+///
+/// Only available from `BlueZ` on Linux, empty elsewhere or if `BlueZ` does not
+/// respond.
+#[cfg(target_os = "linux")]
+pub async fn adapter_addresses() -> BTreeMap<String, BDAddr> {
+    let adapters = async {
+        let (_, session) = bluez_async::BluetoothSession::new().await?;
+        session.get_adapters().await
+    };
+    match adapters.await {
+        Ok(adapters) => adapters
+            .into_iter()
+            .filter_map(|adapter| {
+                let address = adapter.mac_address.to_string().parse().ok()?;
+                Some((adapter.id.to_string(), address))
+            })
+            .collect(),
+        Err(error) => {
+            debug!(%error, "failed to read adapter addresses");
+            BTreeMap::new()
+        }
+    }
+}
+
+/// The bluetooth address of each adapter by its id, such as `hci0`.
+///
+/// Only available from `BlueZ` on Linux, empty elsewhere or if `BlueZ` does not
+/// respond.
+#[cfg(not(target_os = "linux"))]
+#[allow(clippy::unused_async)]
+pub async fn adapter_addresses() -> BTreeMap<String, BDAddr> {
+    BTreeMap::new()
+}
+
+/// Get a new handle of an adapter after it reset, which invalidates the old
+/// handle and those of its peripherals.
+///
+/// The adapter is matched by the first word of its `info`, such as `hci0`,
+/// which survives the reset. Fails with [`AranetError::NoAdapter`] if it has
+/// not come back yet.
+pub async fn reacquire_adapter<M: Manager + Sync>(
+    manager: &M,
+    info: &str,
+) -> Result<M::Adapter, AranetError> {
+    select_adapter(
+        manager,
+        Some(info.split_whitespace().next().unwrap_or(info)),
+    )
+    .await
+}
+
+/// All bluetooth adapters, failing if there are none.
+pub async fn all_adapters<M: Manager + Sync>(manager: &M) -> Result<Vec<M::Adapter>, AranetError> {
+    let adapters = manager.adapters().await?;
+    if adapters.is_empty() {
+        return Err(AranetError::NoAdapter {
+            requested: None,
+            available: Vec::new(),
+        });
+    }
+    Ok(adapters)
+}
+
+/// Number of simultaneous connections most BLE stacks support per adapter.
+pub const CONNECTIONS_PER_ADAPTER: usize = 7;
+
+/// Spread devices over adapters, to stay below the connection limit of each.
+///
+/// `sightings` holds the addresses each adapter has found. Devices are
+/// assigned in order of address to the adapters in turn, skipping adapters
+/// that did not find the device. Returns the addresses assigned to each
+/// adapter, every address exactly once.
+#[must_use]
+pub fn assign_adapters(sightings: &[BTreeSet<BDAddr>]) -> Vec<Vec<BDAddr>> {
+    let mut assigned = vec![Vec::new(); sightings.len()];
+    let addresses = sightings.iter().flatten().collect::<BTreeSet<_>>();
+    let mut next = 0;
+    for &address in addresses {
+        let Some(adapter) = (0..sightings.len())
+            .map(|offset| (next + offset) % sightings.len())
+            .find(|&adapter| sightings[adapter].contains(&address))
+        else {
+            continue;
+        };
+        assigned[adapter].push(address);
+        next = adapter + 1;
+    }
+    assigned
+}
+
+/// Scan filter matching Aranet devices.
+///
+/// Depending on the firmware version devices advertise either the Aranet4
+/// service or the SAF Tehnika service, so both are included.
+#[must_use]
+pub fn scan_filter() -> ScanFilter {
+    ScanFilter {
+        services: vec![ARANET4_SERVICE, SAF_TEHNIKA_SERVICE],
+    }
+}
+
+/// Start scanning and wait until a peripheral matching `predicate` is seen, or
+/// until `timeout` has elapsed.
+///
+/// Returns whether a matching peripheral was seen. Scanning continues after
+/// this returns. A predicate that never matches scans for the full `timeout`,
+/// to discover all devices in range.
+pub async fn scan<C: Central>(
+    central: &C,
+    filter: ScanFilter,
+    timeout: Duration,
+    predicate: impl Fn(&PeripheralProperties) -> bool + Send + Sync,
+) -> Result<bool, AranetError> {
+    let mut events = central.events().await?;
+    central.start_scan(filter).await?;
+    let found = await_device(central, &mut events, predicate, timeout).await?;
+    Ok(found.is_some())
+}
+
+/// Wait on the `events` of a scanning `central` until a peripheral matching
+/// `predicate` is discovered or updated, or until `timeout` has elapsed.
+///
+/// Returns the first match as soon as it is seen, or `None` on timeout.
+pub async fn await_device<C: Central>(
+    central: &C,
+    events: &mut (impl Stream<Item = CentralEvent> + Unpin + Send),
+    predicate: impl Fn(&PeripheralProperties) -> bool + Send + Sync,
+    timeout: Duration,
+) -> Result<Option<C::Peripheral>, AranetError> {
+    let found = time::timeout(timeout, async {
+        while let Some(event) = events.next().await {
+            let (CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id)) = event
+            else {
+                continue;
+            };
+            let p = central.peripheral(&id).await?;
+            let Some(props) = p.properties().await? else {
+                continue;
+            };
+            if predicate(&props) {
+                debug!(address = %props.address, "found device");
+                return Ok(Some(p));
+            }
+        }
+        Ok(None)
+    })
+    .await;
+    found.unwrap_or(Ok(None))
+}
+
+/// Connect to a peripheral and discover its services, giving up after
+/// `timeout`.
+///
+/// Connecting to a device that is out of range or busy can otherwise hang
+/// indefinitely.
+pub async fn connect<P: Peripheral>(p: &P, timeout: Duration) -> Result<(), AranetError> {
+    let connect = async {
+        p.connect().await?;
+        p.discover_services().await
+    };
+    match time::timeout(timeout, connect).await {
+        Ok(result) => Ok(result?),
+        Err(_) => Err(AranetError::Timeout {
+            operation: format!("connecting to {}", p.address()),
+            timeout,
+        }),
+    }
+}
+
+/// Scan for a previously connected device and connect to it again.
+///
+/// Once connected services are discovered, so the peripheral is ready for use
+/// with [`AranetClient`](crate::AranetClient). See [`connect`] for
+/// `connect_timeout`.
+pub async fn reconnect<C: Central>(
+    central: &C,
+    address: BDAddr,
+    scan_timeout: Duration,
+    connect_timeout: Duration,
+) -> Result<C::Peripheral, AranetError> {
+    let mut events = central.events().await?;
+    central.start_scan(scan_filter()).await?;
+    let found = await_device(
+        central,
+        &mut events,
+        |props| props.address == address,
+        scan_timeout,
+    )
+    .await?;
+    let p = match found {
+        Some(p) => p,
+        None => find_by_address(central, address).await?,
+    };
+    connect(&p, connect_timeout).await?;
+    Ok(p)
+}
+
+/// Aranet devices found by the scan so far, one per address ordered by
+/// address. See [`DiscoveredDevice::from_properties`] for `name_prefix` and
+/// [`DiscoveredDevice::has_signal`] for `min_rssi`.
+pub async fn discover_aranets<C: Central>(
+    central: &C,
+    name_prefix: &str,
+    min_rssi: Option<i16>,
+) -> Result<Vec<DiscoveredDevice>, AranetError> {
+    let mut devices = Vec::new();
+    for p in central.peripherals().await? {
+        if let Some(props) = p.properties().await? {
+            devices.extend(DiscoveredDevice::from_properties(&props, name_prefix));
+        }
+    }
+    let mut devices = dedup_devices(devices);
+    devices.retain(|device| device.has_signal(min_rssi));
+    Ok(devices)
+}
+
+/// Merge devices seen more than once into one per address, ordered by
+/// address.
+///
+/// The last sighting is kept, with the strongest signal of all sightings.
+#[must_use]
+pub fn dedup_devices(devices: impl IntoIterator<Item = DiscoveredDevice>) -> Vec<DiscoveredDevice> {
+    let mut by_address = BTreeMap::<BDAddr, DiscoveredDevice>::new();
+    for mut device in devices {
+        if let Some(previous) = by_address.get(&device.address) {
+            device.rssi = device.rssi.max(previous.rssi);
+        }
+        by_address.insert(device.address, device);
+    }
+    by_address.into_values().collect()
+}
+
+/// Find a scanned peripheral by its address.
+///
+/// Fails if no peripheral with this address was found by the scan so far.
+pub async fn find_by_address<C: Central>(
+    central: &C,
+    address: BDAddr,
+) -> Result<C::Peripheral, AranetError> {
+    for p in central.peripherals().await? {
+        if let Some(props) = p.properties().await? {
+            if props.address == address {
+                return Ok(p);
+            }
+        }
+    }
+    Err(AranetError::DeviceNotFound {
+        address:     Some(address),
+        name_prefix: None,
+    })
+}
@@ -0,0 +1,36 @@
+//! Hex dumps of raw characteristic values, for debugging devices whose byte
+//! layout differs from what the parsers expect.
+
+use std::io::{self, Write};
+
+/// Bytes shown per line of a hex dump.
+const BYTES_PER_LINE: usize = 16;
+
+/// Write `data` as lines of the offset, the bytes in hex and the bytes as
+/// ASCII, with `.` for unprintable bytes, like `hexdump -C`.
+pub fn write_hex_dump<W: Write>(mut writer: W, data: &[u8]) -> io::Result<()> {
+    for (line, chunk) in data.chunks(BYTES_PER_LINE).enumerate() {
+        write!(writer, "{:08x} ", line * BYTES_PER_LINE)?;
+        for i in 0..BYTES_PER_LINE {
+            if i == BYTES_PER_LINE / 2 {
+                write!(writer, " ")?;
+            }
+            match chunk.get(i) {
+                Some(byte) => write!(writer, " {byte:02x}")?,
+                None => write!(writer, "   ")?,
+            }
+        }
+        let ascii = chunk
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    char::from(byte)
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        writeln!(writer, "  |{ascii}|")?;
+    }
+    writeln!(writer, "{:08x}", data.len())
+}
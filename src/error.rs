@@ -0,0 +1,133 @@
+use crate::{PairingRequired, ParseError};
+use btleplug::api::BDAddr;
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter, Write as _},
+    io,
+    time::Duration,
+};
+use thiserror::Error;
+
+/// Failure of finding, connecting to or talking to a device.
+///
+/// Returned by the device facing API. The integrations have their own errors,
+/// such as [`HttpError`] and [`MqttError`].
+///
+/// [`MqttError`]: crate::mqtt::MqttError
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum AranetError {
+    /// There is no bluetooth adapter, or none matches the `requested` one.
+    #[error("{}", no_adapter(requested.as_deref(), available))]
+    NoAdapter {
+        requested: Option<String>,
+        /// Info of the adapters that are present.
+        available: Vec<String>,
+    },
+    /// No device with the `address`, or else named with the `name_prefix`,
+    /// was found by the scan.
+    #[error("{}", device_not_found(address.as_ref(), name_prefix.as_deref()))]
+    DeviceNotFound {
+        address:     Option<BDAddr>,
+        name_prefix: Option<String>,
+    },
+    /// Connecting to the device with `address` failed.
+    #[error("failed to connect to {address}")]
+    Connect {
+        address: BDAddr,
+        #[source]
+        source:  Box<Self>,
+    },
+    /// The bluetooth stack failed.
+    #[error(transparent)]
+    Bluetooth(#[from] btleplug::Error),
+    /// A value read from the device is truncated.
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    /// The device sent a value this crate does not understand.
+    #[error("{0}")]
+    InvalidData(String),
+    /// The device does not support a request, or the request is invalid.
+    #[error("{0}")]
+    Unsupported(String),
+    /// The device did not respond within `timeout`.
+    #[error("{operation}: timed out after {}s", timeout.as_secs_f64())]
+    Timeout {
+        /// What was waited for, such as `connecting to <address>`.
+        operation: String,
+        timeout:   Duration,
+    },
+    /// The device refused a request because it is not paired.
+    #[error(transparent)]
+    PairingRequired(#[from] PairingRequired),
+    /// An I/O error of the host, such as failing to start a runtime.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+fn no_adapter(requested: Option<&str>, available: &[String]) -> String {
+    let Some(requested) = requested else {
+        return "no bluetooth adapter found, a Bluetooth Low Energy capable adapter is required"
+            .to_owned();
+    };
+    let mut message = format!("no bluetooth adapter {requested}, available adapters:");
+    for (i, info) in available.iter().enumerate() {
+        // Writing to a `String` does not fail.
+        let _ = write!(message, "\n  {i}: {info}");
+    }
+    message
+}
+
+fn device_not_found(address: Option<&BDAddr>, name_prefix: Option<&str>) -> String {
+    match (address, name_prefix) {
+        (Some(address), _) => format!("no device with address {address} found"),
+        (None, Some(name_prefix)) => format!("no device named {name_prefix}* found"),
+        (None, None) => "no device found".to_owned(),
+    }
+}
+
+/// Failure of posting to an HTTP endpoint, returned by the [`webhook`],
+/// [`influx`] and [`http_sink`] integrations.
+///
+/// [`webhook`]: crate::webhook
+/// [`influx`]: crate::influx
+/// [`http_sink`]: crate::http_sink
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum HttpError {
+    /// An option of the requests is invalid, such as a header.
+    #[error("{0}")]
+    InvalidRequest(String),
+    /// Creating the client failed, or the request to `url` failed or was
+    /// answered with an error status.
+    #[error("failed to post to {url}")]
+    Request {
+        url:    String,
+        source: reqwest::Error,
+    },
+    /// Encoding the body failed.
+    #[error("failed to encode the request body")]
+    Encode(#[from] io::Error),
+    /// The task sending the requests stopped.
+    #[error("the task sending requests stopped")]
+    Stopped,
+    /// Queued requests were not sent within `timeout`.
+    #[error("timed out sending requests after {}s", .0.as_secs_f64())]
+    Timeout(Duration),
+}
+
+/// Display of an error followed by its sources, such as
+/// `failed to post to <url>: <reason>`, for logging it.
+pub struct ErrorChain<'a>(pub &'a (dyn Error + 'static));
+
+impl Display for ErrorChain<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)?;
+        let mut source = self.0.source();
+        while let Some(error) = source {
+            write!(f, ": {error}")?;
+            source = error.source();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+//! Writers for exporting readings.
+
+use crate::{
+    history::{align_history, TimedSamples},
+    CurrentReading, Sensor,
+};
+use btleplug::api::BDAddr;
+use chrono::{DateTime, SecondsFormat, Utc};
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// A writer for exported data that is optionally gzip-compressed.
+///
+/// Call [`Self::finish`] when done, a compressed stream is truncated without
+/// its trailer.
+pub enum ExportWriter<W: Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+}
+
+impl<W: Write> ExportWriter<W> {
+    pub fn new(writer: W, compress: bool) -> Self {
+        if compress {
+            Self::Gzip(GzEncoder::new(writer, Compression::default()))
+        } else {
+            Self::Plain(writer)
+        }
+    }
+
+    /// Flush all data, write the gzip trailer if compressed, and return the
+    /// underlying writer.
+    pub fn finish(self) -> io::Result<W> {
+        let mut writer = match self {
+            Self::Plain(writer) => writer,
+            Self::Gzip(encoder) => encoder.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl ExportWriter<BufWriter<File>> {
+    /// Create the file at `path`, compressed if `compress` is set or the path
+    /// ends in `.gz`.
+    pub fn create(path: &Path, compress: bool) -> io::Result<Self> {
+        let compress = compress || path.extension().is_some_and(|extension| extension == "gz");
+        Ok(Self::new(BufWriter::new(File::create(path)?), compress))
+    }
+}
+
+impl<W: Write> Write for ExportWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(writer) => writer.write(buf),
+            Self::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(writer) => writer.flush(),
+            Self::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Write timestamped history as CSV with columns `timestamp,sensor,value`.
+///
+/// Rows of all sensors are merged and sorted by time. Timestamps are in
+/// ISO-8601 and missing samples have an empty value. `derived` values, such as
+/// those of [`derived_history`](crate::history::derived_history), are written
+/// with their name in the sensor column.
+pub fn write_history_csv<W: Write>(
+    mut writer: W,
+    readings: &[(Sensor, TimedSamples)],
+    derived: &[(&str, TimedSamples)],
+) -> io::Result<()> {
+    let series = readings
+        .iter()
+        .map(|(sensor, samples)| (sensor.name(), samples))
+        .chain(derived.iter().map(|(name, samples)| (*name, samples)));
+    let mut rows = series
+        .flat_map(|(name, samples)| {
+            samples
+                .iter()
+                .map(move |&(timestamp, value)| (timestamp, name, value))
+        })
+        .collect::<Vec<_>>();
+    rows.sort_by_key(|&(timestamp, ..)| timestamp);
+
+    writeln!(writer, "timestamp,sensor,value")?;
+    for (timestamp, name, value) in rows {
+        let timestamp = timestamp.to_rfc3339_opts(SecondsFormat::Secs, true);
+        if value.is_nan() {
+            writeln!(writer, "{timestamp},{name},")?;
+        } else {
+            writeln!(writer, "{timestamp},{name},{value}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Write timestamped history as CSV with a `timestamp` column followed by one
+/// column per sensor and `derived` value, such as
+/// `timestamp,temperature,humidity,pressure,co2`.
+///
+/// There is one row per sample time, see [`align_history`]. Missing samples
+/// have an empty value.
+pub fn write_history_csv_wide<W: Write>(
+    mut writer: W,
+    readings: &[(Sensor, TimedSamples)],
+    derived: &[(&str, TimedSamples)],
+) -> io::Result<()> {
+    let (names, series): (Vec<_>, Vec<_>) = readings
+        .iter()
+        .map(|(sensor, samples)| (sensor.name(), samples))
+        .chain(derived.iter().map(|(name, samples)| (*name, samples)))
+        .unzip();
+
+    writeln!(writer, "timestamp,{}", names.join(","))?;
+    for (timestamp, values) in align_history(&series) {
+        write!(
+            writer,
+            "{}",
+            timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )?;
+        for value in values {
+            if value.is_nan() {
+                write!(writer, ",")?;
+            } else {
+                write!(writer, ",{value}")?;
+            }
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Write a reading as a line of the Influx line protocol, tagged with the
+/// device `address`.
+///
+/// Fields are in the units of the device and `timestamp` is the time the
+/// reading was measured, written in nanoseconds.
+pub fn write_reading_influx<W: Write>(
+    mut writer: W,
+    measurement: &str,
+    address: BDAddr,
+    reading: &CurrentReading,
+    timestamp: DateTime<Utc>,
+) -> io::Result<()> {
+    write!(writer, "{},address={address} ", escape_influx(measurement))?;
+    if let Some(co2) = reading.valid_co2() {
+        write!(writer, "co2={co2}i,")?;
+    }
+    write!(writer, "temperature={},", reading.temperature)?;
+    write!(writer, "humidity={}i,", reading.humidity)?;
+    if let Some(pressure) = reading.pressure {
+        write!(writer, "pressure={pressure},")?;
+    }
+    write!(writer, "battery={}i", reading.battery)?;
+    writeln!(writer, " {}", timestamp_nanos(timestamp))
+}
+
+/// Write timestamped history in the Influx line protocol, one line per
+/// sample with the sensor name as field. Missing samples are skipped.
+pub fn write_history_influx<W: Write>(
+    mut writer: W,
+    measurement: &str,
+    address: BDAddr,
+    readings: &[(Sensor, TimedSamples)],
+) -> io::Result<()> {
+    let measurement = escape_influx(measurement);
+    for (sensor, samples) in readings {
+        for &(timestamp, value) in samples {
+            if value.is_nan() {
+                continue;
+            }
+            writeln!(
+                writer,
+                "{measurement},address={address} {}={value} {}",
+                sensor.name(),
+                timestamp_nanos(timestamp)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Nanoseconds since the epoch, saturating outside of the years 1677 to 2262.
+fn timestamp_nanos(timestamp: DateTime<Utc>) -> i64 {
+    timestamp.timestamp_nanos_opt().unwrap_or_else(|| {
+        if timestamp.timestamp() < 0 {
+            i64::MIN
+        } else {
+            i64::MAX
+        }
+    })
+}
+
+/// Escape commas and spaces in a measurement name.
+fn escape_influx(name: &str) -> String {
+    name.replace(',', "\\,").replace(' ', "\\ ")
+}
@@ -0,0 +1,198 @@
+//! Recent readings for Grafana, in the format of the `SimpleJSON` and Infinity
+//! datasources.
+//!
+//! `serve` keeps the latest readings of each device in memory, see
+//! [`RecentReadings`], and answers
+//!
+//! - `GET /grafana` with `200 OK`, the connection test of the datasource,
+//! - `POST /grafana/search` with the names of all series, such as
+//!   `["Kitchen.co2", "Kitchen.temperature"]`,
+//! - `POST /grafana/query` with the series of a [`Query`], such as
+//!
+//!   ```json
+//!   {"range": {"from": "2024-05-01T12:00:00Z", "to": "2024-05-01T18:00:00Z"},
+//!    "targets": [{"target": "Kitchen.co2"}], "maxDataPoints": 500}
+//!   ```
+//!
+//!   with a [`TimeSeries`] per target, values in the units of
+//!   [`SERIES`] and times in milliseconds since the Unix epoch:
+//!
+//!   ```json
+//!   [{"target": "Kitchen.co2", "datapoints": [[850, 1714564800000]]}]
+//!   ```
+//!
+//!   All fields of the query are optional, without targets all series are
+//!   returned. `GET /grafana/query` returns all series, for the Infinity
+//!   datasource.
+
+use crate::CurrentReading;
+use btleplug::api::BDAddr;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Number of readings kept per device by default, a day at the default
+/// interval of five minutes.
+pub const DEFAULT_CAPACITY: usize = 288;
+
+/// Suffix of the series name and value of a series of a device.
+pub type Series = (&'static str, fn(&CurrentReading) -> Option<f32>);
+
+/// The series of each device, in the units of [`CurrentReading`].
+pub const SERIES: [Series; 5] = [
+    ("co2", |reading| reading.valid_co2().map(f32::from)),
+    ("temperature", |reading| Some(reading.temperature)),
+    ("humidity", |reading| Some(f32::from(reading.humidity))),
+    ("pressure", |reading| reading.pressure),
+    ("battery", |reading| Some(f32::from(reading.battery))),
+];
+
+/// The latest readings of each device, oldest first and at most `capacity`
+/// per device.
+#[derive(Clone, Debug)]
+pub struct RecentReadings {
+    capacity: usize,
+    devices:  BTreeMap<BDAddr, RecentDevice>,
+}
+
+#[derive(Clone, Debug)]
+struct RecentDevice {
+    name:     String,
+    readings: VecDeque<(DateTime<Utc>, CurrentReading)>,
+}
+
+/// Body of a `/grafana/query` request.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Query {
+    /// Only return readings within the range.
+    #[serde(default)]
+    pub range:           Option<Range>,
+    /// Series to return, all if empty.
+    #[serde(default)]
+    pub targets:         Vec<Target>,
+    /// Only return this many of the newest readings of each series.
+    #[serde(default)]
+    pub max_data_points: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Range {
+    pub from: DateTime<Utc>,
+    pub to:   DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Target {
+    pub target: String,
+}
+
+/// A series in the response to a query, with `[value, time]` pairs and the
+/// time in milliseconds since the Unix epoch.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TimeSeries {
+    pub target:     String,
+    pub datapoints: Vec<(f32, i64)>,
+}
+
+impl RecentReadings {
+    #[must_use]
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            devices: BTreeMap::new(),
+        }
+    }
+
+    /// Add a reading of the device with `address` and friendly `name`, read
+    /// at `now`, dropping the oldest reading when full.
+    ///
+    /// The reading is timed at its measurement, `now` minus its age. The same
+    /// measurement read twice is kept once.
+    pub fn push(
+        &mut self,
+        address: BDAddr,
+        name: &str,
+        now: DateTime<Utc>,
+        reading: CurrentReading,
+    ) {
+        let time = now - Duration::seconds(i64::from(reading.age));
+        let device = self.devices.entry(address).or_insert_with(|| RecentDevice {
+            name:     name.to_owned(),
+            readings: VecDeque::with_capacity(self.capacity),
+        });
+        name.clone_into(&mut device.name);
+        if device
+            .readings
+            .back()
+            .is_some_and(|&(last, _)| (time - last).num_seconds().abs() <= 1)
+        {
+            return;
+        }
+        if device.readings.len() == self.capacity {
+            device.readings.pop_front();
+        }
+        if self.capacity > 0 {
+            device.readings.push_back((time, reading));
+        }
+    }
+
+    /// Names of all series, `<device name>.<series>` for each device and
+    /// [`SERIES`].
+    #[must_use]
+    pub fn targets(&self) -> Vec<String> {
+        self.devices
+            .values()
+            .flat_map(|device| {
+                SERIES
+                    .iter()
+                    .map(move |(series, _)| format!("{}.{series}", device.name))
+            })
+            .collect()
+    }
+
+    /// The series requested by `query`, in the order of its targets. Unknown
+    /// targets are omitted.
+    #[must_use]
+    pub fn query(&self, query: &Query) -> Vec<TimeSeries> {
+        let targets = if query.targets.is_empty() {
+            self.targets()
+        } else {
+            query
+                .targets
+                .iter()
+                .map(|target| target.target.clone())
+                .collect()
+        };
+        targets
+            .into_iter()
+            .filter_map(|target| self.series(target, query))
+            .collect()
+    }
+
+    fn series(&self, target: String, query: &Query) -> Option<TimeSeries> {
+        let (name, series) = target.rsplit_once('.')?;
+        let (_, value) = SERIES.iter().find(|(suffix, _)| *suffix == series)?;
+        let device = self.devices.values().find(|device| device.name == name)?;
+        let mut datapoints = device
+            .readings
+            .iter()
+            .filter(|(time, _)| {
+                query
+                    .range
+                    .is_none_or(|range| (range.from..=range.to).contains(time))
+            })
+            .filter_map(|(time, reading)| Some((value(reading)?, time.timestamp_millis())))
+            .collect::<Vec<_>>();
+        if let Some(max) = query.max_data_points {
+            datapoints.drain(..datapoints.len().saturating_sub(max));
+        }
+        Some(TimeSeries { target, datapoints })
+    }
+}
+
+impl Default for RecentReadings {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
@@ -0,0 +1,699 @@
+//! Helpers for working with downloaded history.
+
+use crate::{
+    absolute_humidity, characteristics::HISTORY_NOTIFIER, dew_point, AranetError, CurrentReading,
+    ParseError, Sensor,
+};
+use btleplug::api::ValueNotification;
+use bytes::{Buf, BufMut};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, Utc};
+use futures::{future, Stream, StreamExt};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    ops::{Range, RangeInclusive},
+    pin::Pin,
+    str::FromStr,
+};
+
+/// History samples with the time they were measured.
+pub type TimedSamples = Vec<(DateTime<Utc>, f32)>;
+
+/// Timestamps for `count` history samples, oldest first.
+///
+/// The newest sample was measured at `newest` and each earlier sample one
+/// `interval` before its successor.
+pub fn sample_times(
+    newest: DateTime<Utc>,
+    interval: Duration,
+    count: usize,
+) -> impl Iterator<Item = DateTime<Utc>> {
+    (0..count).map(move |i| {
+        let steps = i32::try_from(count - 1 - i).unwrap_or(i32::MAX);
+        newest - interval * steps
+    })
+}
+
+/// Timestamp history samples relative to a current reading taken at `now`.
+///
+/// All samples are assumed to be the current measurement interval apart. The
+/// device does not record when its interval was changed, so after a change
+/// the timestamps of samples recorded before it are wrong. See
+/// [`AranetClient::read_history_timed`](crate::AranetClient::read_history_timed).
+#[must_use]
+pub fn timestamp_samples(
+    reading: &CurrentReading,
+    now: DateTime<Utc>,
+    samples: Vec<f32>,
+) -> TimedSamples {
+    History::new(reading, now, samples).into_timed()
+}
+
+/// History samples of a sensor with the interval and time of the newest
+/// sample, from which the time of every sample follows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct History {
+    /// Time between samples.
+    pub interval:         std::time::Duration,
+    /// When the newest sample was measured.
+    pub last_sample_time: DateTime<Utc>,
+    /// Samples oldest first, NaN where missing.
+    pub samples:          Vec<f32>,
+}
+
+impl History {
+    /// History of `samples` downloaded while `reading`, taken at `now`, was
+    /// current. See [`timestamp_samples`] for the caveats.
+    #[must_use]
+    pub fn new(reading: &CurrentReading, now: DateTime<Utc>, samples: Vec<f32>) -> Self {
+        Self {
+            interval: reading.interval(),
+            last_sample_time: now - Duration::seconds(reading.age.into()),
+            samples,
+        }
+    }
+
+    /// When each sample was measured, oldest first.
+    pub fn times(&self) -> impl Iterator<Item = DateTime<Utc>> {
+        let interval = Duration::from_std(self.interval).unwrap_or(Duration::MAX);
+        sample_times(self.last_sample_time, interval, self.samples.len())
+    }
+
+    #[must_use]
+    pub fn into_timed(self) -> TimedSamples {
+        self.times().zip(self.samples).collect()
+    }
+}
+
+/// Dew point in °C and absolute humidity in g/m³ computed from the temperature
+/// and humidity history, named `dew_point` and `absolute_humidity`.
+///
+/// Samples are matched by time. Empty if either sensor is missing.
+#[must_use]
+pub fn derived_history(readings: &[(Sensor, TimedSamples)]) -> Vec<(&'static str, TimedSamples)> {
+    let find = |sensor| {
+        readings
+            .iter()
+            .find(|(found, _)| *found == sensor)
+            .map(|(_, samples)| samples)
+    };
+    let (Some(temperature), Some(humidity)) = (find(Sensor::Temperature), find(Sensor::Humidity))
+    else {
+        return Vec::new();
+    };
+    let pairs = temperature
+        .iter()
+        .filter_map(|&(timestamp, temperature)| {
+            let &(_, humidity) = humidity.iter().find(|&&(time, _)| time == timestamp)?;
+            Some((timestamp, temperature, humidity))
+        })
+        .collect::<Vec<_>>();
+    let series = |derive: fn(f32, f32) -> f32| {
+        pairs
+            .iter()
+            .map(|&(timestamp, temperature, humidity)| (timestamp, derive(temperature, humidity)))
+            .collect()
+    };
+    vec![
+        ("dew_point", series(dew_point)),
+        ("absolute_humidity", series(absolute_humidity)),
+    ]
+}
+
+/// Combine series into rows of the samples measured at the same time, oldest
+/// first, with one value per series in the given order.
+///
+/// Samples are matched by timestamp. All sensors are timestamped relative to
+/// the same current reading, so series of different lengths align on their
+/// newest sample. Series without a sample at the time of a row get NaN.
+#[must_use]
+pub fn align_history(series: &[&TimedSamples]) -> Vec<(DateTime<Utc>, Vec<f32>)> {
+    let mut rows = BTreeMap::<DateTime<Utc>, Vec<f32>>::new();
+    for (column, samples) in series.iter().enumerate() {
+        for &(timestamp, value) in *samples {
+            rows.entry(timestamp)
+                .or_insert_with(|| vec![f32::NAN; series.len()])[column] = value;
+        }
+    }
+    rows.into_iter().collect()
+}
+
+/// How [`resample`] combines the samples in a bucket.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Aggregation {
+    #[default]
+    Mean,
+    Min,
+    Max,
+    /// The newest sample.
+    Last,
+}
+
+impl Aggregation {
+    /// Combine non-empty `values`.
+    #[allow(clippy::cast_precision_loss)]
+    fn apply(self, values: &[f32]) -> f32 {
+        match self {
+            Self::Mean => values.iter().sum::<f32>() / values.len() as f32,
+            Self::Min => values.iter().copied().fold(f32::INFINITY, f32::min),
+            Self::Max => values.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+            Self::Last => values.last().copied().unwrap_or(f32::NAN),
+        }
+    }
+}
+
+/// Parses `mean`, `min`, `max` or `last`, ignoring case.
+impl FromStr for Aggregation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mean" => Ok(Self::Mean),
+            "min" => Ok(Self::Min),
+            "max" => Ok(Self::Max),
+            "last" => Ok(Self::Last),
+            _ => Err(format!(
+                "unknown aggregation {s:?}, expected mean, min, max or last"
+            )),
+        }
+    }
+}
+
+/// Combine samples, oldest first, into buckets of `width` aligned to the Unix
+/// epoch, each timestamped with its start.
+///
+/// Missing samples are ignored and buckets without samples omitted.
+#[must_use]
+pub fn resample(
+    samples: &[(DateTime<Utc>, f32)],
+    width: Duration,
+    aggregation: Aggregation,
+) -> TimedSamples {
+    let width = width.num_seconds().max(1);
+    let mut buckets: Vec<(i64, Vec<f32>)> = Vec::new();
+    for &(timestamp, value) in samples {
+        if value.is_nan() {
+            continue;
+        }
+        let start = timestamp.timestamp().div_euclid(width) * width;
+        match buckets.last_mut() {
+            Some((bucket, values)) if *bucket == start => values.push(value),
+            _ => buckets.push((start, vec![value])),
+        }
+    }
+    buckets
+        .into_iter()
+        .filter_map(|(start, values)| {
+            Some((
+                DateTime::from_timestamp(start, 0)?,
+                aggregation.apply(&values),
+            ))
+        })
+        .collect()
+}
+
+/// Statistics of the samples of a sensor, for a quick look at a download.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Summary {
+    pub min:    f32,
+    pub max:    f32,
+    pub mean:   f32,
+    /// The newest sample.
+    pub latest: f32,
+    /// Number of samples summarized.
+    pub count:  usize,
+}
+
+impl Summary {
+    /// Summarize `samples`, oldest first, ignoring missing samples. `None` if
+    /// all are missing.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    pub fn of(samples: impl IntoIterator<Item = f32>) -> Option<Self> {
+        let mut summary: Option<Self> = None;
+        let mut sum = 0.0_f64;
+        for value in samples.into_iter().filter(|value| !value.is_nan()) {
+            sum += f64::from(value);
+            let summary = summary.get_or_insert(Self {
+                min:    value,
+                max:    value,
+                mean:   value,
+                latest: value,
+                count:  0,
+            });
+            summary.min = summary.min.min(value);
+            summary.max = summary.max.max(value);
+            summary.latest = value;
+            summary.count += 1;
+        }
+        summary.map(|summary| Self {
+            mean: (sum / summary.count as f64) as f32,
+            ..summary
+        })
+    }
+}
+
+/// Parse a positive duration such as `90s`, `15m` or `1h30m`, see
+/// [`humantime::parse_duration`] for the format.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let duration =
+        humantime::parse_duration(s).map_err(|error| format!("invalid duration {s:?}: {error}"))?;
+    Duration::from_std(duration)
+        .ok()
+        .filter(|&duration| duration > Duration::zero())
+        .ok_or_else(|| format!("invalid duration {s:?}, expected for example 15m or 1h30m"))
+}
+
+/// Start of a window of history, see [`samples_since`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Since {
+    /// A duration before now.
+    Ago(Duration),
+    /// A point in time.
+    At(DateTime<Utc>),
+}
+
+impl Since {
+    #[must_use]
+    pub fn start(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Self::Ago(duration) => now - duration,
+            Self::At(time) => time,
+        }
+    }
+}
+
+impl FromStr for Since {
+    type Err = String;
+
+    /// Parse a duration like [`parse_duration`], an RFC 3339 time such as
+    /// `2024-01-01T12:00:00Z`, or a date such as `2024-01-01` meaning its
+    /// local midnight.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(duration) = parse_duration(s) {
+            return Ok(Self::Ago(duration));
+        }
+        if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+            return Ok(Self::At(time.to_utc()));
+        }
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| {
+                date.and_time(NaiveTime::MIN)
+                    .and_local_timezone(Local)
+                    .earliest()
+            })
+            .map(|time| Self::At(time.to_utc()))
+            .ok_or_else(|| {
+                format!(
+                    "invalid time {s:?}, expected for example 24h, 2024-01-01 or an RFC 3339 time"
+                )
+            })
+    }
+}
+
+/// Number of samples measured at or after `start`, of `stored` samples
+/// `interval` apart with the newest measured at `newest`.
+///
+/// All `stored` samples if the window starts before the oldest, none if it
+/// starts after the newest.
+#[must_use]
+pub fn samples_since(
+    newest: DateTime<Utc>,
+    interval: Duration,
+    start: DateTime<Utc>,
+    stored: u16,
+) -> u16 {
+    if start > newest {
+        return 0;
+    }
+    let steps = (newest - start).num_seconds() / interval.num_seconds().max(1);
+    u16::try_from(steps.saturating_add(1)).map_or(stored, |count| count.min(stored))
+}
+
+/// One-based index of the sample measured at `time`, of `stored` samples
+/// `interval` apart with the newest measured at `newest`.
+///
+/// Once the history is full the device drops its oldest sample for every new
+/// one, so the index of a sample shifts while the number stored stays the
+/// same. Half an interval of clock drift is tolerated. `None` if the sample is
+/// no longer stored, or is newer than the newest.
+#[must_use]
+pub fn sample_index(
+    newest: DateTime<Utc>,
+    interval: Duration,
+    time: DateTime<Utc>,
+    stored: u16,
+) -> Option<u16> {
+    let interval = interval.num_seconds().max(1);
+    let newer = ((newest - time).num_seconds() + interval / 2).div_euclid(interval);
+    if newer < 0 {
+        return None;
+    }
+    let index = i64::from(stored) - newer;
+    u16::try_from(index).ok().filter(|&index| index > 0)
+}
+
+/// A request for history samples, the contents of the [`HISTORY_RANGE`]
+/// characteristic.
+///
+/// The packet is `82 <sensor> 0000 <start> <end>` with little endian `u16`s:
+/// `0x82` is the history request command, the sensor is its [`Sensor::id`],
+/// the two bytes after it are the [`param`](Self::param), and `start` and
+/// `end` are inclusive one-based sample indices. The device sends the samples
+/// in the range that it stores as [`HISTORY_NOTIFIER`] notifications.
+///
+/// [`HISTORY_RANGE`]: crate::characteristics::HISTORY_RANGE
+/// [`HISTORY_NOTIFIER`]: crate::characteristics::HISTORY_NOTIFIER
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistoryRange {
+    pub sensor: Sensor,
+    /// Zero in every request seen from the official app and ignored by the
+    /// firmware versions tested. Kept so a request read back from the device
+    /// is written unchanged.
+    pub param:  u16,
+    /// One-based index of the first sample.
+    pub start:  u16,
+    /// One-based index of the last sample, inclusive.
+    pub end:    u16,
+}
+
+impl HistoryRange {
+    /// Command byte of a history request.
+    pub const COMMAND: u8 = 0x82;
+
+    /// Request the samples from `start` to `end` of `sensor`.
+    #[must_use]
+    pub const fn new(sensor: Sensor, start: u16, end: u16) -> Self {
+        Self {
+            sensor,
+            param: 0,
+            start,
+            end,
+        }
+    }
+
+    /// Request the newest `count` of the `stored` samples of `sensor`, or all
+    /// of them if fewer are stored.
+    #[must_use]
+    pub const fn newest(sensor: Sensor, stored: u16, count: u16) -> Self {
+        Self::new(
+            sensor,
+            stored.saturating_sub(count).saturating_add(1),
+            stored,
+        )
+    }
+
+    /// Limit the range to the samples of a device storing `stored` samples.
+    #[must_use]
+    pub fn clamp(self, stored: u16) -> Self {
+        Self {
+            start: self.start.max(1),
+            end: self.end.min(stored),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; 8] {
+        let mut data = [0_u8; 8];
+        let mut writer = &mut data[..];
+        writer.put_u8(Self::COMMAND);
+        writer.put_u8(self.sensor.id());
+        writer.put_u16_le(self.param);
+        writer.put_u16_le(self.start);
+        writer.put_u16_le(self.end);
+        data
+    }
+
+    /// Parse a history request, failing on other commands or unknown sensors.
+    pub fn parse(mut data: &[u8]) -> Result<Self, AranetError> {
+        ParseError::ensure_remaining(&data, 8)?;
+        let command = data.get_u8();
+        if command != Self::COMMAND {
+            return Err(AranetError::InvalidData(format!(
+                "not a history range, command {command:#04x}"
+            )));
+        }
+        let id = data.get_u8();
+        let sensor = Sensor::from_id(id)
+            .ok_or_else(|| AranetError::InvalidData(format!("unknown sensor id {id}")))?;
+        Ok(Self {
+            sensor,
+            param: data.get_u16_le(),
+            start: data.get_u16_le(),
+            end: data.get_u16_le(),
+        })
+    }
+}
+
+/// Samples of a sensor in one [`HISTORY_NOTIFIER`] notification.
+///
+/// The notification is `<sensor> <index> <length> <values>` with the sensor
+/// its [`Sensor::id`], `index` a little endian `u16` and `length` the number
+/// of values that follow, each [`Sensor::size`] bytes.
+///
+/// [`HISTORY_NOTIFIER`]: crate::characteristics::HISTORY_NOTIFIER
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryChunk {
+    pub sensor: Sensor,
+    /// One-based index of the first sample.
+    pub index:  u16,
+    /// Decoded samples, see [`Sensor::read`].
+    pub values: Vec<f32>,
+}
+
+impl HistoryChunk {
+    /// Parse the value of a history notification, `None` for sensors this
+    /// crate does not know. Fails if the notification is truncated.
+    pub fn parse(data: &[u8]) -> Result<Option<Self>, AranetError> {
+        let mut reader = data;
+        if reader.remaining() < 4 {
+            return Err(AranetError::InvalidData(format!(
+                "history notification too short: {data:02x?}"
+            )));
+        }
+        let sensor_id = reader.get_u8();
+        let index = reader.get_u16_le();
+        let length = reader.get_u8();
+        let Some(sensor) = Sensor::from_id(sensor_id) else {
+            return Ok(None);
+        };
+        if reader.remaining() < usize::from(length) * sensor.size() {
+            return Err(AranetError::InvalidData(format!(
+                "history notification for {} {length} samples too short: {data:02x?}",
+                sensor.name()
+            )));
+        }
+        let values = (0..length)
+            .map(|_| sensor.read(&mut reader))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(Self {
+            sensor,
+            index,
+            values,
+        }))
+    }
+
+    /// Number of samples.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// One-based indices of the samples.
+    #[must_use]
+    pub fn indices(&self) -> Range<usize> {
+        let start = usize::from(self.index);
+        start..start + self.len()
+    }
+}
+
+/// Stream of [`HistoryChunk`]s, see [`aranet_notifications`].
+pub type HistoryChunks = Pin<Box<dyn Stream<Item = Result<HistoryChunk, AranetError>> + Send>>;
+
+/// The history chunks among `notifications`, in the order they arrive.
+///
+/// Notifications of other characteristics, such as the current reading, and
+/// of unknown sensors are skipped, so one subscription can serve downloads of
+/// several sensors. Truncated notifications are errors, the stream continues
+/// after them.
+pub fn aranet_notifications(
+    notifications: impl Stream<Item = ValueNotification>,
+) -> impl Stream<Item = Result<HistoryChunk, AranetError>> {
+    notifications.filter_map(|notification| {
+        future::ready(if notification.uuid == HISTORY_NOTIFIER.uuid {
+            HistoryChunk::parse(&notification.value).transpose()
+        } else {
+            None
+        })
+    })
+}
+
+/// Result of a history download that may have lost samples in transit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryResult {
+    /// Samples oldest first, NaN where missing.
+    pub samples: Vec<f32>,
+    /// One-based indices of the samples not received.
+    pub missing: Vec<usize>,
+}
+
+impl HistoryResult {
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Progress of a history download, allowing it to be resumed after a
+/// disconnect.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryDownload {
+    sensor:      Sensor,
+    /// One-based index of the first sample to download.
+    first_index: usize,
+    samples:     Vec<f32>,
+    received:    Vec<bool>,
+    /// One past the highest one-based index received.
+    next_index:  usize,
+}
+
+impl HistoryDownload {
+    /// Start a download of `count` samples, none of which are received yet.
+    #[must_use]
+    pub fn new(sensor: Sensor, count: u16) -> Self {
+        Self::starting_at(sensor, count, 1)
+    }
+
+    /// Start a download of the samples from one-based index `first_index` up
+    /// to `count`.
+    #[must_use]
+    pub fn starting_at(sensor: Sensor, count: u16, first_index: u16) -> Self {
+        let first_index = usize::from(first_index.max(1));
+        let len = usize::from(count).saturating_sub(first_index - 1);
+        Self {
+            sensor,
+            first_index,
+            samples: vec![f32::NAN; len],
+            received: vec![false; len],
+            next_index: first_index,
+        }
+    }
+
+    #[must_use]
+    pub const fn sensor(&self) -> Sensor {
+        self.sensor
+    }
+
+    /// One-based index of the first sample in [`Self::samples`].
+    #[must_use]
+    pub const fn first_index(&self) -> usize {
+        self.first_index
+    }
+
+    /// Samples so far, oldest first. Samples not yet received are NaN.
+    #[must_use]
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    #[must_use]
+    pub fn into_samples(self) -> Vec<f32> {
+        self.samples
+    }
+
+    #[must_use]
+    pub fn into_result(self) -> HistoryResult {
+        let missing = self.missing();
+        HistoryResult {
+            samples: self.samples,
+            missing,
+        }
+    }
+
+    /// One past the highest one-based index received, where the download
+    /// resumes.
+    #[must_use]
+    pub const fn next_index(&self) -> usize {
+        self.next_index
+    }
+
+    /// Number of samples received so far.
+    #[must_use]
+    pub fn received(&self) -> usize {
+        self.received.iter().filter(|&&received| received).count()
+    }
+
+    /// Number of samples to download.
+    #[must_use]
+    pub const fn expected(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Whether the device has sent up to the last sample, possibly with gaps.
+    #[must_use]
+    pub const fn reached_end(&self) -> bool {
+        self.next_index >= self.first_index + self.samples.len()
+    }
+
+    /// Whether all samples are received.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.received.iter().all(|&received| received)
+    }
+
+    /// One-based indices of samples not received.
+    #[must_use]
+    pub fn missing(&self) -> Vec<usize> {
+        (0..self.received.len())
+            .filter(|&i| !self.received[i])
+            .map(|i| i + self.first_index)
+            .collect()
+    }
+
+    /// Ranges of one-based indices of samples not received, before
+    /// [`Self::next_index`].
+    #[must_use]
+    pub fn gaps(&self) -> Vec<RangeInclusive<usize>> {
+        let mut gaps: Vec<RangeInclusive<usize>> = Vec::new();
+        for index in self.missing() {
+            if index >= self.next_index {
+                break;
+            }
+            match gaps.last_mut() {
+                Some(gap) if *gap.end() + 1 == index => *gap = *gap.start()..=index,
+                _ => gaps.push(index..=index),
+            }
+        }
+        gaps
+    }
+
+    /// Whether any sample in `range` of one-based indices is missing.
+    #[must_use]
+    pub fn is_missing(&self, range: &RangeInclusive<usize>) -> bool {
+        range.clone().any(|index| {
+            index
+                .checked_sub(self.first_index)
+                .and_then(|i| self.received.get(i))
+                .is_some_and(|&received| !received)
+        })
+    }
+
+    /// Store the sample with the given one-based index.
+    pub fn receive(&mut self, index: usize, value: f32) {
+        let Some(i) = index.checked_sub(self.first_index) else {
+            return;
+        };
+        if let Some(sample) = self.samples.get_mut(i) {
+            *sample = value;
+            self.received[i] = true;
+            self.next_index = self.next_index.max(index + 1);
+        }
+    }
+}
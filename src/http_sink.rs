@@ -0,0 +1,165 @@
+//! Posting readings as JSON to a custom HTTP endpoint.
+//!
+//! With a batch size of one every reading is posted on its own as a
+//! [`ReadingPayload`] object, such as
+//!
+//! ```json
+//! {"address": "01:02:03:04:05:06", "name": "office", "timestamp": "2024-05-01T12:00:00Z",
+//!  "co2": 850, "co2_valid": true, "temperature_c": 21.5, "humidity": 45, ...}
+//! ```
+//!
+//! With larger batches the body is an array of these.
+
+pub use crate::batch::{BUFFER_CAP, FLUSH_INTERVAL};
+use crate::{batch::BatchSender, CurrentReading, HttpError};
+use btleplug::api::BDAddr;
+use chrono::{DateTime, Utc};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client,
+};
+use serde::Serialize;
+use std::{io, time::Duration};
+
+/// Timeout of a single attempt.
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// JSON of a reading posted to the endpoint, in the units of the device.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ReadingPayload {
+    pub address:   String,
+    /// Friendly name of the device, if configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name:      Option<String>,
+    /// When the reading was measured.
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub reading:   CurrentReading,
+}
+
+/// Endpoint and request options of an [`HttpSink`].
+#[derive(Clone, Debug)]
+pub struct HttpSinkConfig {
+    pub url:        String,
+    /// Sent with every request, such as an `Authorization` header.
+    pub headers:    HeaderMap,
+    /// Number of readings posted in one request, at least one.
+    pub batch_size: usize,
+}
+
+impl HttpSinkConfig {
+    /// Config posting each reading on its own with the `headers`, given as
+    /// `Name: value`.
+    pub fn new(url: &str, headers: &[String]) -> Result<Self, HttpError> {
+        Ok(Self {
+            url:        url.to_owned(),
+            headers:    headers
+                .iter()
+                .map(|header| parse_header(header))
+                .collect::<Result<_, _>>()?,
+            batch_size: 1,
+        })
+    }
+
+    #[must_use]
+    pub fn with_batch_size(self, batch_size: usize) -> Self {
+        Self {
+            batch_size: batch_size.max(1),
+            ..self
+        }
+    }
+}
+
+/// Parse a header given as `Name: value`.
+pub fn parse_header(header: &str) -> Result<(HeaderName, HeaderValue), HttpError> {
+    let invalid = |message: String| HttpError::InvalidRequest(message);
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| invalid(format!("invalid header {header:?}, expected `Name: value`")))?;
+    Ok((
+        HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|error| invalid(format!("invalid header name {name:?}: {error}")))?,
+        HeaderValue::from_str(value.trim())
+            .map_err(|error| invalid(format!("invalid value of header {name:?}: {error}")))?,
+    ))
+}
+
+/// Client posting readings in batches to a URL.
+#[derive(Debug)]
+pub struct HttpSink {
+    readings: BatchSender<ReadingPayload>,
+}
+
+impl HttpSink {
+    /// Start posting to the endpoint of `config`.
+    ///
+    /// A task is spawned that posts a batch once it is full, and incomplete
+    /// batches every [`FLUSH_INTERVAL`], so this must be called within a
+    /// Tokio runtime. Failed posts are logged and retried on the next flush,
+    /// use [`Self::close`] to wait until all readings are posted.
+    pub fn connect(config: HttpSinkConfig) -> Result<Self, HttpError> {
+        let client = Client::builder()
+            .timeout(POST_TIMEOUT)
+            .default_headers(config.headers.clone())
+            .build()
+            .map_err(|source| HttpError::Request {
+                url: config.url.clone(),
+                source,
+            })?;
+        let destination = config.url.clone();
+        let readings = BatchSender::spawn(destination, config.batch_size, move |readings| {
+            let client = client.clone();
+            let config = config.clone();
+            async move { post(&client, &config, &readings).await }
+        });
+        Ok(Self { readings })
+    }
+
+    /// Post the reading of the device with `address` and friendly `name`
+    /// measured at `timestamp`.
+    pub fn write_reading(
+        &self,
+        address: BDAddr,
+        name: Option<&str>,
+        reading: &CurrentReading,
+        timestamp: DateTime<Utc>,
+    ) -> Result<(), HttpError> {
+        self.readings.send(ReadingPayload {
+            address: address.to_string(),
+            name: name.map(str::to_owned),
+            timestamp,
+            reading: *reading,
+        })
+    }
+
+    /// Post the buffered readings, giving up after `timeout`.
+    pub async fn close(self, timeout: Duration) -> Result<(), HttpError> {
+        self.readings.close(timeout).await
+    }
+}
+
+/// Post readings as a JSON body, a single object with a batch size of one.
+async fn post(
+    client: &Client,
+    config: &HttpSinkConfig,
+    readings: &[ReadingPayload],
+) -> Result<(), HttpError> {
+    let body = if config.batch_size == 1 {
+        serde_json::to_vec(&readings[0])
+    } else {
+        serde_json::to_vec(readings)
+    };
+    let body = body.map_err(io::Error::from)?;
+    client
+        .post(&config.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|source| HttpError::Request {
+            url: config.url.clone(),
+            source,
+        })?;
+    Ok(())
+}
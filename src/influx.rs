@@ -0,0 +1,123 @@
+//! Writing readings to an `InfluxDB` v2 server over HTTP.
+
+pub use crate::batch::{BUFFER_CAP, FLUSH_INTERVAL};
+use crate::{
+    batch::BatchSender,
+    export::{write_history_influx, write_reading_influx},
+    history::TimedSamples,
+    CurrentReading, HttpError, Sensor,
+};
+use btleplug::api::BDAddr;
+use chrono::{DateTime, Utc};
+use reqwest::{header::AUTHORIZATION, Client};
+use std::time::Duration;
+
+/// Maximum number of lines written in one request. Reaching it also triggers
+/// a write before the [`FLUSH_INTERVAL`].
+pub const BATCH_SIZE: usize = 500;
+
+/// Timeout of a single attempt.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Location of and credentials for an `InfluxDB` v2 bucket.
+#[derive(Clone, Debug)]
+pub struct InfluxConfig {
+    /// Base url of the server, such as `http://localhost:8086`.
+    pub url:    String,
+    pub org:    String,
+    pub bucket: String,
+    /// API token with write access to the bucket.
+    pub token:  String,
+}
+
+/// Client writing readings in batches to the `/api/v2/write` endpoint.
+#[derive(Debug)]
+pub struct InfluxWriter {
+    lines:       BatchSender<String>,
+    measurement: String,
+}
+
+impl InfluxWriter {
+    /// Start writing to the bucket of `config` with points named
+    /// `measurement`.
+    ///
+    /// A task is spawned that writes buffered lines every [`FLUSH_INTERVAL`]
+    /// or once [`BATCH_SIZE`] are buffered, so this must be called within a
+    /// Tokio runtime. Failed writes are retried on the next flush, use
+    /// [`Self::close`] to wait until all lines are written.
+    pub fn connect(config: InfluxConfig, measurement: &str) -> Result<Self, HttpError> {
+        let client = Client::builder()
+            .timeout(WRITE_TIMEOUT)
+            .build()
+            .map_err(|source| HttpError::Request {
+                url: config.url.clone(),
+                source,
+            })?;
+        let lines = BatchSender::spawn("InfluxDB".to_owned(), BATCH_SIZE, move |lines| {
+            let client = client.clone();
+            let config = config.clone();
+            async move { write(&client, &config, &lines).await }
+        });
+        Ok(Self {
+            lines,
+            measurement: measurement.to_owned(),
+        })
+    }
+
+    /// Write the reading of the device with `address` measured at
+    /// `timestamp`, see [`write_reading_influx`].
+    pub fn write_reading(
+        &self,
+        address: BDAddr,
+        reading: &CurrentReading,
+        timestamp: DateTime<Utc>,
+    ) -> Result<(), HttpError> {
+        let mut data = Vec::new();
+        write_reading_influx(&mut data, &self.measurement, address, reading, timestamp)?;
+        self.send(&data)
+    }
+
+    /// Write downloaded history of the device with `address`, see
+    /// [`write_history_influx`].
+    pub fn write_history(
+        &self,
+        address: BDAddr,
+        readings: &[(Sensor, TimedSamples)],
+    ) -> Result<(), HttpError> {
+        let mut data = Vec::new();
+        write_history_influx(&mut data, &self.measurement, address, readings)?;
+        self.send(&data)
+    }
+
+    fn send(&self, data: &[u8]) -> Result<(), HttpError> {
+        for line in String::from_utf8_lossy(data).lines() {
+            self.lines.send(line.to_owned())?;
+        }
+        Ok(())
+    }
+
+    /// Write the buffered lines, giving up after `timeout`.
+    pub async fn close(self, timeout: Duration) -> Result<(), HttpError> {
+        self.lines.close(timeout).await
+    }
+}
+
+/// Post lines to the write endpoint.
+async fn write(client: &Client, config: &InfluxConfig, lines: &[String]) -> Result<(), HttpError> {
+    let url = format!("{}/api/v2/write", config.url.trim_end_matches('/'));
+    let body = lines.iter().fold(String::new(), |mut body, line| {
+        body.push_str(line);
+        body.push('\n');
+        body
+    });
+    client
+        .post(&url)
+        .query(&[("org", &config.org), ("bucket", &config.bucket)])
+        .header(AUTHORIZATION, format!("Token {}", config.token))
+        .body(body)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|source| HttpError::Request { url, source })?;
+    Ok(())
+}
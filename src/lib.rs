@@ -0,0 +1,61 @@
+#![doc = include_str!("../Readme.md")]
+#![warn(clippy::all, clippy::pedantic, clippy::cargo, clippy::nursery)]
+#![allow(clippy::multiple_crate_versions, clippy::cargo_common_metadata)]
+#![allow(clippy::missing_errors_doc)]
+
+mod advertisement;
+pub mod alert;
+mod batch;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod builder;
+mod capabilities;
+pub mod characteristics;
+mod client;
+mod clock;
+pub mod config;
+mod device_info;
+mod device_type;
+pub mod discovery;
+pub mod dump;
+mod error;
+pub mod export;
+pub mod grafana;
+pub mod history;
+pub mod http_sink;
+pub mod influx;
+pub mod metrics;
+pub mod mqtt;
+mod pairing;
+mod parse_error;
+pub mod radiation;
+mod reading;
+mod retry;
+mod sensor;
+mod session;
+pub mod smoothing;
+pub mod sqlite;
+mod status;
+pub mod sync_state;
+pub mod units;
+pub mod webhook;
+
+pub use crate::{
+    advertisement::{parse_manufacturer_data, MANUFACTURER_ID},
+    builder::AranetClientBuilder,
+    capabilities::Capabilities,
+    client::AranetClient,
+    clock::{parse_current_time, CLOCK_DRIFT_WARNING},
+    device_info::{DeviceInfo, InfoTable},
+    device_type::DeviceType,
+    error::{AranetError, HttpError},
+    pairing::PairingRequired,
+    parse_error::ParseError,
+    reading::{
+        parse_current_reading, parse_short_reading, CurrentReading, ReadingDisplay, STALE_SLACK,
+    },
+    retry::{read_with_retry, READ_ATTEMPTS},
+    sensor::{absolute_humidity, dew_point, Sensor},
+    session::{AdapterRecovery, AranetSession, ADAPTER_RESET_FAILURES, RECONNECT_ATTEMPTS},
+    status::Status,
+};
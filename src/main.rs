@@ -1,197 +1,2238 @@
 #![doc = include_str!("../Readme.md")]
 #![warn(clippy::all, clippy::pedantic, clippy::cargo, clippy::nursery)]
+#![allow(clippy::multiple_crate_versions, clippy::cargo_common_metadata)]
 
+use axum::{
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
 use btleplug::{
-    api::{Central, Manager as _, Peripheral, ScanFilter, WriteType},
-    platform::{Adapter, Manager},
+    api::{BDAddr, Central, Peripheral, PeripheralProperties},
+    platform::{self, Adapter, Manager},
 };
-use bytes::{Buf, BufMut, Bytes};
-use color_eyre::eyre::Result;
-use futures::stream::StreamExt;
-use std::time::Duration;
-use tokio::time;
-
-pub mod characteristics {
-    use btleplug::api::{CharPropFlags, Characteristic};
-    use uuid::{uuid, Uuid};
-
-    // Aranet BLE uuids.
-    // See <https://github.com/Anrijs/Aranet4-Python/blob/master/docs/UUIDs.md>
-    // See <https://github.com/stijnstijn/pyaranet4/blob/f144d504434aa0d597c4694f659244561c225e3c/pyaranet4/pyaranet4.py#L32>
-    const ARANET4_SERVICE: Uuid = uuid!("f0cd1400-95da-4f4b-9ac8-aa55d312af0c");
-    const BLUETOOTH_SERVICE: Uuid = uuid!("0000180a-0000-1000-8000-00805f9b34fb");
-
-    pub const SERIAL_NUMBER: Characteristic = Characteristic {
-        service_uuid: BLUETOOTH_SERVICE,
-        uuid:         uuid!("00002a25-0000-1000-8000-00805f9b34fb"),
-        properties:   CharPropFlags::READ,
-    };
+use chrono::{DateTime, Utc};
+use clap::{
+    parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+};
+use color_eyre::eyre::{bail, eyre, Report, Result, WrapErr};
+use cotracker::{
+    absolute_humidity,
+    alert::{AlertEvent, Co2Alert},
+    characteristics::{CURRENT_READING_FULL, HISTORY_RANGE, STORED_READINGS},
+    config::Config,
+    dew_point,
+    discovery::{
+        all_adapters, assign_adapters, connect, discover_aranets, find_by_address,
+        reacquire_adapter, scan, scan_filter, select_adapter, DiscoveredDevice,
+        CONNECTIONS_PER_ADAPTER, DEFAULT_NAME_PREFIX,
+    },
+    dump::write_hex_dump,
+    export::{
+        write_history_csv, write_history_csv_wide, write_history_influx, write_reading_influx,
+        ExportWriter,
+    },
+    grafana::{self, RecentReadings},
+    history::{
+        self, derived_history, parse_duration, samples_since, timestamp_samples, Aggregation,
+        HistoryRange, Since, Summary, TimedSamples,
+    },
+    http_sink::{HttpSink, HttpSinkConfig},
+    influx::{InfluxConfig, InfluxWriter},
+    metrics::{write_metrics, DeviceReading},
+    mqtt::MqttPublisher,
+    parse_manufacturer_data, read_with_retry,
+    smoothing::{ReadingSmoother, Smoothed},
+    sqlite::Database,
+    sync_state::{SensorSync, SyncState},
+    units::{PressureUnit, TemperatureUnit, Units},
+    webhook::{AlertPayload, Webhook},
+    AranetClient, AranetSession, CurrentReading, DeviceInfo, DeviceType, Sensor, Status,
+    MANUFACTURER_ID, READ_ATTEMPTS,
+};
+use futures::future;
+use indicatif::{ProgressBar, ProgressStyle};
+use rumqttc::QoS;
+use serde::Serialize;
+use std::{
+    collections::{btree_map::Entry, BTreeMap, BTreeSet},
+    fs::File,
+    io::{self, BufWriter, IsTerminal, Write},
+    net::SocketAddr,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{net::TcpListener, process, signal, time};
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::EnvFilter;
 
-    pub const CURRENT_READING_FULL: Characteristic = Characteristic {
-        service_uuid: ARANET4_SERVICE,
-        uuid:         uuid!("f0cd3001-95da-4f4b-9ac8-aa55d312af0c"),
-        properties:   CharPropFlags::READ,
-    };
+/// Tool to fetch Aranet4 data.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    pub const STORED_READINGS: Characteristic = Characteristic {
-        service_uuid: ARANET4_SERVICE,
-        uuid:         uuid!("f0cd2001-95da-4f4b-9ac8-aa55d312af0c"),
-        properties:   CharPropFlags::READ,
-    };
+    /// Read defaults for the options from this TOML file, options given on
+    /// the command line take precedence.
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
 
-    pub const HISTORY_RANGE: Characteristic = Characteristic {
-        service_uuid: ARANET4_SERVICE,
-        uuid:         uuid!("f0cd1402-95da-4f4b-9ac8-aa55d312af0c"),
-        properties:   CharPropFlags::READ,
-    };
+    /// Only use the device with this address.
+    #[arg(long, global = true, value_name = "MAC")]
+    address: Option<BDAddr>,
 
-    pub const HISTORY_NOTIFIER: Characteristic = Characteristic {
-        service_uuid: ARANET4_SERVICE,
-        uuid:         uuid!("f0cd2003-95da-4f4b-9ac8-aa55d312af0c"),
-        properties:   CharPropFlags::READ.union(CharPropFlags::NOTIFY),
-    };
+    /// Only use devices whose advertised name starts with this, such as
+    /// `Aranet4` to skip other product lines. Use an empty prefix for devices
+    /// renamed in the app.
+    #[arg(long, global = true, value_name = "PREFIX", default_value = DEFAULT_NAME_PREFIX)]
+    name_prefix: String,
+
+    /// Skip devices received weaker than this many dBm, such as -80, to
+    /// ignore those of the neighbors. The signal strength is noisy and only a
+    /// rough proxy for distance, so leave some margin.
+    #[arg(long, global = true, value_name = "DBM", allow_negative_numbers = true)]
+    min_rssi: Option<i16>,
+
+    /// Index, name such as hci0, or address such as 00:1A:7D:DA:71:13 of the
+    /// bluetooth adapter to use, by default the first. Names and addresses are
+    /// only available on Linux.
+    #[arg(long, global = true, value_name = "INDEX|NAME|ADDRESS")]
+    adapter: Option<String>,
+
+    /// Scan with all bluetooth adapters and spread the devices over them, to
+    /// use more devices at once than one adapter can connect to.
+    #[arg(long, global = true, conflicts_with = "adapter")]
+    all_adapters: bool,
+
+    /// Output format.
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Measurement name for `--format influx`.
+    #[arg(long, global = true, value_name = "NAME", default_value = "aranet")]
+    measurement: String,
+
+    /// Also publish readings to the MQTT broker at this url, such as
+    /// `mqtt://localhost:1883`.
+    #[arg(long, global = true, value_name = "URL")]
+    mqtt_broker: Option<String>,
+
+    /// Prefix of the MQTT topics, readings are published to
+    /// `<prefix>/<device>/<value>` where the device is its name from the
+    /// config file or its address.
+    #[arg(long, global = true, value_name = "PREFIX", default_value = "aranet")]
+    mqtt_topic_prefix: String,
+
+    /// MQTT quality of service level.
+    #[arg(long, global = true, value_name = "QOS", default_value_t = 0,
+        value_parser = clap::value_parser!(u8).range(0..=2))]
+    mqtt_qos: u8,
+
+    /// Also write readings and downloaded history to the Influx v2 server
+    /// at this url, such as `http://localhost:8086`. Requires --influx-org,
+    /// --influx-bucket and --influx-token.
+    #[arg(long, global = true, value_name = "URL")]
+    influx_url: Option<String>,
+
+    /// Organization of the Influx bucket.
+    #[arg(long, global = true, value_name = "ORG")]
+    influx_org: Option<String>,
+
+    /// Influx bucket to write to.
+    #[arg(long, global = true, value_name = "BUCKET")]
+    influx_bucket: Option<String>,
+
+    /// Influx API token with write access to the bucket.
+    #[arg(long, global = true, value_name = "TOKEN")]
+    influx_token: Option<String>,
+
+    /// Also post readings as JSON to this url, with the address, name and
+    /// measurement time of the device.
+    #[arg(long, global = true, value_name = "URL")]
+    http_sink: Option<String>,
+
+    /// Header of the requests to --http-sink, such as `Authorization: Bearer
+    /// <token>`. May be repeated.
+    #[arg(
+        long = "header",
+        global = true,
+        value_name = "NAME: VALUE",
+        requires = "http_sink"
+    )]
+    headers: Vec<String>,
+
+    /// Post this many readings at once to --http-sink, as a JSON array.
+    /// Incomplete batches are posted every 10 seconds.
+    #[arg(long, global = true, value_name = "N", requires = "http_sink",
+        value_parser = clap::value_parser!(u16).range(1..))]
+    batch_size: Option<u16>,
+
+    /// Also append readings and downloaded history to this sqlite database,
+    /// created if it does not exist.
+    #[arg(long, global = true, value_name = "PATH")]
+    sqlite: Option<PathBuf>,
+
+    /// Write the output of each device to its own file in this directory,
+    /// named by its name from the config file or its address, such as
+    /// `living_room.csv`. The extension follows --format.
+    #[arg(long, global = true, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+
+    /// Temperature unit: c, f or k.
+    #[arg(long, global = true, value_name = "UNIT", default_value = "c")]
+    units: TemperatureUnit,
+
+    /// Pressure unit: hpa, mmhg or inhg.
+    #[arg(long, global = true, value_name = "UNIT", default_value = "hpa")]
+    pressure_units: PressureUnit,
+
+    /// Seconds to scan for devices. Scanning ends early once the device of
+    /// --address is found.
+    #[arg(long, global = true, value_name = "SECONDS", default_value_t = 2.0)]
+    scan_timeout: f64,
+
+    /// Seconds to wait for a connection to a device before giving up on it.
+    #[arg(long, global = true, value_name = "SECONDS", default_value_t = 15.0)]
+    connect_timeout: f64,
+
+    /// Seconds to wait for history data before giving up on a download.
+    #[arg(long, global = true, value_name = "SECONDS", default_value_t = 10.0)]
+    history_timeout: f64,
+
+    /// Comma separated sensors to download the history of.
+    #[arg(
+        long = "sensor",
+        global = true,
+        value_name = "SENSORS",
+        value_delimiter = ',',
+        default_values_t = Sensor::ALL
+    )]
+    sensors: Vec<Sensor>,
+
+    /// Warn when the battery charge is below this percentage, `read` then
+    /// exits with code 4.
+    #[arg(long, global = true, value_name = "PERCENT",
+        value_parser = clap::value_parser!(u8).range(0..=100))]
+    low_battery: Option<u8>,
+
+    /// Also output the dew point and absolute humidity computed from the
+    /// temperature and humidity.
+    #[arg(long, global = true)]
+    derived: bool,
+
+    /// Log diagnostics to stderr, -v for debug and -vv for trace messages.
+    /// `RUST_LOG` overrides this, for example `RUST_LOG=cotracker=debug`.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Only print the data and errors, for use in scripts.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Friendly names of devices from the config file.
+    #[arg(skip)]
+    device_names: BTreeMap<BDAddr, String>,
 }
 
-#[allow(clippy::wildcard_imports)]
-use characteristics::*;
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print the current reading, the default without a command.
+    Read {
+        /// Print the reading broadcast in the advertisement instead of
+        /// connecting. Requires "Smart Home Integration" enabled on the
+        /// device.
+        #[arg(long)]
+        passive: bool,
 
-#[derive(Clone, Copy, Debug)]
-enum Sensor {
-    Temperature,
-    Humidity,
-    Pressure,
-    CO2,
+        /// Exit with the CO2 status of the reading: 0 for green, 1 for amber,
+        /// 2 for red and 3 if unknown. With several devices the worst status
+        /// is used.
+        #[arg(long)]
+        status_exit: bool,
+    },
+
+    /// Download the stored history.
+    History {
+        /// Remember the last downloaded sample of each device in this file and
+        /// only output newer samples on the next run. Starts over when the
+        /// device was reset since.
+        #[arg(long, value_name = "PATH")]
+        state_file: Option<PathBuf>,
+
+        /// Combine the samples into buckets of this duration, such as 15m or
+        /// 1h, timestamped with their start.
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+        resample: Option<chrono::Duration>,
+
+        /// How samples are combined with --resample: mean, min, max or last.
+        #[arg(
+            long,
+            value_name = "AGG",
+            default_value = "mean",
+            requires = "resample"
+        )]
+        agg: Aggregation,
+
+        /// Timestamp all samples the current measurement interval apart, even
+        /// if the interval changed since the last sync with --state-file. The
+        /// device does not record when the interval changed, so timestamps of
+        /// samples recorded before are wrong either way.
+        #[arg(long, requires = "state_file")]
+        assume_constant_interval: bool,
+
+        /// Only download the most recent COUNT samples of each sensor, or all
+        /// if fewer are stored.
+        #[arg(
+            long,
+            value_name = "COUNT",
+            value_parser = clap::value_parser!(u16).range(1..),
+            conflicts_with = "state_file"
+        )]
+        newest: Option<u16>,
+
+        /// Only download the samples measured since this time, a duration
+        /// before now such as 24h, a date such as 2024-01-01 or an RFC 3339
+        /// time. All stored samples if the device does not go back as far.
+        #[arg(
+            long,
+            value_name = "TIME",
+            conflicts_with_all = ["state_file", "newest"]
+        )]
+        since: Option<Since>,
+
+        /// Write the history to this file instead of stdout, gzip-compressed
+        /// if the name ends in .gz.
+        #[arg(long, value_name = "PATH", conflicts_with = "output_dir")]
+        output: Option<PathBuf>,
+
+        /// Gzip-compress the --output file regardless of its name, or the
+        /// files in --output-dir.
+        #[arg(long)]
+        compress: bool,
+
+        /// Print the minimum, maximum, mean and latest value of each sensor
+        /// instead of the samples. Missing samples are ignored. Only text and
+        /// JSON output are supported.
+        #[arg(long, conflicts_with = "resample")]
+        summary: bool,
+    },
+
+    /// Keep the connection open and print the current reading periodically.
+    Watch(WatchArgs),
+
+    /// Like `watch`, but keep running when a device goes away or reading it
+    /// fails, scanning for it until it returns. Stops cleanly on SIGTERM, for
+    /// running as a service.
+    Daemon {
+        #[command(flatten)]
+        watch: WatchArgs,
+
+        /// Seconds to wait between attempts to reconnect to a device that
+        /// went away.
+        #[arg(long, value_name = "SECONDS", default_value_t = 30.0)]
+        retry_delay: f64,
+    },
+
+    /// Print the model, firmware, battery, measurement interval, stored
+    /// reading count and capabilities of the device.
+    Info,
+
+    /// List discovered devices.
+    List,
+
+    /// Serve the current readings of all devices as Prometheus metrics, and
+    /// recent readings as a Grafana JSON datasource.
+    Serve {
+        /// Address to listen on, metrics are served at `/metrics` and the
+        /// datasource at `/grafana`.
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:9090")]
+        listen: SocketAddr,
+
+        /// Number of recent readings per device kept for the datasource.
+        #[arg(long, value_name = "N", default_value_t = grafana::DEFAULT_CAPACITY)]
+        keep: usize,
+    },
+
+    /// Set the measurement interval of the device.
+    SetInterval {
+        /// Interval of 60, 120, 300 or 600 seconds.
+        seconds: u16,
+    },
+
+    /// Force a CO2 calibration to the fresh air baseline.
+    Calibrate {
+        /// Confirm overwriting the calibration of the device.
+        #[arg(long, required = true)]
+        yes: bool,
+    },
+
+    /// Print hex dumps of the raw current reading, stored readings and history
+    /// range characteristics, for bug reports about unsupported firmware.
+    Dump,
 }
 
-impl Sensor {
-    const fn id(self) -> u8 {
-        match self {
-            Self::Temperature => 1,
-            Self::Humidity => 2,
-            Self::Pressure => 3,
-            Self::CO2 => 4,
+impl Args {
+    /// Parse the command line, filling in options not given from the
+    /// configuration file.
+    fn load() -> Result<Self> {
+        let matches = Self::command().get_matches();
+        let mut args = Self::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+        if let Some(path) = &args.config {
+            args.apply_config(Config::load(path)?, &matches)?;
+        }
+        Ok(args)
+    }
+
+    /// Use the values of `config` for options not given on the command line.
+    fn apply_config(&mut self, config: Config, matches: &ArgMatches) -> Result<()> {
+        let defaulted = |id| matches.value_source(id) != Some(ValueSource::CommandLine);
+        self.adapter = self.adapter.take().or(config.adapter);
+        self.address = self.address.or(config.address);
+        self.mqtt_broker = self.mqtt_broker.take().or(config.mqtt_broker);
+        self.influx_url = self.influx_url.take().or(config.influx_url);
+        self.influx_org = self.influx_org.take().or(config.influx_org);
+        self.influx_bucket = self.influx_bucket.take().or(config.influx_bucket);
+        self.influx_token = self.influx_token.take().or(config.influx_token);
+        self.http_sink = self.http_sink.take().or(config.http_sink);
+        self.low_battery = self.low_battery.or(config.low_battery);
+        if let Some(format) = config.format.filter(|_| defaulted("format")) {
+            self.format = Format::from_str(&format, true)
+                .map_err(|error| eyre!("invalid format in config file: {error}"))?;
+        }
+        if let Some(prefix) = config
+            .mqtt_topic_prefix
+            .filter(|_| defaulted("mqtt_topic_prefix"))
+        {
+            self.mqtt_topic_prefix = prefix;
+        }
+        if let Some(units) = config.units.filter(|_| defaulted("units")) {
+            self.units = units;
+        }
+        if let Some(units) = config
+            .pressure_units
+            .filter(|_| defaulted("pressure_units"))
+        {
+            self.pressure_units = units;
+        }
+        self.min_rssi = self.min_rssi.or(config.min_rssi);
+        if let Some(prefix) = config.name_prefix.filter(|_| defaulted("name_prefix")) {
+            self.name_prefix = prefix;
+        }
+        if let Some(timeout) = config.scan_timeout.filter(|_| defaulted("scan_timeout")) {
+            self.scan_timeout = timeout;
+        }
+        if let Some(timeout) = config
+            .connect_timeout
+            .filter(|_| defaulted("connect_timeout"))
+        {
+            self.connect_timeout = timeout;
+        }
+        if let Some(
+            Command::Watch(WatchArgs { alert, .. })
+            | Command::Daemon {
+                watch: WatchArgs { alert, .. },
+                ..
+            },
+        ) = &mut self.command
+        {
+            alert.co2_threshold = alert.co2_threshold.or(config.co2_threshold);
+            alert.co2_clear = alert.co2_clear.or(config.co2_clear);
+        }
+        self.device_names = config.devices;
+        Ok(())
+    }
+
+    /// Friendly name of the device with `address` from the config file,
+    /// falling back to its advertised `local_name` and then the address.
+    fn device_name(&self, address: BDAddr, local_name: Option<&str>) -> String {
+        self.device_names
+            .get(&address)
+            .map(String::as_str)
+            .or(local_name)
+            .map_or_else(|| address.to_string(), str::to_owned)
+    }
+
+    /// Name of a peripheral, see [`Self::device_name`].
+    async fn peripheral_name(&self, p: &platform::Peripheral) -> Result<String> {
+        let props = p.properties().await?.unwrap_or_default();
+        Ok(self.device_name(p.address(), props.local_name.as_deref()))
+    }
+
+    const fn mqtt_qos(&self) -> QoS {
+        match self.mqtt_qos {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            _ => QoS::ExactlyOnce,
         }
     }
 
-    #[allow(clippy::cast_lossless)]
-    fn read(self, reader: &mut impl Buf) -> f32 {
+    /// The Influx bucket to write to, if `--influx-url` is given.
+    fn influx_config(&self) -> Result<Option<InfluxConfig>> {
+        let Some(url) = &self.influx_url else {
+            return Ok(None);
+        };
+        let required = |value: &Option<String>, option| {
+            value
+                .clone()
+                .ok_or_else(|| eyre!("--influx-url requires --{option}"))
+        };
+        Ok(Some(InfluxConfig {
+            url:    url.clone(),
+            org:    required(&self.influx_org, "influx-org")?,
+            bucket: required(&self.influx_bucket, "influx-bucket")?,
+            token:  required(&self.influx_token, "influx-token")?,
+        }))
+    }
+
+    /// Log filter, by default warnings of dependencies and progress messages
+    /// of this crate.
+    fn log_filter(&self) -> EnvFilter {
+        let default = match self.verbose {
+            _ if self.quiet => return EnvFilter::new("error"),
+            0 => "warn,cotracker=info",
+            1 => "debug",
+            _ => "trace",
+        };
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default))
+    }
+
+    const fn units(&self) -> Units {
+        Units {
+            temperature: self.units,
+            pressure:    self.pressure_units,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Human readable output.
+    Text,
+    /// JSON, one object per device or reading.
+    Json,
+    /// Newline delimited JSON for log shippers, one timestamped object per
+    /// reading flushed immediately. Only supported by `read` and `watch`.
+    Ndjson,
+    /// `timestamp,sensor,value` rows, only supported by `history`.
+    Csv,
+    /// `timestamp,temperature,humidity,pressure,co2` rows with all sensors
+    /// measured at the same time, only supported by `history`.
+    CsvWide,
+    /// Influx line protocol, in the units of the device.
+    Influx,
+}
+
+impl Format {
+    /// Extension of files in this format.
+    const fn extension(self) -> &'static str {
         match self {
-            Self::Temperature => reader.get_u16_le() as f32 / 20.0,
-            Self::Humidity => reader.get_u8() as f32,
-            Self::Pressure => reader.get_u16_le() as f32 / 10.0,
-            Self::CO2 => reader.get_u16_le() as f32,
+            Self::Text => "txt",
+            Self::Json => "json",
+            Self::Ndjson => "ndjson",
+            Self::Csv | Self::CsvWide => "csv",
+            Self::Influx => "lp",
         }
     }
 }
 
+/// Options of `watch` and `daemon`.
+#[derive(Debug, clap::Args)]
+struct WatchArgs {
+    /// Seconds between readings, by default the measurement interval of the
+    /// device.
+    #[arg(long, value_name = "SECONDS")]
+    period: Option<u64>,
+
+    /// Whether to stay connected between readings.
+    #[arg(long, value_enum, default_value_t = ConnectionMode::Persistent)]
+    connection_mode: ConnectionMode,
+
+    /// Also print the moving average of each sensor over the last N
+    /// readings. Sinks such as MQTT still get the raw values.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    smooth: Option<u16>,
+
+    #[command(flatten)]
+    alert: AlertArgs,
+}
+
+/// Options of `watch` for alerting on high CO2 concentrations.
+#[derive(Debug, clap::Args)]
+struct AlertArgs {
+    /// Warn when the CO2 concentration exceeds this many ppm.
+    #[arg(long, value_name = "PPM")]
+    co2_threshold: Option<u16>,
+
+    /// Clear the alert once the CO2 concentration drops below this many ppm,
+    /// by default the threshold.
+    #[arg(long, value_name = "PPM", requires = "co2_threshold")]
+    co2_clear: Option<u16>,
+
+    /// Shell command to run when the alert is raised or cleared. It gets the
+    /// environment variables `ARANET_ALERT` (raised or cleared),
+    /// `ARANET_ADDRESS` and `ARANET_CO2`.
+    #[arg(long, value_name = "COMMAND", requires = "co2_threshold")]
+    on_alert: Option<String>,
+
+    /// Exit with an error when the alert is raised.
+    #[arg(long, requires = "co2_threshold")]
+    exit_on_alert: bool,
+
+    /// Post alerts as JSON to this URL, such as a Slack incoming webhook.
+    #[arg(long, value_name = "URL", requires = "co2_threshold")]
+    webhook_url: Option<String>,
+}
+
+impl AlertArgs {
+    fn alert(&self) -> Option<Co2Alert> {
+        self.co2_threshold
+            .map(|threshold| Co2Alert::new(threshold, self.co2_clear.unwrap_or(threshold)))
+    }
+
+    fn webhook(&self) -> Result<Option<Webhook>> {
+        Ok(self.webhook_url.as_deref().map(Webhook::new).transpose()?)
+    }
+
+    /// Act on an alert `event` for `reading`.
+    async fn handle(
+        &self,
+        event: AlertEvent,
+        address: BDAddr,
+        reading: &CurrentReading,
+        webhook: Option<&Webhook>,
+    ) -> Result<()> {
+        let threshold = self.co2_threshold.unwrap_or_default();
+        let payload = AlertPayload::co2(event, address, threshold, reading, Utc::now());
+        match event {
+            AlertEvent::Raised => warn!("{}", payload.text),
+            AlertEvent::Cleared => info!("{}", payload.text),
+        }
+        if let Some(webhook) = webhook {
+            // Keep watching when the webhook is unreachable.
+            if let Err(error) = webhook.send(&payload).await {
+                warn!("{:#}", Report::new(error));
+            }
+        }
+        let co2 = reading.co2.unwrap_or_default();
+        if let Some(command) = &self.on_alert {
+            let mut child = process::Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .env("ARANET_ALERT", event.name())
+                .env("ARANET_ADDRESS", address.to_string())
+                .env("ARANET_CO2", co2.to_string())
+                .spawn()
+                .wrap_err_with(|| format!("failed to run {command:?}"))?;
+            let command = command.clone();
+            tokio::spawn(async move {
+                match child.wait().await {
+                    Ok(status) if status.success() => {}
+                    Ok(status) => warn!("Alert command {command:?} failed with {status}"),
+                    Err(error) => warn!("Alert command {command:?} failed: {error}"),
+                }
+            });
+        }
+        if event == AlertEvent::Raised && self.exit_on_alert {
+            bail!("CO2 of {address} exceeded {threshold} ppm");
+        }
+        Ok(())
+    }
+}
+
+/// How `watch` treats the connection between readings.
+///
+/// A Bluetooth connection keeps the radio of the device active, so staying
+/// connected drains its battery much faster than the advertising it does
+/// anyway. Connecting costs a few seconds of radio time per reading instead,
+/// which is the better tradeoff for deployments that run for months on one set
+/// of batteries or poll less often than every few minutes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ConnectionMode {
+    /// Stay connected, for frequent readings and the lowest latency.
+    Persistent,
+    /// Disconnect after each reading and reconnect before the next, to save
+    /// the battery of the device.
+    PerPoll,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let manager = Manager::new().await.unwrap();
+    color_eyre::install()?;
+    let args = Args::load()?;
+    tracing_subscriber::fmt()
+        .with_env_filter(args.log_filter())
+        .with_target(args.verbose > 0)
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .init();
+    let command = args.command.as_ref().unwrap_or(&Command::Read {
+        passive:     false,
+        status_exit: false,
+    });
+    if matches!(args.format, Format::Csv | Format::CsvWide)
+        && !matches!(command, Command::History { .. })
+    {
+        bail!("--format csv and csv-wide are only supported by the history command");
+    }
+    if args.format == Format::Ndjson
+        && !matches!(
+            command,
+            Command::Read { .. } | Command::Watch(_) | Command::Daemon { .. }
+        )
+    {
+        bail!("--format ndjson is only supported by the read and watch commands");
+    }
+    if matches!(command, Command::History { summary: true, .. })
+        && !matches!(args.format, Format::Text | Format::Json)
+    {
+        bail!("--summary is only supported with --format text or json");
+    }
+    if let Command::History {
+        output: None,
+        compress: true,
+        ..
+    } = command
+    {
+        if args.output_dir.is_none() {
+            bail!("--compress requires --output or --output-dir");
+        }
+    }
+    if let Command::Daemon { watch, .. } = command {
+        if watch.alert.exit_on_alert {
+            bail!("--exit-on-alert is not supported by daemon, which keeps running");
+        }
+    }
+    let manager = Manager::new()
+        .await
+        .wrap_err("failed to initialize bluetooth manager")?;
 
-    // get the first bluetooth adapter
-    // TODO: support multiple adapters
-    let adapters = manager.adapters().await?;
-    let central = adapters.into_iter().next().unwrap();
+    let centrals = if args.all_adapters {
+        all_adapters(&manager).await?
+    } else {
+        vec![select_adapter(&manager, args.adapter.as_deref()).await?]
+    };
+    let sinks = Sinks::new(&args, command)?;
 
-    // start scanning for devices
-    central.start_scan(ScanFilter::default()).await?;
+    // scan for devices, stopping early only when looking for a single address,
+    // otherwise for the full timeout to find every device in range
+    let address = args.address;
+    let list = matches!(command, Command::List);
+    let scans = centrals.iter().map(|central| {
+        scan(
+            central,
+            scan_filter(),
+            Duration::from_secs_f64(args.scan_timeout),
+            move |props| address.is_some_and(|address| props.address == address),
+        )
+    });
+    future::try_join_all(scans)
+        .await
+        .wrap_err("failed to scan for devices")?;
 
-    // instead of waiting, you can use central.events() to get a stream which will
-    // notify you of new devices, for an example of that see
-    // examples/event_driven_discovery.rs
-    time::sleep(Duration::from_secs(2)).await;
+    if list {
+        return list_devices(&centrals, &args).await;
+    }
 
-    // find the device we're interested in
-    find_aranets(&central).await?;
+    // find the devices we're interested in
+    let peripherals = assign_peripherals(&centrals, &args).await?;
+    if let Command::Serve { listen, keep } = command {
+        return serve(peripherals, &args, *listen, *keep).await;
+    }
+    let exit_code = use_devices(peripherals, &args, command, &sinks).await?;
 
+    io::stdout().flush()?;
+    sinks.close().await?;
+    if exit_code != 0 {
+        std::process::exit(exit_code.into());
+    }
     Ok(())
 }
 
-async fn find_aranets(central: &Adapter) -> Result<()> {
+/// Run `command` on all `peripherals`, returning the highest exit code.
+///
+/// Devices are used concurrently, except for history downloads which are
+/// printed one device after the other. If using any device fails, errors of
+/// the others are logged and the first is returned.
+async fn use_devices(
+    peripherals: Vec<(&Adapter, platform::Peripheral)>,
+    args: &Args,
+    command: &Command,
+    sinks: &Sinks,
+) -> Result<u8> {
+    let mut flows = Vec::new();
+    if matches!(command, Command::History { .. }) {
+        for (central, p) in peripherals {
+            let address = p.address();
+            let result = use_device(central, p, args, command, sinks)
+                .await
+                .wrap_err_with(|| format!("failed to use {address}"));
+            let stop = matches!(result, Ok(ControlFlow::Break(())));
+            flows.push(result);
+            if stop {
+                break;
+            }
+        }
+    } else {
+        let uses = peripherals.into_iter().map(|(central, p)| {
+            let address = p.address();
+            async move {
+                use_device(central, p, args, command, sinks)
+                    .await
+                    .wrap_err_with(|| format!("failed to use {address}"))
+            }
+        });
+        flows = future::join_all(uses).await;
+    }
+
+    let mut exit_code = 0;
+    let mut first_error = None;
+    for flow in flows {
+        match flow {
+            Ok(ControlFlow::Continue(code)) => exit_code = exit_code.max(code),
+            Ok(ControlFlow::Break(())) => {}
+            Err(error) if first_error.is_none() => first_error = Some(error),
+            Err(error) => error!("{error:#}"),
+        }
+    }
+    first_error.map_or(Ok(exit_code), Err)
+}
+
+/// Exit code for `read --status-exit`.
+const fn status_exit_code(status: Status) -> u8 {
+    match status {
+        Status::Green => 0,
+        Status::Amber => 1,
+        Status::Red => 2,
+        Status::Unknown(_) => 3,
+    }
+}
+
+/// Exit code of `read` when the battery is below `--low-battery`.
+const LOW_BATTERY_EXIT_CODE: u8 = 4;
+
+/// Warn when the `battery` charge in % of the device with `address` is below
+/// `--low-battery`, returning whether it is.
+fn check_battery(address: BDAddr, battery: u8, args: &Args) -> bool {
+    let low = args
+        .low_battery
+        .is_some_and(|threshold| battery < threshold);
+    if low {
+        warn!("Battery of {address} is low at {battery}%, replace it soon");
+    }
+    low
+}
+
+/// The devices to use, each with the adapter to connect to it with.
+///
+/// With more than one adapter the devices are spread over the adapters that
+/// found them, see [`assign_adapters`].
+async fn assign_peripherals<'a>(
+    centrals: &'a [Adapter],
+    args: &Args,
+) -> Result<Vec<(&'a Adapter, platform::Peripheral)>> {
+    let mut found = Vec::with_capacity(centrals.len());
+    for central in centrals {
+        found.push(match args.address {
+            // Only fail if no adapter found the device.
+            Some(address) => find_by_address(central, address)
+                .await
+                .into_iter()
+                .collect(),
+            None => find_aranets(central, &args.name_prefix, args.min_rssi).await?,
+        });
+    }
+    if let Some(address) = args.address.filter(|_| found.iter().all(Vec::is_empty)) {
+        bail!("no device with address {address} found during scan");
+    }
+    let sightings = found
+        .iter()
+        .map(|peripherals| peripherals.iter().map(Peripheral::address).collect())
+        .collect::<Vec<_>>();
+    let mut assigned = Vec::new();
+    for ((central, peripherals), addresses) in
+        centrals.iter().zip(found).zip(assign_adapters(&sightings))
+    {
+        if addresses.len() > CONNECTIONS_PER_ADAPTER {
+            warn!(
+                "Using {} devices with one adapter, more than the {CONNECTIONS_PER_ADAPTER} \
+                 connections most adapters support",
+                addresses.len()
+            );
+        }
+        assigned.extend(
+            peripherals
+                .into_iter()
+                .filter(|p| addresses.contains(&p.address()))
+                .map(|p| (central, p)),
+        );
+    }
+    Ok(assigned)
+}
+
+/// Aranet devices found by the scan, once per address.
+async fn find_aranets(
+    central: &Adapter,
+    name_prefix: &str,
+    min_rssi: Option<i16>,
+) -> Result<Vec<platform::Peripheral>> {
+    let mut aranets = Vec::new();
+    let mut addresses = BTreeSet::new();
     for p in central.peripherals().await? {
-        if let Some(props) = p.properties().await? &&
-         let Some(name) = &props.local_name &&
-         name.starts_with("Aranet4") {
-            dbg!(&props);
-            p.connect().await?;
-            p.discover_services().await?;
-            dbg!(&p.characteristics());
+        let Some(props) = p.properties().await? else {
+            continue;
+        };
+        if !DiscoveredDevice::from_properties(&props, name_prefix)
+            .is_some_and(|device| device.has_signal(min_rssi))
+            || !addresses.insert(props.address)
+        {
+            continue;
+        }
+        debug!(?props, "found device");
+        aranets.push(p);
+    }
+    Ok(aranets)
+}
+
+/// Completes on Ctrl-C, or on SIGTERM as sent by systemd to stop a service.
+async fn shutdown_signal() -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            result = signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    signal::ctrl_c().await
+}
 
-            read_aranet(p).await?;
-         }
+/// Run `command` on a scanned device, also writing readings to `sinks`.
+///
+/// Returns the exit code, non-zero only for `read --status-exit`. The device is
+/// disconnected afterwards, also when interrupted with Ctrl-C or SIGTERM in
+/// which case [`ControlFlow::Break`] is returned.
+async fn use_device(
+    central: &Adapter,
+    p: platform::Peripheral,
+    args: &Args,
+    command: &Command,
+    sinks: &Sinks,
+) -> Result<ControlFlow<(), u8>> {
+    if let Command::Read {
+        passive: true,
+        status_exit,
+    } = command
+    {
+        let props = p.properties().await?.unwrap_or_default();
+        let reading = print_advertisement(&props, args, sinks)?;
+        let mut code = match reading {
+            Some(reading) if *status_exit => status_exit_code(reading.status),
+            _ => 0,
+        };
+        if reading
+            .is_some_and(|reading| check_battery(props.address, reading.battery_percent(), args))
+        {
+            code = code.max(LOW_BATTERY_EXIT_CODE);
+        }
+        return Ok(ControlFlow::Continue(code));
+    }
+    let mut device = Device::connect(central, p, args).await?;
+    let flow = tokio::select! {
+        result = run_command(&mut device, args, command, sinks) => {
+            result.map(ControlFlow::Continue)
+        }
+        result = shutdown_signal() => result.map(ControlFlow::Break).map_err(Report::from),
+    };
+    if flow.as_ref().is_ok_and(ControlFlow::is_break) {
+        info!(
+            "Interrupted, disconnecting from {}",
+            device.session.address()
+        );
+    }
+    // Report errors of the command rather than of disconnecting.
+    let disconnected = device.session.disconnect().await;
+    let flow = flow?;
+    disconnected?;
+    Ok(flow)
+}
+
+/// Print the reading of the `read` command. The files of the output
+/// directory get the same lines as with `watch`.
+async fn print_read(
+    device: &Device<'_>,
+    reading: &CurrentReading,
+    args: &Args,
+    sinks: &Sinks,
+) -> Result<()> {
+    let address = device.session.address();
+    match args.format {
+        _ if sinks.output_dir.is_some() => {
+            print_reading(address, &device.name, reading, None, args, sinks)?;
+        }
+        Format::Json => print_json(device, reading, args).await?,
+        Format::Influx | Format::Ndjson => {
+            print_reading(address, &device.name, reading, None, args, sinks)?;
+        }
+        Format::Text | Format::Csv | Format::CsvWide if args.quiet => {
+            println!(
+                "{}",
+                reading
+                    .display(args.units())
+                    .with_color(io::stdout().is_terminal())
+            );
+        }
+        Format::Text | Format::Csv | Format::CsvWide => {
+            read_aranet(device, reading, args.units()).await?;
+        }
     }
     Ok(())
 }
 
-async fn read_aranet(p: impl Peripheral) -> Result<()> {
-    let serial = p.read(&SERIAL_NUMBER).await?;
-    dbg!(&serial);
+/// Print the reading of an Aranet Radon Plus or Aranet Radiation, which do not
+/// fit [`CurrentReading`], returning its status and battery charge in %.
+/// Returns `None` for other devices.
+///
+/// The temperature, humidity and pressure of the Radon Plus are written to the
+/// sinks. The Aranet Radiation has none of these, so it fails if any sink is
+/// given.
+async fn print_radioactivity(
+    device: &Device<'_>,
+    args: &Args,
+    sinks: &Sinks,
+) -> Result<Option<(Status, u8)>> {
+    #[derive(Serialize)]
+    struct Output<'a, T> {
+        address: String,
+        name:    &'a str,
+        #[serde(flatten)]
+        reading: T,
+    }
 
-    let result = p.read(&CURRENT_READING_FULL).await?;
-    let mut reader = &result[..];
-    println!("CO2 = {}", Sensor::CO2.read(&mut reader));
-    println!("Temperature = {}", Sensor::Temperature.read(&mut reader));
-    println!("Pressure = {}", Sensor::Pressure.read(&mut reader));
-    println!("Humidity = {}", Sensor::Humidity.read(&mut reader));
-    println!("Battery = {}", reader.get_u8());
-    println!("Status = {}", reader.get_u8());
-    println!("Interval = {}", reader.get_u16_le());
-    println!("Passed = {}", reader.get_u16_le());
+    let client = device.session.client();
+    let address = device.session.address();
+    let (text, json, status, battery, current) = match client.device_type() {
+        DeviceType::AranetRadon => {
+            let reading = client.radon_reading().await?;
+            let output = Output {
+                address: address.to_string(),
+                name: &device.name,
+                reading,
+            };
+            (
+                reading.to_string(),
+                serde_json::to_string(&output)?,
+                reading.status,
+                reading.battery,
+                Some(CurrentReading::from(reading)),
+            )
+        }
+        DeviceType::AranetRadiation => {
+            if sinks.stores_readings() {
+                bail!(
+                    "the {} has no temperature and humidity to write to MQTT, SQLite, InfluxDB or \
+                     an HTTP sink",
+                    client.device_type()
+                );
+            }
+            let reading = client.radiation_reading().await?;
+            let output = Output {
+                address: address.to_string(),
+                name: &device.name,
+                reading,
+            };
+            (
+                reading.to_string(),
+                serde_json::to_string(&output)?,
+                reading.status,
+                reading.battery,
+                None,
+            )
+        }
+        _ => return Ok(None),
+    };
+    let out = match args.format {
+        Format::Json | Format::Ndjson => json,
+        Format::Text => text,
+        format => bail!(
+            "{format:?} output is not supported for the {}",
+            client.device_type()
+        ),
+    };
+    sinks.output(address, format!("{out}\n").as_bytes())?;
+    if let Some(reading) = current {
+        sinks.reading(address, &reading)?;
+    }
+    Ok(Some((status, battery.min(100))))
+}
 
+/// Run `command` on a connected device, returning the exit code.
+async fn run_command(
+    device: &mut Device<'_>,
+    args: &Args,
+    command: &Command,
+    sinks: &Sinks,
+) -> Result<u8> {
+    match command {
+        Command::Read { status_exit, .. } => {
+            let (status, battery) =
+                if let Some(read) = print_radioactivity(device, args, sinks).await? {
+                    read
+                } else {
+                    let reading = device.session.read_once().await?;
+                    warn_if_stale(device.session.address(), &reading);
+                    print_read(device, &reading, args, sinks).await?;
+                    sinks.reading(device.session.address(), &reading)?;
+                    (reading.status, reading.battery_percent())
+                };
+            let mut code = if *status_exit {
+                status_exit_code(status)
+            } else {
+                0
+            };
+            if check_battery(device.session.address(), battery, args) {
+                code = code.max(LOW_BATTERY_EXIT_CODE);
+            }
+            Ok(code)
+        }
+        Command::History {
+            state_file,
+            resample,
+            agg,
+            assume_constant_interval,
+            newest,
+            since,
+            summary,
+            ..
+        } => {
+            let output = match resample {
+                _ if *summary => HistoryOutput::Summary,
+                Some(width) => HistoryOutput::Resampled(*width, *agg),
+                None => HistoryOutput::Samples,
+            };
+            let window = newest
+                .map(Window::Newest)
+                .or_else(|| since.map(Window::Since));
+            print_history(
+                device,
+                args,
+                state_file.as_deref(),
+                *assume_constant_interval,
+                window,
+                output,
+                sinks,
+            )
+            .await?;
+            Ok(0)
+        }
+        Command::Watch(watch) => {
+            watch_loop(device, watch, args, sinks).await?;
+            Ok(0)
+        }
+        Command::Daemon { watch, retry_delay } => {
+            daemon_loop(
+                device,
+                watch,
+                Duration::from_secs_f64(*retry_delay),
+                args,
+                sinks,
+            )
+            .await;
+            Ok(0)
+        }
+        Command::SetInterval { seconds } => {
+            device.session.client().set_interval(*seconds).await?;
+            println!(
+                "Measurement interval of {} set to {seconds}s",
+                device.session.address()
+            );
+            Ok(0)
+        }
+        Command::Calibrate { .. } => {
+            device.session.client().calibrate().await?;
+            println!("Calibrated CO2 sensor of {}", device.session.address());
+            Ok(0)
+        }
+        Command::Info => {
+            print_info(device, args).await?;
+            Ok(0)
+        }
+        Command::Dump => {
+            dump_characteristics(device).await?;
+            Ok(0)
+        }
+        Command::List | Command::Serve { .. } => {
+            unreachable!("command does not run on a single device")
+        }
+    }
+}
+
+/// Print the device info as a table, or JSON with `--format json`.
+async fn print_info(device: &Device<'_>, args: &Args) -> Result<()> {
+    #[derive(Serialize)]
+    struct JsonInfo<'a> {
+        address: String,
+        /// Friendly name, see [`Args::device_name`].
+        name:    &'a str,
+        #[serde(flatten)]
+        info:    &'a DeviceInfo,
+    }
+
+    let info = device.session.client().device_info().await?;
+    let address = device.session.address();
+    if args.format == Format::Json {
+        let json = JsonInfo {
+            address: address.to_string(),
+            name:    &device.name,
+            info:    &info,
+        };
+        println!("{}", serde_json::to_string(&json)?);
+    } else {
+        println!(
+            "Name = {}\nAddress = {address}\n{}\n",
+            device.name,
+            info.table()
+        );
+    }
+    Ok(())
+}
+
+/// Print hex dumps of the characteristics parsed by the client.
+///
+/// Characteristics that fail to read are reported and skipped.
+async fn dump_characteristics(device: &Device<'_>) -> Result<()> {
+    let p = device.session.client().peripheral();
+    let firmware = device.session.client().firmware_version().await?;
+    // A single print, so dumps of concurrently read devices don't interleave.
+    let mut out = Vec::new();
+    writeln!(
+        out,
+        "Name = {}\nAddress = {}\nFirmware = {firmware}",
+        device.name,
+        device.session.address()
+    )?;
+    for (name, characteristic) in [
+        ("CURRENT_READING_FULL", &CURRENT_READING_FULL),
+        ("STORED_READINGS", &STORED_READINGS),
+        ("HISTORY_RANGE", &HISTORY_RANGE),
+    ] {
+        match read_with_retry(p, characteristic, READ_ATTEMPTS).await {
+            Ok(data) => {
+                writeln!(out, "\n{name} {}", characteristic.uuid)?;
+                write_hex_dump(&mut out, &data)?;
+            }
+            Err(error) => writeln!(out, "\n{name} {} failed: {error}", characteristic.uuid)?,
+        }
+    }
+    io::stdout().write_all(&out)?;
+    Ok(())
+}
+
+/// Print discovered devices, connecting to each to read its device info.
+///
+/// Devices found by more than one adapter are listed once, with the first
+/// adapter that found them.
+async fn list_devices(centrals: &[Adapter], args: &Args) -> Result<()> {
+    let mut listed = BTreeSet::new();
+    for central in centrals {
+        list_adapter_devices(central, args, &mut listed).await?;
+    }
+    Ok(())
+}
+
+/// Print the devices found by `central` that are not in `listed` yet, adding
+/// them.
+async fn list_adapter_devices(
+    central: &Adapter,
+    args: &Args,
+    listed: &mut BTreeSet<BDAddr>,
+) -> Result<()> {
+    for mut device in discover_aranets(central, &args.name_prefix, args.min_rssi).await? {
+        if !listed.insert(device.address) {
+            continue;
+        }
+        device.alias = args.device_names.get(&device.address).cloned();
+        let p = find_by_address(central, device.address).await?;
+        let info = async {
+            connect(&p, Duration::from_secs_f64(args.connect_timeout)).await?;
+            let info = AranetClient::new(p.clone()).device_info().await;
+            p.disconnect().await?;
+            info
+        };
+        match info.await {
+            Ok(info) => println!(
+                "{device} {info}, {} stored readings, supports {}",
+                info.stored, info.capabilities
+            ),
+            Err(error) => {
+                warn!("Failed to read device info of {}: {error}", device.address);
+                println!("{device}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_advertisement(
+    props: &PeripheralProperties,
+    args: &Args,
+    sinks: &Sinks,
+) -> Result<Option<CurrentReading>> {
+    let reading = props
+        .manufacturer_data
+        .get(&MANUFACTURER_ID)
+        .and_then(|data| parse_manufacturer_data(data));
+    if let Some(reading) = &reading {
+        warn_if_stale(props.address, reading);
+        let name = args.device_name(props.address, props.local_name.as_deref());
+        print_reading(props.address, &name, reading, None, args, sinks)?;
+        sinks.reading(props.address, reading)?;
+    } else {
+        warn!(
+            "{} does not broadcast readings, enable Smart Home Integration in the Aranet app",
+            props.address
+        );
+    }
+    Ok(reading)
+}
+
+/// How long to wait for queued MQTT messages to be sent before exiting.
+const MQTT_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for buffered lines to be written to Influx before
+/// exiting.
+const INFLUX_CLOSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait for buffered readings to be posted to the HTTP sink
+/// before exiting.
+const HTTP_SINK_CLOSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Destinations readings are written to besides the output.
+struct Sinks {
+    mqtt:       Option<MqttPublisher>,
+    database:   Option<Database>,
+    influx:     Option<InfluxWriter>,
+    http:       Option<HttpSink>,
+    /// Friendly names used in MQTT topics instead of the address.
+    names:      BTreeMap<BDAddr, String>,
+    /// File given with `history --output` that is written instead of stdout.
+    output:     Option<Mutex<ExportWriter<BufWriter<File>>>>,
+    /// Files per device written instead of stdout.
+    output_dir: Option<OutputDir>,
+}
+
+impl Sinks {
+    /// Connect to the destinations given in `args` and create the output
+    /// files of `command`.
+    fn new(args: &Args, command: &Command) -> Result<Self> {
+        Ok(Self {
+            mqtt:       args
+                .mqtt_broker
+                .as_deref()
+                .map(|url| MqttPublisher::connect(url, &args.mqtt_topic_prefix, args.mqtt_qos()))
+                .transpose()?,
+            database:   args.sqlite.as_deref().map(Database::open).transpose()?,
+            influx:     args
+                .influx_config()?
+                .map(|config| InfluxWriter::connect(config, &args.measurement))
+                .transpose()?,
+            http:       args
+                .http_sink
+                .as_deref()
+                .map(|url| {
+                    let config = HttpSinkConfig::new(url, &args.headers)?
+                        .with_batch_size(args.batch_size.unwrap_or(1).into());
+                    HttpSink::connect(config)
+                })
+                .transpose()?,
+            names:      args.device_names.clone(),
+            output:     match command {
+                Command::History {
+                    output: Some(path),
+                    compress,
+                    ..
+                } => Some(Mutex::new(
+                    ExportWriter::create(path, *compress)
+                        .wrap_err_with(|| format!("failed to create {}", path.display()))?,
+                )),
+                _ => None,
+            },
+            output_dir: args
+                .output_dir
+                .as_deref()
+                .map(|dir| {
+                    let compress = matches!(command, Command::History { compress: true, .. });
+                    OutputDir::create(dir, args.format, compress)
+                })
+                .transpose()?,
+        })
+    }
+
+    /// Write a reading of the device with `address`.
+    fn reading(&self, address: BDAddr, reading: &CurrentReading) -> Result<()> {
+        if let Some(mqtt) = &self.mqtt {
+            let device = self
+                .names
+                .get(&address)
+                .map_or_else(|| address.to_string(), String::clone);
+            mqtt.publish(&device, reading);
+        }
+        let timestamp = Utc::now() - chrono::Duration::seconds(reading.age.into());
+        if let Some(database) = &self.database {
+            database.insert_reading(address, timestamp, reading)?;
+        }
+        if let Some(influx) = &self.influx {
+            influx.write_reading(address, reading, timestamp)?;
+        }
+        if let Some(http) = &self.http {
+            let name = self.names.get(&address).map(String::as_str);
+            http.write_reading(address, name, reading, timestamp)?;
+        }
+        Ok(())
+    }
+
+    /// Write downloaded history of the device with `address`.
+    fn history(&self, address: BDAddr, readings: &[(Sensor, TimedSamples)]) -> Result<()> {
+        if let Some(database) = &self.database {
+            database.insert_history(address, readings)?;
+        }
+        if let Some(influx) = &self.influx {
+            influx.write_history(address, readings)?;
+        }
+        Ok(())
+    }
+
+    /// Write `data` of the device with `address` to its file in the output
+    /// directory, the output file, or stdout without either.
+    fn output(&self, address: BDAddr, data: &[u8]) -> Result<()> {
+        if let Some(output_dir) = &self.output_dir {
+            let name = self
+                .names
+                .get(&address)
+                .map_or_else(|| address.to_string(), String::clone);
+            return output_dir.write(&name, data);
+        }
+        match &self.output {
+            Some(output) => output.lock().unwrap().write_all(data)?,
+            None => io::stdout().lock().write_all(data)?,
+        }
+        Ok(())
+    }
+
+    /// Whether readings are written anywhere besides the output.
+    const fn stores_readings(&self) -> bool {
+        self.mqtt.is_some()
+            || self.database.is_some()
+            || self.influx.is_some()
+            || self.http.is_some()
+    }
+
+    /// Whether output goes to a terminal, to color it.
+    fn is_terminal(&self) -> bool {
+        self.output.is_none() && self.output_dir.is_none() && io::stdout().is_terminal()
+    }
+
+    /// Wait for queued messages to be sent and finish the output file.
+    async fn close(self) -> Result<()> {
+        if let Some(mqtt) = self.mqtt {
+            mqtt.close(MQTT_CLOSE_TIMEOUT).await?;
+        }
+        if let Some(influx) = self.influx {
+            influx.close(INFLUX_CLOSE_TIMEOUT).await?;
+        }
+        if let Some(http) = self.http {
+            http.close(HTTP_SINK_CLOSE_TIMEOUT).await?;
+        }
+        if let Some(output) = self.output {
+            output
+                .into_inner()
+                .unwrap()
+                .finish()
+                .wrap_err("failed to write output file")?;
+        }
+        if let Some(output_dir) = self.output_dir {
+            output_dir.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// The files of `--output-dir`, one per device created on its first output.
+struct OutputDir {
+    dir:       PathBuf,
+    extension: String,
+    compress:  bool,
+    /// Open files by path, devices with the same name share one.
+    files:     Mutex<BTreeMap<PathBuf, ExportWriter<BufWriter<File>>>>,
+}
+
+impl OutputDir {
+    /// Create `dir` if needed, for files in `format`, gzip-compressed with
+    /// `compress`.
+    fn create(dir: &Path, format: Format, compress: bool) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .wrap_err_with(|| format!("failed to create {}", dir.display()))?;
+        let mut extension = format.extension().to_owned();
+        if compress {
+            extension.push_str(".gz");
+        }
+        Ok(Self {
+            dir: dir.to_owned(),
+            extension,
+            compress,
+            files: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Append `data` to the file of the device `name`, flushing it so the
+    /// file can be followed.
+    fn write(&self, name: &str, data: &[u8]) -> Result<()> {
+        let stem = name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        let path = self.dir.join(format!("{stem}.{}", self.extension));
+        let mut files = self.files.lock().unwrap();
+        let file = match files.entry(path.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(
+                ExportWriter::create(&path, self.compress)
+                    .wrap_err_with(|| format!("failed to create {}", path.display()))?,
+            ),
+        };
+        let written = file.write_all(data).and_then(|()| file.flush());
+        drop(files);
+        written.wrap_err_with(|| format!("failed to write {}", path.display()))
+    }
+
+    fn finish(self) -> Result<()> {
+        for (path, file) in self.files.into_inner().unwrap() {
+            file.finish()
+                .wrap_err_with(|| format!("failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// A connected device with the name to show for it.
+struct Device<'a> {
+    /// Friendly name, see [`Args::device_name`].
+    name:    String,
+    session: AranetSession<'a, Adapter>,
+}
+
+impl<'a> Device<'a> {
+    async fn connect(central: &'a Adapter, p: platform::Peripheral, args: &Args) -> Result<Self> {
+        let name = args.peripheral_name(&p).await?;
+        let client = AranetClient::new(p)
+            .with_timeout(Duration::from_secs_f64(args.history_timeout))
+            .with_connect_timeout(Duration::from_secs_f64(args.connect_timeout));
+        let mut session =
+            AranetSession::connect(central, client, Duration::from_secs_f64(args.scan_timeout))
+                .await?;
+        let info = central.adapter_info().await?;
+        session.set_adapter_recovery(move || {
+            let info = info.clone();
+            Box::pin(async move {
+                info!("Re-acquiring bluetooth adapter {info}");
+                let manager = Manager::new().await?;
+                reacquire_adapter(&manager, &info).await
+            })
+        });
+        Ok(Self { name, session })
+    }
+}
+
+async fn read_aranet(device: &Device<'_>, reading: &CurrentReading, units: Units) -> Result<()> {
+    let (name, address) = (&device.name, device.session.address());
+    let serial = device.session.client().serial_number().await?;
+    let firmware = device.session.client().firmware_version().await?;
+    // A single print, so readings of concurrently read devices don't interleave.
     println!(
-        "Temperature = {:?}",
-        read_history(&p, Sensor::Temperature).await?
+        "Name = {name}\nAddress = {address}\nSerial number = {serial}\nFirmware = {firmware}\n{}",
+        reading
+            .display(units)
+            .with_color(io::stdout().is_terminal())
     );
-    println!("Pressure = {:?}", read_history(&p, Sensor::Pressure).await?);
-    println!("Humidity = {:?}", read_history(&p, Sensor::Humidity).await?);
-    println!("CO2 = {:?}", read_history(&p, Sensor::CO2).await?);
+    Ok(())
+}
 
+/// Warn when the device with `address` missed its last measurement.
+fn warn_if_stale(address: BDAddr, reading: &CurrentReading) {
+    if reading.is_stale() {
+        warn!(
+            "Reading of {address} is {}s old but the interval is {}s, check the sensor and clock \
+             of the device",
+            reading.age, reading.interval
+        );
+    }
+}
+
+/// JSON of a reading, with temperature and pressure both in the units of the
+/// device and the chosen units.
+#[derive(Serialize)]
+struct JsonReading<'a> {
+    address:           String,
+    /// Friendly name, see [`Args::device_name`].
+    name:              &'a str,
+    #[serde(flatten)]
+    reading:           &'a CurrentReading,
+    temperature:       f32,
+    temperature_unit:  TemperatureUnit,
+    pressure:          Option<f32>,
+    pressure_unit:     PressureUnit,
+    /// Whether the device missed its last measurement.
+    stale:             bool,
+    /// In the chosen temperature unit, with `--derived`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dew_point:         Option<f32>,
+    /// In g/m³, with `--derived`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    absolute_humidity: Option<f32>,
+    /// Moving averages in the chosen units, with `--smooth`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    smoothed:          Option<BTreeMap<Sensor, f32>>,
+}
+
+impl<'a> JsonReading<'a> {
+    fn new(address: BDAddr, name: &'a str, reading: &'a CurrentReading, args: &Args) -> Self {
+        let units = args.units();
+        let humidity = f32::from(reading.humidity);
+        let derived = |derive: fn(f32, f32) -> f32| {
+            args.derived.then(|| derive(reading.temperature, humidity))
+        };
+        Self {
+            address: address.to_string(),
+            name,
+            reading,
+            temperature: units.temperature.from_celsius(reading.temperature),
+            temperature_unit: units.temperature,
+            pressure: reading.pressure.map(|hpa| units.pressure.from_hpa(hpa)),
+            pressure_unit: units.pressure,
+            stale: reading.is_stale(),
+            dew_point: derived(dew_point).map(|celsius| units.temperature.from_celsius(celsius)),
+            absolute_humidity: derived(absolute_humidity),
+            smoothed: None,
+        }
+    }
+
+    fn with_smoothed(mut self, smoothed: Option<&Smoothed>, units: Units) -> Self {
+        self.smoothed = smoothed.map(|smoothed| {
+            smoothed
+                .averages
+                .iter()
+                .map(|(&sensor, &value)| (sensor, units.convert(sensor, value)))
+                .collect()
+        });
+        self
+    }
+}
+
+async fn print_json(device: &Device<'_>, reading: &CurrentReading, args: &Args) -> Result<()> {
+    #[derive(Serialize)]
+    struct Output<'a> {
+        serial_number:    String,
+        firmware_version: String,
+        #[serde(flatten)]
+        reading:          JsonReading<'a>,
+    }
+
+    let output = Output {
+        serial_number:    device.session.client().serial_number().await?,
+        firmware_version: device.session.client().firmware_version().await?,
+        reading:          JsonReading::new(device.session.address(), &device.name, reading, args),
+    };
+    println!("{}", serde_json::to_string(&output)?);
+    Ok(())
+}
+
+/// Print a reading of the device with `address` and friendly `name` in the
+/// output format, to the output of the device in `sinks`.
+fn print_reading(
+    address: BDAddr,
+    name: &str,
+    reading: &CurrentReading,
+    smoothed: Option<&Smoothed>,
+    args: &Args,
+    sinks: &Sinks,
+) -> Result<()> {
+    let units = args.units();
+    let json = || JsonReading::new(address, name, reading, args).with_smoothed(smoothed, units);
+    let mut out = Vec::new();
+    match args.format {
+        Format::Json => writeln!(out, "{}", serde_json::to_string(&json())?)?,
+        Format::Ndjson => {
+            #[derive(Serialize)]
+            struct Line<'a> {
+                /// When the reading was measured.
+                timestamp: DateTime<Utc>,
+                #[serde(flatten)]
+                reading:   JsonReading<'a>,
+            }
+
+            let line = Line {
+                timestamp: Utc::now() - chrono::Duration::seconds(reading.age.into()),
+                reading:   json(),
+            };
+            serde_json::to_writer(&mut out, &line)?;
+            writeln!(out)?;
+        }
+        Format::Influx => {
+            let age = chrono::Duration::seconds(reading.age.into());
+            let timestamp = Utc::now() - age;
+            write_reading_influx(&mut out, &args.measurement, address, reading, timestamp)?;
+        }
+        Format::Text | Format::Csv | Format::CsvWide => {
+            writeln!(
+                out,
+                "Name = {name}\nAddress = {address}\n{}",
+                reading.display(units).with_color(sinks.is_terminal())
+            )?;
+            if let Some(smoothed) = smoothed {
+                write_smoothed(&mut out, smoothed, units)?;
+            }
+            writeln!(out)?;
+        }
+    }
+    sinks.output(address, &out)?;
+    // Flush every reading, so tailing consumers see it immediately.
+    io::stdout().flush()?;
+    Ok(())
+}
+
+/// Write the moving averages of `--smooth` below a reading table.
+fn write_smoothed(out: &mut impl Write, smoothed: &Smoothed, units: Units) -> io::Result<()> {
+    writeln!(out, "Average of the last {} readings:", smoothed.window)?;
+    for (&sensor, &value) in &smoothed.averages {
+        let value = units.convert(sensor, value);
+        let precision = match sensor {
+            Sensor::Temperature => 2,
+            Sensor::Humidity | Sensor::Pressure => 1,
+            Sensor::CO2 => 0,
+        };
+        writeln!(
+            out,
+            "  {:<12}{value:>7.precision$} {}",
+            sensor.name(),
+            units.symbol(sensor)
+        )?;
+    }
     Ok(())
 }
 
-async fn read_history(p: &impl Peripheral, sensor: Sensor) -> Result<Vec<f32>> {
-    // This will trigger a pairing request.
-    let data = p.read(&STORED_READINGS).await?;
-    let mut reader = &data[..];
-    let num_samples = reader.get_u16_le();
-    dbg!(num_samples);
-
-    // Fetch history range.
-    // 8200 0000 0100 ffff
-    let mut data = [0_u8; 8];
-    let mut writer = &mut data[..];
-    writer.put_u8(0x82); // ?
-    writer.put_u8(sensor.id());
-    writer.put_u16_le(0); // ?
-    writer.put_u16_le(1); // start
-    writer.put_u16_le(0xffff); // end
-    p.write(&HISTORY_RANGE, &data, WriteType::WithoutResponse)
-        .await?;
-    dbg!(Bytes::from(data.to_vec()));
-
-    let mut samples = vec![f32::NAN; num_samples as usize];
-    let mut samples_read = 0;
-
-    // Derive sample timestamps from the interval and time passed values.
-    p.subscribe(&HISTORY_NOTIFIER).await?;
-    let mut notifications = p.notifications().await?;
-    while let Some(notification) = notifications.next().await {
-        if notification.uuid != HISTORY_NOTIFIER.uuid {
+/// Print the current reading every `period`, only returning on errors.
+///
+/// Without a `period` the measurement interval reported by the device is used.
+/// With [`ConnectionMode::Persistent`] the connection is kept open between
+/// readings, with [`ConnectionMode::PerPoll`] it is closed after each reading.
+/// Either way it is re-established when it drops.
+async fn watch_loop(
+    device: &mut Device<'_>,
+    watch: &WatchArgs,
+    args: &Args,
+    sinks: &Sinks,
+) -> Result<()> {
+    let period = watch.period.map(Duration::from_secs);
+    let alert_args = &watch.alert;
+    let mut alert = alert_args.alert();
+    let webhook = alert_args.webhook()?;
+    let webhook = webhook.as_ref();
+    let mut smoothing = watch
+        .smooth
+        .map(|window| ReadingSmoother::new(window.into()));
+    let (address, name) = (device.session.address(), device.name.as_str());
+    device
+        .session
+        .set_keep_connected(watch.connection_mode == ConnectionMode::Persistent);
+    device
+        .session
+        .poll(period, |reading| {
+            let smoothed = smoothing.as_mut().map(|smoothing| smoothing.push(&reading));
+            let event = alert
+                .as_mut()
+                .zip(reading.valid_co2())
+                .and_then(|(alert, co2)| alert.update(co2));
+            async move {
+                warn_if_stale(address, &reading);
+                check_battery(address, reading.battery_percent(), args);
+                print_reading(address, name, &reading, smoothed.as_ref(), args, sinks)?;
+                sinks.reading(address, &reading)?;
+                if let Some(event) = event {
+                    alert_args.handle(event, address, &reading, webhook).await?;
+                }
+                Ok(ControlFlow::Continue(()))
+            }
+        })
+        .await
+}
+
+/// Run [`watch_loop`] forever, waiting `retry_delay` after failures and
+/// scanning for the device until it returns when it went away.
+async fn daemon_loop(
+    device: &mut Device<'_>,
+    watch: &WatchArgs,
+    retry_delay: Duration,
+    args: &Args,
+    sinks: &Sinks,
+) {
+    let address = device.session.address();
+    loop {
+        if let Err(error) = watch_loop(device, watch, args, sinks).await {
+            warn!("Reading {address} failed: {error:#}");
+        }
+        time::sleep(retry_delay).await;
+        let client = device.session.client();
+        if client.peripheral().is_connected().await.unwrap_or(false) {
             continue;
         }
-        let mut reader = &notification.value[..];
-        let sensor_id = reader.get_u8();
-        let index = reader.get_u16_le();
-        let length = reader.get_u8();
-        dbg!((sensor, index, length));
-        assert_eq!(sensor_id, sensor.id());
-        for i in index as usize..index as usize + length as usize {
-            samples[i - 1] = sensor.read(&mut reader);
-            samples_read += 1;
+        info!("Disconnected from {address}, scanning for it");
+        while let Err(error) = device.session.reconnect().await {
+            debug!("{:#}", Report::new(error));
+            time::sleep(retry_delay).await;
+        }
+        info!("Reconnected to {address}");
+    }
+}
+
+/// Which of the stored samples the history command downloads, all by
+/// default.
+#[derive(Clone, Copy, Debug)]
+enum Window {
+    /// Only the most recent count.
+    Newest(u16),
+    /// Only those measured since a time.
+    Since(Since),
+}
+
+/// What the history command prints of the downloaded samples. The sinks get
+/// all samples regardless.
+#[derive(Clone, Copy, Debug)]
+enum HistoryOutput {
+    Samples,
+    /// The samples combined into buckets of a width.
+    Resampled(chrono::Duration, Aggregation),
+    /// Statistics of the samples of each sensor.
+    Summary,
+}
+
+/// Print the history of all sensors. With a `state_file` only samples newer
+/// than those of the previous run are printed, with a `window` only those in
+/// it. See [`HistoryOutput`] for `output`.
+async fn print_history(
+    device: &mut Device<'_>,
+    args: &Args,
+    state_file: Option<&Path>,
+    assume_constant_interval: bool,
+    window: Option<Window>,
+    output: HistoryOutput,
+    sinks: &Sinks,
+) -> Result<()> {
+    // Progress is drawn on stderr and hidden if that is not a terminal.
+    #[allow(clippy::literal_string_with_formatting_args)]
+    let progress = if args.quiet || args.format == Format::Json {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::no_length().with_style(ProgressStyle::with_template(
+            "{msg:>11} [{bar:40}] {pos}/{len} samples",
+        )?)
+    };
+    let client = device.session.client_mut();
+    *client = client.clone().with_progress({
+        let progress = progress.clone();
+        move |download| {
+            progress.set_message(download.sensor().name());
+            progress.set_length(download.expected() as u64);
+            progress.set_position(download.received() as u64);
+        }
+    });
+    let readings = read_history(
+        device,
+        &args.sensors,
+        state_file,
+        assume_constant_interval,
+        window,
+    )
+    .await;
+    progress.finish_and_clear();
+    let mut readings = readings?;
+    sinks.history(device.session.address(), &readings)?;
+    if let HistoryOutput::Resampled(width, aggregation) = output {
+        for (_, samples) in &mut readings {
+            *samples = history::resample(samples, width, aggregation);
+        }
+    }
+    // Buffered so the output of concurrent devices is not interleaved.
+    let mut out = Vec::new();
+    if args.format == Format::Influx {
+        write_history_influx(
+            &mut out,
+            &args.measurement,
+            device.session.address(),
+            &readings,
+        )?;
+        return sinks.output(device.session.address(), &out);
+    }
+    let units = args.units();
+    let mut derived = if args.derived {
+        derived_history(&readings)
+    } else {
+        Vec::new()
+    };
+    for (sensor, samples) in &mut readings {
+        for (_, value) in samples {
+            *value = units.convert(*sensor, *value);
+        }
+    }
+    for (name, samples) in &mut derived {
+        if *name == "dew_point" {
+            for (_, value) in samples {
+                *value = units.temperature.from_celsius(*value);
+            }
+        }
+    }
+    let series = readings
+        .iter()
+        .map(|(sensor, samples)| (sensor.name(), samples))
+        .chain(derived.iter().map(|(name, samples)| (*name, samples)));
+    if matches!(output, HistoryOutput::Summary) {
+        write_summaries(&mut out, series, args.format)?;
+        return sinks.output(device.session.address(), &out);
+    }
+    match args.format {
+        Format::Text => {
+            for (name, samples) in series {
+                let values = samples.iter().map(|&(_, value)| value).collect::<Vec<_>>();
+                writeln!(out, "{name} = {values:?}")?;
+            }
+        }
+        Format::Json => {
+            let series = series.collect::<BTreeMap<_, _>>();
+            serde_json::to_writer(&mut out, &series)?;
+            writeln!(out)?;
+        }
+        Format::Csv => write_history_csv(&mut out, &readings, &derived)?,
+        Format::CsvWide => write_history_csv_wide(&mut out, &readings, &derived)?,
+        Format::Influx => unreachable!("influx output is written in device units"),
+        Format::Ndjson => unreachable!("ndjson is not supported by history"),
+    }
+    sinks.output(device.session.address(), &out)
+}
+
+/// Write a [`Summary`] of each series for `history --summary`, as a table or
+/// as a JSON object by series name with `null` for series without samples.
+fn write_summaries<'a>(
+    out: &mut Vec<u8>,
+    series: impl Iterator<Item = (&'a str, &'a TimedSamples)>,
+    format: Format,
+) -> Result<()> {
+    let summaries = series
+        .map(|(name, samples)| (name, Summary::of(samples.iter().map(|&(_, value)| value))))
+        .collect::<Vec<_>>();
+    if format == Format::Json {
+        serde_json::to_writer(
+            out.by_ref(),
+            &summaries.into_iter().collect::<BTreeMap<_, _>>(),
+        )?;
+        writeln!(out)?;
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "{:<17} {:>8} {:>8} {:>8} {:>8} {:>7}",
+        "sensor", "min", "max", "mean", "latest", "samples"
+    )?;
+    for (name, summary) in summaries {
+        match summary {
+            Some(Summary {
+                min,
+                max,
+                mean,
+                latest,
+                count,
+            }) => writeln!(
+                out,
+                "{name:<17} {min:>8.1} {max:>8.1} {mean:>8.1} {latest:>8.1} {count:>7}"
+            )?,
+            None => writeln!(out, "{name:<17} no samples")?,
+        }
+    }
+    Ok(())
+}
+
+/// Download the newest `count` of the `stored` samples of `sensors`,
+/// timestamped relative to `reading` taken at `now`.
+async fn read_newest_history(
+    device: &mut Device<'_>,
+    sensors: &[Sensor],
+    reading: &CurrentReading,
+    now: DateTime<Utc>,
+    stored: u16,
+    count: u16,
+) -> Result<Vec<(Sensor, TimedSamples)>> {
+    info!(
+        "Downloading the newest {} samples per sensor from {}",
+        count.min(stored),
+        device.session.address()
+    );
+    let mut readings = Vec::new();
+    for &sensor in sensors {
+        let range = HistoryRange::newest(sensor, stored, count);
+        let history = device
+            .session
+            .read_history_since(sensor, range.start - 1)
+            .await?;
+        readings.push((sensor, timestamp_samples(reading, now, history.samples)));
+    }
+    Ok(readings)
+}
+
+/// Download the history of all sensors with timestamps.
+///
+/// With a `state_file` only samples newer than those of the previous run are
+/// downloaded, and the state file is updated. With a `window` only the samples
+/// in it are downloaded.
+async fn read_history(
+    device: &mut Device<'_>,
+    sensors: &[Sensor],
+    state_file: Option<&Path>,
+    assume_constant_interval: bool,
+    window: Option<Window>,
+) -> Result<Vec<(Sensor, TimedSamples)>> {
+    let device_type = device.session.client().device_type();
+    let sensors = Sensor::ALL
+        .into_iter()
+        .filter(|&sensor| sensors.contains(&sensor) && device_type.supports(sensor))
+        .collect::<Vec<_>>();
+    if sensors.is_empty() {
+        bail!("{device_type} has none of the selected sensors");
+    }
+    let reading = device.session.client().current_reading().await?;
+    let now = Utc::now();
+    let stored = device.session.client().stored_reading_count().await?;
+    if let Some(window) = window {
+        let count = match window {
+            Window::Newest(count) => count,
+            Window::Since(since) => samples_since(
+                now - chrono::Duration::seconds(reading.age.into()),
+                chrono::Duration::seconds(reading.interval.into()),
+                since.start(now),
+                stored,
+            ),
+        };
+        return read_newest_history(device, &sensors, &reading, now, stored, count).await;
+    }
+    let Some(state_file) = state_file else {
+        info!(
+            "Downloading {stored} samples per sensor from {}",
+            device.session.address()
+        );
+        let mut history = device.session.read_history_of(&sensors).await?;
+        return Ok(sensors
+            .into_iter()
+            .filter_map(|sensor| {
+                let history = history.remove(&sensor)?;
+                Some((sensor, timestamp_samples(&reading, now, history.samples)))
+            })
+            .collect());
+    };
+
+    let mut state = SyncState::load(state_file)?;
+    let newest = now - chrono::Duration::seconds(reading.age.into());
+    let interval = chrono::Duration::seconds(reading.interval.into());
+    let mut readings = Vec::new();
+    for sensor in sensors {
+        let sync = state.get(device.session.address(), sensor);
+        let synced_interval = sync.and_then(|sync| sync.interval);
+        let synced_index = sync.and_then(|sync| sync.synced_index(stored, newest, interval));
+        if let Some(synced) = synced_interval.filter(|&synced| synced != reading.interval) {
+            if !assume_constant_interval {
+                bail!(
+                    "measurement interval of {} changed from {synced}s to {}s since the last \
+                     sync, timestamps of samples recorded before the change would be wrong; pass \
+                     --assume-constant-interval to download anyway",
+                    device.session.address(),
+                    reading.interval
+                );
+            }
+            warn!(
+                "Measurement interval of {} changed from {synced}s to {}s since the last sync, \
+                 downloading all {} samples assuming {}s between all of them",
+                device.session.address(),
+                reading.interval,
+                sensor.name(),
+                reading.interval
+            );
+            state.remove(device.session.address(), sensor);
+        } else if sync.is_some() && synced_index.is_none() {
+            warn!(
+                "{} history of {} does not continue the last sync, the device was likely reset, \
+                 downloading all samples",
+                sensor.name(),
+                device.session.address()
+            );
+            state.remove(device.session.address(), sensor);
         }
-        if samples_read == num_samples as usize {
-            break;
+        let last_index = synced_index.unwrap_or(0);
+        info!(
+            "Downloading {} new {} samples from {}",
+            stored.saturating_sub(last_index),
+            sensor.name(),
+            device.session.address()
+        );
+        let history = device
+            .session
+            .read_history_since(sensor, last_index)
+            .await?;
+        let samples = timestamp_samples(&reading, now, history.samples);
+        if let Some(&(last_timestamp, _)) = samples.last() {
+            let last_index = usize::from(last_index) + samples.len();
+            state.insert(device.session.address(), sensor, SensorSync {
+                last_index: u16::try_from(last_index)?,
+                last_timestamp,
+                interval: Some(reading.interval),
+            });
         }
+        readings.push((sensor, samples));
     }
-    Ok(samples)
+    state.save(state_file)?;
+    Ok(readings)
+}
+
+/// Serve the current readings of `peripherals` as Prometheus metrics, and the
+/// `keep` most recent readings as a Grafana datasource, on `listen` until
+/// interrupted with Ctrl-C.
+async fn serve(
+    peripherals: Vec<(&Adapter, platform::Peripheral)>,
+    args: &Args,
+    listen: SocketAddr,
+    keep: usize,
+) -> Result<()> {
+    if peripherals.is_empty() {
+        bail!("no Aranet devices found");
+    }
+    let readings = Arc::new(Mutex::new(BTreeMap::new()));
+    let recent = Arc::new(Mutex::new(RecentReadings::new(keep)));
+    let mut polls = Vec::new();
+    for (central, p) in peripherals.iter().cloned() {
+        let device = Device::connect(central, p, args).await?;
+        polls.push(poll_device(
+            device,
+            Arc::clone(&readings),
+            Arc::clone(&recent),
+        ));
+    }
+
+    let app = Router::new()
+        .route(
+            "/metrics",
+            get(move || {
+                let devices = readings
+                    .lock()
+                    .unwrap()
+                    .values()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                async move {
+                    let mut body = String::new();
+                    write_metrics(&mut body, &devices).expect("writing to a string can not fail");
+                    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+                }
+            }),
+        )
+        .merge(grafana_routes(recent));
+    let listener = TcpListener::bind(listen)
+        .await
+        .wrap_err_with(|| format!("failed to listen on {listen}"))?;
+    info!("Serving metrics on http://{listen}/metrics and Grafana on http://{listen}/grafana");
+    tokio::select! {
+        result = axum::serve(listener, app) => result?,
+        result = future::try_join_all(polls) => { result?; }
+        result = shutdown_signal() => {
+            result?;
+            info!("Interrupted, disconnecting");
+        }
+    }
+    for (_, p) in peripherals {
+        p.disconnect().await?;
+    }
+    Ok(())
+}
+
+/// Routes of the Grafana datasource, see [`grafana`].
+fn grafana_routes(recent: Arc<Mutex<RecentReadings>>) -> Router {
+    fn json(value: &impl Serialize) -> Response {
+        (
+            [(CONTENT_TYPE, "application/json")],
+            serde_json::to_string(value).expect("series serialize"),
+        )
+            .into_response()
+    }
+
+    let search = Arc::clone(&recent);
+    let all = Arc::clone(&recent);
+    Router::new()
+        .route("/grafana", get(|| async { StatusCode::OK }))
+        .route(
+            "/grafana/search",
+            post(move || {
+                let targets = search.lock().unwrap().targets();
+                async move { json(&targets) }
+            }),
+        )
+        .route(
+            "/grafana/query",
+            get(move || {
+                let series = all.lock().unwrap().query(&grafana::Query::default());
+                async move { json(&series) }
+            })
+            .post(move |body: String| {
+                let query = if body.trim().is_empty() {
+                    Ok(grafana::Query::default())
+                } else {
+                    serde_json::from_str::<grafana::Query>(&body)
+                };
+                let response = match query {
+                    Ok(query) => json(&recent.lock().unwrap().query(&query)),
+                    Err(error) => {
+                        (StatusCode::BAD_REQUEST, format!("invalid query: {error}")).into_response()
+                    }
+                };
+                async move { response }
+            }),
+        )
+}
+
+/// Store the current reading of `device` in `readings`, and add it to
+/// `recent`, every measurement interval.
+async fn poll_device(
+    mut device: Device<'_>,
+    readings: Arc<Mutex<BTreeMap<BDAddr, DeviceReading>>>,
+    recent: Arc<Mutex<RecentReadings>>,
+) -> Result<()> {
+    let (address, name) = (device.session.address(), device.name);
+    device
+        .session
+        .poll(None, |reading| {
+            recent
+                .lock()
+                .unwrap()
+                .push(address, &name, Utc::now(), reading);
+            readings.lock().unwrap().insert(address, DeviceReading {
+                address,
+                name: name.clone(),
+                reading,
+            });
+            future::ok(ControlFlow::Continue(()))
+        })
+        .await
 }
@@ -0,0 +1,75 @@
+//! Current readings in the Prometheus text exposition format.
+
+use crate::CurrentReading;
+use btleplug::api::BDAddr;
+use std::fmt::{self, Write};
+
+/// The latest reading of a device, labeled for export.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceReading {
+    pub address: BDAddr,
+    pub name:    String,
+    pub reading: CurrentReading,
+}
+
+/// Name, help text and value of a gauge.
+type Gauge = (
+    &'static str,
+    &'static str,
+    fn(&CurrentReading) -> Option<f32>,
+);
+
+const GAUGES: [Gauge; 5] = [
+    ("aranet_co2_ppm", "CO2 concentration in ppm.", |reading| {
+        reading.valid_co2().map(f32::from)
+    }),
+    (
+        "aranet_temperature_celsius",
+        "Temperature in °C.",
+        |reading| Some(reading.temperature),
+    ),
+    (
+        "aranet_humidity_percent",
+        "Relative humidity in %.",
+        |reading| Some(f32::from(reading.humidity)),
+    ),
+    (
+        "aranet_pressure_hpa",
+        "Atmospheric pressure in hPa.",
+        |reading| reading.pressure,
+    ),
+    (
+        "aranet_battery_percent",
+        "Battery charge in %.",
+        |reading| Some(f32::from(reading.battery)),
+    ),
+];
+
+/// Write gauges for the readings of all devices, one label set per device.
+///
+/// Sensors a device does not have are omitted for that device.
+pub fn write_metrics<W: Write>(writer: &mut W, devices: &[DeviceReading]) -> fmt::Result {
+    for (name, help, value) in GAUGES {
+        writeln!(writer, "# HELP {name} {help}")?;
+        writeln!(writer, "# TYPE {name} gauge")?;
+        for device in devices {
+            if let Some(value) = value(&device.reading) {
+                writeln!(
+                    writer,
+                    "{name}{{address=\"{}\",name=\"{}\"}} {value}",
+                    device.address,
+                    escape_label(&device.name)
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Escape a label value as required by the exposition format.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,159 @@
+//! Publishing readings to an MQTT broker.
+
+use crate::{CurrentReading, Sensor};
+use rumqttc::{AsyncClient, ClientError, Event, MqttOptions, OptionError, Outgoing, QoS};
+use serde_json::{json, Value};
+use std::{iter, panic, process, time::Duration};
+use thiserror::Error;
+use tokio::{task::JoinHandle, time};
+use tracing::warn;
+
+/// Delay before reconnecting after the connection to the broker failed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Number of messages queued for the broker, each reading takes up to six.
+pub const QUEUE_CAPACITY: usize = 60;
+
+/// Failure of connecting to or disconnecting from the broker.
+#[derive(Debug, Error)]
+pub enum MqttError {
+    /// The broker `url` is invalid.
+    #[error("invalid MQTT broker url {url}")]
+    InvalidUrl { url: String, source: OptionError },
+    /// The connection task stopped.
+    #[error(transparent)]
+    Client(ClientError),
+    /// Queued messages were not sent within `timeout`.
+    #[error("timed out sending MQTT messages after {}s", .0.as_secs_f64())]
+    Timeout(Duration),
+}
+
+/// Client publishing each reading as JSON to a topic per value.
+#[derive(Debug)]
+pub struct MqttPublisher {
+    client:     AsyncClient,
+    connection: JoinHandle<()>,
+    prefix:     String,
+    qos:        QoS,
+}
+
+impl MqttPublisher {
+    /// Connect to the broker at `url`, such as `mqtt://localhost:1883`.
+    ///
+    /// A task is spawned that drives the connection and reconnects when it
+    /// fails, so this must be called within a Tokio runtime. Publishing only
+    /// queues messages, use [`Self::close`] to wait until they are sent.
+    pub fn connect(url: &str, prefix: &str, qos: QoS) -> Result<Self, MqttError> {
+        let url = if url.contains("client_id=") {
+            url.to_owned()
+        } else {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            format!("{url}{separator}client_id=cotracker-{}", process::id())
+        };
+        let options =
+            MqttOptions::parse_url(&url).map_err(|source| MqttError::InvalidUrl { url, source })?;
+        let (client, mut event_loop) = AsyncClient::new(options, QUEUE_CAPACITY);
+        let connection = tokio::spawn(async move {
+            loop {
+                match event_loop.poll().await {
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                    Ok(_) => {}
+                    Err(error) => {
+                        warn!("MQTT connection failed, reconnecting: {error}");
+                        time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            client,
+            connection,
+            prefix: prefix.trim_end_matches('/').to_owned(),
+            qos,
+        })
+    }
+
+    /// Send the queued messages and disconnect, giving up after `timeout`.
+    ///
+    /// When the broker stays unreachable the connection task is stopped and
+    /// the queued messages are lost.
+    pub async fn close(self, timeout: Duration) -> Result<(), MqttError> {
+        let Self {
+            client,
+            mut connection,
+            ..
+        } = self;
+        let closed = time::timeout(timeout, async {
+            client.disconnect().await.map_err(MqttError::Client)?;
+            Ok((&mut connection).await)
+        })
+        .await;
+        let Ok(closed) = closed else {
+            connection.abort();
+            return Err(MqttError::Timeout(timeout));
+        };
+        if let Err(error) = closed? {
+            if error.is_panic() {
+                panic::resume_unwind(error.into_panic());
+            }
+        }
+        Ok(())
+    }
+
+    /// Publish a reading of `device`, its friendly name or address.
+    ///
+    /// This does not wait for the broker. While it is unreachable the queue
+    /// of [`QUEUE_CAPACITY`] messages fills up, and further readings are
+    /// dropped with a warning rather than blocking the caller. When the queue
+    /// fills partway through a reading, the warning lists the topics lost.
+    pub fn publish(&self, device: &str, reading: &CurrentReading) {
+        let mut topics = reading_topics(&self.prefix, device, reading).into_iter();
+        while let Some((topic, payload)) = topics.next() {
+            if let Err(error) =
+                self.client
+                    .try_publish(&topic, self.qos, false, payload.to_string())
+            {
+                let dropped = iter::once(topic)
+                    .chain(topics.map(|(topic, _)| topic))
+                    .collect::<Vec<_>>();
+                warn!(
+                    "Dropping MQTT messages of {device} to {}: {error}",
+                    dropped.join(", ")
+                );
+                return;
+            }
+        }
+    }
+}
+
+/// Topics and JSON payloads for a reading, such as `aranet/<device>/co2`
+/// with `{"value":850,"unit":"ppm"}`.
+///
+/// Values a device does not have are omitted.
+#[must_use]
+pub fn reading_topics(
+    prefix: &str,
+    device: &str,
+    reading: &CurrentReading,
+) -> Vec<(String, Value)> {
+    let sensor = |sensor: Sensor, value| (sensor.name(), value, sensor.unit());
+    let values = [
+        sensor(Sensor::CO2, reading.valid_co2().map(|co2| json!(co2))),
+        sensor(Sensor::Temperature, Some(json!(reading.temperature))),
+        sensor(Sensor::Humidity, Some(json!(reading.humidity))),
+        sensor(
+            Sensor::Pressure,
+            reading.pressure.map(|pressure| json!(pressure)),
+        ),
+        ("battery", Some(json!(reading.battery)), "%"),
+    ];
+    let topic = |name| format!("{prefix}/{device}/{name}");
+    let mut topics = values
+        .into_iter()
+        .filter_map(|(name, value, unit)| {
+            Some((topic(name), json!({ "value": value?, "unit": unit })))
+        })
+        .collect::<Vec<_>>();
+    topics.push((topic("status"), json!({ "value": reading.status })));
+    topics
+}
@@ -0,0 +1,52 @@
+use crate::AranetError;
+use btleplug::api::BDAddr;
+use thiserror::Error;
+
+/// A device refused a request because it is not paired with this host.
+///
+/// Reading the history, changing settings and calibrating require pairing,
+/// reading the current values does not. Pairing can not be started from here,
+/// btleplug has no API for it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Error)]
+#[error(
+    "{address} requires pairing, pair it first while it shows its PIN, on Linux with \
+     `bluetoothctl pair {address}`"
+)]
+pub struct PairingRequired {
+    pub address: BDAddr,
+}
+
+impl PairingRequired {
+    /// Whether `error` is a device refusing a request for lack of pairing,
+    /// such as the insufficient authentication and encryption errors of
+    /// the Linux bluetooth stack.
+    #[must_use]
+    pub fn is_pairing_error(error: &btleplug::Error) -> bool {
+        match error {
+            btleplug::Error::PermissionDenied => true,
+            btleplug::Error::Other(error) => {
+                let message = error.to_string().to_ascii_lowercase();
+                [
+                    "notauthorized",
+                    "notpermitted",
+                    "authentication",
+                    "encryption",
+                ]
+                .iter()
+                .any(|needle| message.contains(needle))
+            }
+            _ => false,
+        }
+    }
+
+    /// Turn `error` of the device with `address` into
+    /// [`AranetError::PairingRequired`] if it is a pairing error.
+    #[must_use]
+    pub fn check(address: BDAddr, error: btleplug::Error) -> AranetError {
+        if Self::is_pairing_error(&error) {
+            AranetError::PairingRequired(Self { address })
+        } else {
+            AranetError::Bluetooth(error)
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use bytes::Buf;
+use thiserror::Error;
+
+/// A packet ended before all of its fields were read, as happens with
+/// truncated BLE reads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Error)]
+#[error("packet too short, expected {expected} bytes but {remaining} remain")]
+pub struct ParseError {
+    /// Bytes needed for the next field or layout.
+    pub expected:  usize,
+    /// Bytes left in the packet.
+    pub remaining: usize,
+}
+
+impl ParseError {
+    /// Fail unless `buf` holds at least `expected` more bytes.
+    pub fn ensure_remaining(buf: &impl Buf, expected: usize) -> Result<(), Self> {
+        let remaining = buf.remaining();
+        if remaining < expected {
+            return Err(Self {
+                expected,
+                remaining,
+            });
+        }
+        Ok(())
+    }
+}
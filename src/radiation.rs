@@ -0,0 +1,155 @@
+//! Current readings of the Aranet Radon Plus and the Aranet Radiation, which
+//! measure radioactivity rather than air quality.
+//!
+//! Both are read from [`CURRENT_READING_ARANET2`] like the Aranet2, with
+//! their own layouts. Only current readings are supported, not history.
+//!
+//! [`CURRENT_READING_ARANET2`]: crate::characteristics::CURRENT_READING_ARANET2
+
+use crate::{reading::align_rows, ParseError, Sensor, Status};
+use bytes::Buf;
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+
+/// Current reading of an Aranet Radon Plus.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct RadonReading {
+    /// Radon concentration in Bq/m³.
+    #[serde(rename = "radon_bq_m3")]
+    pub radon:       u32,
+    /// Temperature in °C.
+    #[serde(rename = "temperature_c")]
+    pub temperature: f32,
+    /// Atmospheric pressure in hPa.
+    #[serde(rename = "pressure_hpa")]
+    pub pressure:    f32,
+    /// Relative humidity in %.
+    pub humidity:    f32,
+    /// Battery charge in %.
+    pub battery:     u8,
+    /// Radon level indicator.
+    pub status:      Status,
+    /// Measurement interval in seconds.
+    pub interval:    u16,
+    /// Seconds since the last measurement.
+    pub age:         u16,
+}
+
+/// Parse the current reading of an Aranet Radon Plus.
+///
+/// The layout is `type: u16, interval: u16, age: u16, battery: u8,
+/// temperature: u16, pressure: u16, humidity: u16, radon: u32, status: u8`
+/// with humidity in tenths of a percent.
+///
+/// Fails if `buf` is shorter than the layout.
+pub fn parse_radon_reading(buf: &mut impl Buf) -> Result<RadonReading, ParseError> {
+    ParseError::ensure_remaining(buf, 18)?;
+    let _type = buf.get_u16_le();
+    let interval = buf.get_u16_le();
+    let age = buf.get_u16_le();
+    let battery = buf.get_u8();
+    Ok(RadonReading {
+        temperature: Sensor::Temperature.read(buf)?,
+        pressure: Sensor::Pressure.read(buf)?,
+        humidity: f32::from(buf.get_u16_le()) / 10.0,
+        radon: buf.get_u32_le(),
+        status: Status::from_byte(buf.get_u8()),
+        battery,
+        interval,
+        age,
+    })
+}
+
+impl Display for RadonReading {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_table(f, &[
+            ("Radon", self.radon.to_string(), "Bq/m³"),
+            (
+                "Temperature",
+                format!("{:.2}", self.temperature),
+                Sensor::Temperature.unit(),
+            ),
+            (
+                "Humidity",
+                format!("{:.1}", self.humidity),
+                Sensor::Humidity.unit(),
+            ),
+            (
+                "Pressure",
+                format!("{:.1}", self.pressure),
+                Sensor::Pressure.unit(),
+            ),
+            ("Battery", self.battery.to_string(), "%"),
+            ("Status", self.status.to_string(), ""),
+            ("Interval", self.interval.to_string(), "s"),
+            ("Age", self.age.to_string(), "s"),
+        ])
+    }
+}
+
+/// Current reading of an Aranet Radiation.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct RadiationReading {
+    /// Ambient dose equivalent rate in µSv/h.
+    #[serde(rename = "dose_rate_usv_h")]
+    pub dose_rate:  f64,
+    /// Dose accumulated over `duration` in mSv.
+    #[serde(rename = "total_dose_msv")]
+    pub total_dose: f64,
+    /// Seconds the dose has been accumulated for.
+    pub duration:   u64,
+    /// Battery charge in %.
+    pub battery:    u8,
+    /// Radiation level indicator.
+    pub status:     Status,
+    /// Measurement interval in seconds.
+    pub interval:   u16,
+    /// Seconds since the last measurement.
+    pub age:        u16,
+}
+
+/// Parse the current reading of an Aranet Radiation.
+///
+/// The layout is `type: u16, interval: u16, age: u16, battery: u8, dose_rate:
+/// u32, total_dose: u64, duration: u64, status: u8` with the dose rate in
+/// nSv/h and the total dose in nSv.
+///
+/// Fails if `buf` is shorter than the layout.
+#[allow(clippy::cast_precision_loss)]
+pub fn parse_radiation_reading(buf: &mut impl Buf) -> Result<RadiationReading, ParseError> {
+    ParseError::ensure_remaining(buf, 28)?;
+    let _type = buf.get_u16_le();
+    Ok(RadiationReading {
+        interval:   buf.get_u16_le(),
+        age:        buf.get_u16_le(),
+        battery:    buf.get_u8(),
+        dose_rate:  f64::from(buf.get_u32_le()) / 1_000.0,
+        total_dose: buf.get_u64_le() as f64 / 1_000_000.0,
+        duration:   buf.get_u64_le(),
+        status:     Status::from_byte(buf.get_u8()),
+    })
+}
+
+impl Display for RadiationReading {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_table(f, &[
+            ("Dose rate", format!("{:.3}", self.dose_rate), "µSv/h"),
+            ("Total dose", format!("{:.6}", self.total_dose), "mSv"),
+            ("Duration", self.duration.to_string(), "s"),
+            ("Battery", self.battery.to_string(), "%"),
+            ("Status", self.status.to_string(), ""),
+            ("Interval", self.interval.to_string(), "s"),
+            ("Age", self.age.to_string(), "s"),
+        ])
+    }
+}
+
+fn write_table(f: &mut Formatter<'_>, rows: &[(&str, String, &str)]) -> fmt::Result {
+    for (i, (_, row)) in align_rows(rows).enumerate() {
+        if i > 0 {
+            writeln!(f)?;
+        }
+        f.write_str(&row)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,319 @@
+use crate::{
+    radiation::{parse_radon_reading, RadonReading},
+    sensor::is_valid_co2,
+    units::Units,
+    AranetError, DeviceType, ParseError, Sensor, Status,
+};
+use bytes::Buf;
+use serde::Serialize;
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+/// Contents of the [`CURRENT_READING_FULL`] characteristic.
+///
+/// [`CURRENT_READING_FULL`]: crate::characteristics::CURRENT_READING_FULL
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct CurrentReading {
+    /// CO2 concentration in ppm, `None` on devices without CO2 sensor. See
+    /// [`Self::valid_co2`] to skip bogus values.
+    pub co2:         Option<u16>,
+    /// Whether `co2` is a measurement. After power on the sensor warms up
+    /// and reports a sentinel instead, the status byte has no flag for it.
+    pub co2_valid:   bool,
+    /// Temperature in °C.
+    #[serde(rename = "temperature_c")]
+    pub temperature: f32,
+    /// Relative humidity in %.
+    pub humidity:    u8,
+    /// Atmospheric pressure in hPa, `None` on devices without pressure
+    /// sensor.
+    #[serde(rename = "pressure_hpa")]
+    pub pressure:    Option<f32>,
+    /// Battery charge in %, see [`Self::battery_percent`].
+    pub battery:     u8,
+    /// CO2 level indicator. Use [`Status::to_byte`] for the raw value.
+    pub status:      Status,
+    /// Measurement interval in seconds, see [`Self::interval()`].
+    pub interval:    u16,
+    /// Seconds since the last measurement, see [`Self::age()`].
+    pub age:         u16,
+}
+
+/// Parse the current reading of a device.
+///
+/// For the Aranet4 this is the contents of the [`CURRENT_READING_FULL`]
+/// characteristic, for the other devices of [`CURRENT_READING_ARANET2`]. For
+/// the Aranet2 that has layout `type: u16, interval: u16, age: u16, battery:
+/// u8, temperature: u16, humidity: u16, status: u8` with humidity in tenths of
+/// a percent. The Aranet Radon Plus reading is converted from
+/// [`parse_radon_reading`], dropping the radon concentration.
+///
+/// Fails if `buf` is shorter than the layout, or for the Aranet Radiation,
+/// see [`DeviceType::has_current_reading`].
+///
+/// [`CURRENT_READING_FULL`]: crate::characteristics::CURRENT_READING_FULL
+/// [`CURRENT_READING_ARANET2`]: crate::characteristics::CURRENT_READING_ARANET2
+pub fn parse_current_reading(
+    device_type: DeviceType,
+    buf: &mut impl Buf,
+) -> Result<CurrentReading, AranetError> {
+    match device_type {
+        DeviceType::Aranet4 => {
+            ParseError::ensure_remaining(buf, 13)?;
+            let co2 = buf.get_u16_le();
+            Ok(CurrentReading {
+                co2:         Some(co2),
+                co2_valid:   is_valid_co2(co2),
+                temperature: Sensor::Temperature.read(buf)?,
+                pressure:    Some(Sensor::Pressure.read(buf)?),
+                humidity:    buf.get_u8(),
+                battery:     buf.get_u8(),
+                status:      Status::from_byte(buf.get_u8()),
+                interval:    buf.get_u16_le(),
+                age:         buf.get_u16_le(),
+            })
+        }
+        DeviceType::Aranet2 => {
+            ParseError::ensure_remaining(buf, 12)?;
+            let _type = buf.get_u16_le();
+            let interval = buf.get_u16_le();
+            let age = buf.get_u16_le();
+            let battery = buf.get_u8();
+            let temperature = Sensor::Temperature.read(buf)?;
+            // Tenths of a percent, rounded.
+            let raw = buf.get_u16_le();
+            let humidity = u8::try_from((u32::from(raw) + 5) / 10).map_err(|_| {
+                AranetError::InvalidData(format!("humidity of {raw} tenths % out of range"))
+            })?;
+            Ok(CurrentReading {
+                co2: None,
+                co2_valid: false,
+                temperature,
+                humidity,
+                pressure: None,
+                battery,
+                status: Status::from_byte(buf.get_u8()),
+                interval,
+                age,
+            })
+        }
+        DeviceType::AranetRadon => Ok(parse_radon_reading(buf)?.into()),
+        DeviceType::AranetRadiation => Err(AranetError::Unsupported(format!(
+            "{device_type} has no temperature and humidity reading"
+        ))),
+    }
+}
+
+/// Parse the [`CURRENT_READING`] characteristic of an Aranet4, with layout
+/// `co2: u16, temperature: u16, pressure: u16, humidity: u8`.
+///
+/// The characteristic lacks the battery, status, interval and age, which are
+/// zero and [`Status::Unknown`] in the result. Fails if `buf` is too short.
+///
+/// [`CURRENT_READING`]: crate::characteristics::CURRENT_READING
+pub fn parse_short_reading(buf: &mut impl Buf) -> Result<CurrentReading, ParseError> {
+    ParseError::ensure_remaining(buf, 7)?;
+    let co2 = buf.get_u16_le();
+    Ok(CurrentReading {
+        co2:         Some(co2),
+        co2_valid:   is_valid_co2(co2),
+        temperature: Sensor::Temperature.read(buf)?,
+        pressure:    Some(Sensor::Pressure.read(buf)?),
+        humidity:    buf.get_u8(),
+        battery:     0,
+        status:      Status::Unknown(0),
+        interval:    0,
+        age:         0,
+    })
+}
+
+/// How much older than its interval a reading may be before it is considered
+/// stale, to allow for the time a measurement takes.
+pub const STALE_SLACK: Duration = Duration::from_secs(30);
+
+impl CurrentReading {
+    /// The CO2 concentration in ppm if the device has a CO2 sensor and it
+    /// reported a measurement.
+    #[must_use]
+    pub fn valid_co2(&self) -> Option<u16> {
+        self.co2.filter(|_| self.co2_valid)
+    }
+
+    /// The value of `sensor` in [`Sensor::unit`], if the device has the
+    /// sensor and, for CO2, it reported a measurement.
+    #[must_use]
+    pub fn value(&self, sensor: Sensor) -> Option<f32> {
+        match sensor {
+            Sensor::Temperature => Some(self.temperature),
+            Sensor::Humidity => Some(f32::from(self.humidity)),
+            Sensor::Pressure => self.pressure,
+            Sensor::CO2 => self.valid_co2().map(f32::from),
+        }
+    }
+
+    /// Battery charge in %, limited to 100 in case the device reports more.
+    #[must_use]
+    pub const fn battery_percent(&self) -> u8 {
+        if self.battery > 100 {
+            100
+        } else {
+            self.battery
+        }
+    }
+
+    /// Measurement interval.
+    #[must_use]
+    pub const fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval as u64)
+    }
+
+    /// Time since the last measurement.
+    #[must_use]
+    pub const fn age(&self) -> Duration {
+        Duration::from_secs(self.age as u64)
+    }
+
+    /// Seconds until the device takes its next measurement, zero if it is
+    /// overdue.
+    #[must_use]
+    pub const fn seconds_until_next_measurement(&self) -> u16 {
+        self.interval.saturating_sub(self.age)
+    }
+
+    /// Whether the device missed its last measurement by more than
+    /// [`STALE_SLACK`], which usually means a sensor or clock problem.
+    #[must_use]
+    pub fn is_stale(&self) -> bool {
+        self.age() > self.interval().saturating_add(STALE_SLACK)
+    }
+
+    /// Format the reading with values converted to `units`.
+    #[must_use]
+    pub const fn display(&self, units: Units) -> ReadingDisplay<'_> {
+        ReadingDisplay {
+            reading: self,
+            units,
+            color: false,
+        }
+    }
+}
+
+/// The temperature, humidity and pressure of an Aranet Radon Plus, dropping
+/// the radon concentration.
+impl From<RadonReading> for CurrentReading {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn from(radon: RadonReading) -> Self {
+        Self {
+            co2:         None,
+            co2_valid:   false,
+            temperature: radon.temperature,
+            humidity:    radon.humidity.round().clamp(0.0, 255.0) as u8,
+            pressure:    Some(radon.pressure),
+            battery:     radon.battery,
+            status:      radon.status,
+            interval:    radon.interval,
+            age:         radon.age,
+        }
+    }
+}
+
+/// Formats values in the units of the device.
+impl Display for CurrentReading {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.display(Units::default()).fmt(f)
+    }
+}
+
+/// Human readable [`CurrentReading`] in chosen units, see
+/// [`CurrentReading::display`].
+///
+/// Formats a table with a row per value, the values right aligned and followed
+/// by their unit.
+#[derive(Clone, Copy, Debug)]
+pub struct ReadingDisplay<'a> {
+    reading: &'a CurrentReading,
+    units:   Units,
+    color:   bool,
+}
+
+impl ReadingDisplay<'_> {
+    /// Color the CO2 row by the status, using ANSI escape codes. Only enable
+    /// this when writing to a terminal.
+    #[must_use]
+    pub const fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+impl Display for ReadingDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let reading = self.reading;
+        let mut rows = Vec::new();
+        match reading.valid_co2() {
+            Some(co2) => rows.push(("CO2", co2.to_string(), Sensor::CO2.unit())),
+            None if reading.co2.is_some() => rows.push(("CO2", "warming up".to_owned(), "")),
+            None => {}
+        }
+        let temperature = self.units.temperature.from_celsius(reading.temperature);
+        rows.push((
+            "Temperature",
+            format!("{temperature:.2}"),
+            self.units.temperature.symbol(),
+        ));
+        rows.push((
+            "Humidity",
+            reading.humidity.to_string(),
+            Sensor::Humidity.unit(),
+        ));
+        if let Some(pressure) = reading.pressure {
+            let pressure = self.units.pressure.from_hpa(pressure);
+            rows.push((
+                "Pressure",
+                format!("{pressure:.1}"),
+                self.units.pressure.symbol(),
+            ));
+        }
+        rows.push(("Battery", reading.battery_percent().to_string(), "%"));
+        rows.push(("Status", reading.status.to_string(), ""));
+        rows.push(("Interval", reading.interval.to_string(), "s"));
+        rows.push(("Age", reading.age.to_string(), "s"));
+
+        for (i, (label, row)) in align_rows(&rows).enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            match self.color.then(|| status_color(reading.status)).flatten() {
+                Some(color) if label == "CO2" => write!(f, "\x1b[{color}m{row}\x1b[0m")?,
+                _ => f.write_str(&row)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Format `(label, value, unit)` rows of a table, with the labels left and the
+/// values right aligned. Returns the label and the formatted row.
+pub fn align_rows<'a>(
+    rows: &'a [(&'a str, String, &'a str)],
+) -> impl Iterator<Item = (&'a str, String)> + 'a {
+    let label_width = rows.iter().map(|(label, ..)| label.len()).max();
+    let value_width = rows.iter().map(|(_, value, _)| value.len()).max();
+    let (label_width, value_width) = (label_width.unwrap_or(0), value_width.unwrap_or(0));
+    rows.iter().map(move |(label, value, unit)| {
+        let row = format!("{label:<label_width$}  {value:>value_width$} {unit}");
+        (*label, row.trim_end().to_owned())
+    })
+}
+
+/// ANSI color code of a status.
+const fn status_color(status: Status) -> Option<&'static str> {
+    match status {
+        Status::Green => Some("32"),
+        Status::Amber => Some("33"),
+        Status::Red => Some("31"),
+        Status::Unknown(_) => None,
+    }
+}
@@ -0,0 +1,43 @@
+use btleplug::api::{Characteristic, Peripheral};
+use std::{fmt::Display, future::Future, time::Duration};
+use tokio::time;
+use tracing::debug;
+
+/// Number of attempts made for reads by [`AranetClient`](crate::AranetClient).
+pub const READ_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry, doubled on every further attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Run `operation` up to `attempts` times with exponential backoff, returning
+/// the last error if all attempts fail. Retries are logged at debug level.
+pub async fn with_retry<T, E, F, Fut>(attempts: u32, mut operation: F) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut + Send,
+    Fut: Future<Output = Result<T, E>> + Send,
+{
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Err(error) if attempt < attempts => {
+                debug!(attempt, attempts, "retrying after {error}");
+                time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Read a characteristic, retrying up to `attempts` times with exponential
+/// backoff.
+pub async fn read_with_retry(
+    p: &impl Peripheral,
+    characteristic: &Characteristic,
+    attempts: u32,
+) -> btleplug::Result<Vec<u8>> {
+    with_retry(attempts, || p.read(characteristic)).await
+}
@@ -0,0 +1,163 @@
+use crate::ParseError;
+use bytes::Buf;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::RangeInclusive,
+    str::FromStr,
+};
+
+/// Raw CO2 value reported while the sensor is warming up.
+const CO2_WARMUP: u16 = 0xffff;
+
+/// Whether a raw CO2 value is a measurement, rather than the warmup sentinel
+/// or an implausible value.
+pub fn is_valid_co2(raw: u16) -> bool {
+    raw != CO2_WARMUP && Sensor::CO2.plausible_range().contains(&f32::from(raw))
+}
+
+/// The sensors of an Aranet4 for which history is recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sensor {
+    Temperature,
+    Humidity,
+    Pressure,
+    CO2,
+}
+
+impl Sensor {
+    /// All sensors, in the order of their ids.
+    pub const ALL: [Self; 4] = [Self::Temperature, Self::Humidity, Self::Pressure, Self::CO2];
+
+    /// The identifier used for this sensor in history requests and
+    /// notifications.
+    #[must_use]
+    pub const fn id(self) -> u8 {
+        match self {
+            Self::Temperature => 1,
+            Self::Humidity => 2,
+            Self::Pressure => 3,
+            Self::CO2 => 4,
+        }
+    }
+
+    /// The sensor with the given [`Self::id`].
+    #[must_use]
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|sensor| sensor.id() == id)
+    }
+
+    /// Lowercase name of the sensor.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Temperature => "temperature",
+            Self::Humidity => "humidity",
+            Self::Pressure => "pressure",
+            Self::CO2 => "co2",
+        }
+    }
+
+    /// Number of bytes of an encoded value.
+    #[must_use]
+    pub const fn size(self) -> usize {
+        match self {
+            Self::Humidity => 1,
+            Self::Temperature | Self::Pressure | Self::CO2 => 2,
+        }
+    }
+
+    /// Unit of the values of this sensor, as decoded by [`Self::read`].
+    #[must_use]
+    pub const fn unit(self) -> &'static str {
+        match self {
+            Self::Temperature => "°C",
+            Self::Humidity => "%",
+            Self::Pressure => "hPa",
+            Self::CO2 => "ppm",
+        }
+    }
+
+    /// Values in [`Self::unit`] outside this range indicate a corrupt packet
+    /// or broken sensor.
+    #[must_use]
+    pub const fn plausible_range(self) -> RangeInclusive<f32> {
+        match self {
+            Self::Temperature => -40.0..=85.0,
+            Self::Humidity => 0.0..=100.0,
+            Self::Pressure => 300.0..=1100.0,
+            Self::CO2 => 0.0..=10_000.0,
+        }
+    }
+
+    /// Decode a single value for this sensor, failing if `reader` holds fewer
+    /// than [`Self::size`] bytes.
+    ///
+    /// CO2 values reported during sensor warmup are NaN.
+    #[allow(clippy::cast_lossless)]
+    pub fn read(self, reader: &mut impl Buf) -> Result<f32, ParseError> {
+        ParseError::ensure_remaining(reader, self.size())?;
+        Ok(match self {
+            Self::Temperature => reader.get_u16_le() as f32 / 20.0,
+            Self::Humidity => reader.get_u8() as f32,
+            Self::Pressure => reader.get_u16_le() as f32 / 10.0,
+            Self::CO2 => match reader.get_u16_le() {
+                CO2_WARMUP => f32::NAN,
+                raw => raw as f32,
+            },
+        })
+    }
+
+    /// Decode a single value for this sensor, returning `None` for values
+    /// outside the plausible range, CO2 values during warmup and truncated
+    /// values.
+    pub fn read_checked(self, reader: &mut impl Buf) -> Option<f32> {
+        self.read(reader)
+            .ok()
+            .filter(|value| self.plausible_range().contains(value))
+    }
+}
+
+impl Display for Sensor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parses the [`Sensor::name`], or `temp` for the temperature, ignoring case.
+impl FromStr for Sensor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "temp" | "temperature" => Ok(Self::Temperature),
+            "humidity" => Ok(Self::Humidity),
+            "pressure" => Ok(Self::Pressure),
+            "co2" => Ok(Self::CO2),
+            _ => Err(format!(
+                "unknown sensor {s:?}, expected co2, temperature, humidity or pressure"
+            )),
+        }
+    }
+}
+
+/// Dew point in °C for a temperature in °C and relative humidity in %, using
+/// the Magnus formula with the coefficients of Sonntag (1990).
+#[must_use]
+pub fn dew_point(temperature: f32, humidity: f32) -> f32 {
+    const A: f32 = 17.62;
+    const B: f32 = 243.12;
+    let gamma = (humidity / 100.0).ln() + A * temperature / (B + temperature);
+    B * gamma / (A - gamma)
+}
+
+/// Absolute humidity in g/m³ for a temperature in °C and relative humidity in
+/// %.
+#[must_use]
+pub fn absolute_humidity(temperature: f32, humidity: f32) -> f32 {
+    // Saturation vapor pressure in hPa, times the molar mass of water over the
+    // gas constant in g·K/(hPa·m³).
+    let saturation = 6.112 * (17.67 * temperature / (temperature + 243.5)).exp();
+    saturation * humidity * 2.1674 / (273.15 + temperature)
+}
@@ -0,0 +1,364 @@
+//! A connection to a device that survives the link dropping.
+//!
+//! The lifecycle of a session is
+//!
+//! 1. connect: [`AranetSession::connect`] connects to a peripheral found by
+//!    [`scan`](crate::discovery::scan),
+//! 2. discover: it discovers the services and detects the device type,
+//! 3. session: [`AranetSession::read_once`] and [`AranetSession::poll`] read
+//!    the device, reconnecting when it goes away,
+//! 4. drop: dropping the session disconnects in the background, use
+//!    [`AranetSession::disconnect`] to wait for it and see errors.
+//!
+//! When the adapter itself resets, such as on suspend and resume or with
+//! `rfkill`, the handles of the adapter and its peripherals stop working.
+//! Reconnecting then needs a new adapter handle, see
+//! [`AranetSession::set_adapter_recovery`].
+
+use crate::{
+    characteristics::{CURRENT_READING_NOTIFY, HISTORY_NOTIFIER},
+    discovery::reconnect,
+    history::HistoryResult,
+    AranetClient, AranetError, CurrentReading, Sensor,
+};
+use btleplug::api::{BDAddr, Central, Peripheral};
+use futures::future::BoxFuture;
+use std::{collections::HashMap, future::Future, ops::ControlFlow, time::Duration};
+use tokio::{runtime::Handle, time};
+use tracing::{debug, info, warn};
+
+/// Number of times to reconnect before giving up on an operation.
+pub const RECONNECT_ATTEMPTS: u32 = 3;
+
+/// Number of reconnects in a row that fail before the adapter is assumed to
+/// have reset, even though it still responds.
+pub const ADAPTER_RESET_FAILURES: u32 = 2;
+
+/// Returns a new handle of the adapter of a session, see
+/// [`AranetSession::set_adapter_recovery`].
+pub type AdapterRecovery<C> =
+    Box<dyn Fn() -> BoxFuture<'static, Result<C, AranetError>> + Send + Sync>;
+
+/// A connected device that is reconnected when the link drops.
+///
+/// Both one-shot reads and polling go through here, so they share the same
+/// reconnection logic.
+pub struct AranetSession<'a, C: Central>
+where
+    C::Peripheral: 'static,
+{
+    central:           &'a C,
+    /// Handle of the adapter after it was recovered, used instead of
+    /// `central`.
+    recovered:         Option<C>,
+    recover_adapter:   Option<AdapterRecovery<C>>,
+    failed_reconnects: u32,
+    address:           BDAddr,
+    scan_timeout:      Duration,
+    keep_connected:    bool,
+    client:            AranetClient<C::Peripheral>,
+}
+
+impl<'a, C: Central> AranetSession<'a, C>
+where
+    C::Peripheral: 'static,
+{
+    /// Connect to a scanned peripheral and detect its device type.
+    ///
+    /// When the device disconnects it is scanned for on `central` for up to
+    /// `scan_timeout` and connected again. Connecting and reconnecting time out
+    /// after the [connect timeout](AranetClient::with_connect_timeout) of
+    /// `client`.
+    pub async fn connect(
+        central: &'a C,
+        client: AranetClient<C::Peripheral>,
+        scan_timeout: Duration,
+    ) -> Result<Self, AranetError> {
+        client.ensure_connected().await?;
+        let device_type = client.read_device_type().await?.unwrap_or_default();
+        Ok(Self {
+            central,
+            recovered: None,
+            recover_adapter: None,
+            failed_reconnects: 0,
+            address: client.peripheral().address(),
+            scan_timeout,
+            keep_connected: true,
+            client: client.with_device_type(device_type),
+        })
+    }
+
+    /// Whether [`Self::poll`] stays connected between readings, the default,
+    /// or disconnects after each reading to save the battery of the device.
+    pub const fn set_keep_connected(&mut self, keep_connected: bool) {
+        self.keep_connected = keep_connected;
+    }
+
+    /// Get a new handle of the adapter with `recover` when it failed, rather
+    /// than only reconnecting to the device.
+    ///
+    /// The adapter is assumed to have failed when it no longer reports its
+    /// info, or when [`ADAPTER_RESET_FAILURES`] reconnects in a row failed.
+    pub fn set_adapter_recovery(
+        &mut self,
+        recover: impl Fn() -> BoxFuture<'static, Result<C, AranetError>> + Send + Sync + 'static,
+    ) {
+        self.recover_adapter = Some(Box::new(recover));
+    }
+
+    /// The adapter the device is connected with.
+    pub fn central(&self) -> &C {
+        self.recovered.as_ref().unwrap_or(self.central)
+    }
+
+    pub const fn address(&self) -> BDAddr {
+        self.address
+    }
+
+    /// The client of the current connection, which changes on reconnects.
+    pub const fn client(&self) -> &AranetClient<C::Peripheral> {
+        &self.client
+    }
+
+    /// Change settings of the client, which are kept on reconnects.
+    pub const fn client_mut(&mut self) -> &mut AranetClient<C::Peripheral> {
+        &mut self.client
+    }
+
+    /// Unsubscribe from notifications and disconnect, unless already
+    /// disconnected.
+    pub async fn disconnect(&self) -> Result<(), AranetError> {
+        disconnect(self.client.peripheral()).await
+    }
+
+    /// Reconnect if `error` was caused by the device disconnecting, otherwise
+    /// return the error.
+    pub async fn recover(&mut self, error: AranetError) -> Result<(), AranetError> {
+        if self
+            .client
+            .peripheral()
+            .is_connected()
+            .await
+            .unwrap_or(false)
+        {
+            return Err(error);
+        }
+        warn!("Disconnected from {}, reconnecting: {error}", self.address);
+        self.reconnect().await
+    }
+
+    /// Scan for the device and connect to it again.
+    ///
+    /// If that fails and the adapter appears to have reset, the adapter is
+    /// recovered and reconnecting tried once more, see
+    /// [`Self::set_adapter_recovery`].
+    pub async fn reconnect(&mut self) -> Result<(), AranetError> {
+        let mut result = self.reconnect_device().await;
+        if let Err(error) = &result {
+            self.failed_reconnects += 1;
+            if self.adapter_failed().await {
+                warn!("Bluetooth adapter of {} failed: {error}", self.address);
+                self.recover_adapter().await?;
+                result = self.reconnect_device().await;
+            }
+        }
+        let p = result.map_err(|error| AranetError::Connect {
+            address: self.address,
+            source:  Box::new(error),
+        })?;
+        self.failed_reconnects = 0;
+        self.client = self.client.clone().with_peripheral(p);
+        Ok(())
+    }
+
+    async fn reconnect_device(&self) -> Result<C::Peripheral, AranetError> {
+        reconnect(
+            self.central(),
+            self.address,
+            self.scan_timeout,
+            self.client.connect_timeout(),
+        )
+        .await
+    }
+
+    /// Whether the adapter should be recovered, which requires a recovery to
+    /// be set.
+    async fn adapter_failed(&self) -> bool {
+        if self.recover_adapter.is_none() {
+            return false;
+        }
+        if self.failed_reconnects >= ADAPTER_RESET_FAILURES {
+            return true;
+        }
+        match self.central().adapter_info().await {
+            Ok(_) => false,
+            Err(error) => {
+                debug!(%error, "adapter does not respond");
+                true
+            }
+        }
+    }
+
+    async fn recover_adapter(&mut self) -> Result<(), AranetError> {
+        let Some(recover) = &self.recover_adapter else {
+            return Ok(());
+        };
+        let central = recover().await?;
+        info!(
+            "Recovered bluetooth adapter {} for {}",
+            central.adapter_info().await.unwrap_or_default(),
+            self.address
+        );
+        self.recovered = Some(central);
+        self.failed_reconnects = 0;
+        Ok(())
+    }
+
+    /// Read the current reading, connecting first if needed and reconnecting
+    /// after disconnects.
+    pub async fn read_once(&mut self) -> Result<CurrentReading, AranetError> {
+        let mut attempts = 0;
+        loop {
+            let result = async {
+                self.client.ensure_connected().await?;
+                self.client.current_reading().await
+            }
+            .await;
+            match result {
+                Ok(reading) => return Ok(reading),
+                Err(error) if attempts < RECONNECT_ATTEMPTS => self.recover(error).await?,
+                Err(error) => return Err(error),
+            }
+            attempts += 1;
+        }
+    }
+
+    /// Pass the current reading to `handler` every `period` until it returns
+    /// [`ControlFlow::Break`] or fails.
+    ///
+    /// Without a `period` the measurement interval reported by the device is
+    /// used. See [`Self::set_keep_connected`] for the connection between
+    /// readings. Errors of the `handler` are returned as is.
+    pub async fn poll<F, Fut, E>(
+        &mut self,
+        period: Option<Duration>,
+        mut handler: F,
+    ) -> Result<(), E>
+    where
+        F: FnMut(CurrentReading) -> Fut,
+        Fut: Future<Output = Result<ControlFlow<()>, E>>,
+        E: From<AranetError>,
+    {
+        loop {
+            let reading = self.read_once().await?;
+            if !self.keep_connected {
+                self.client
+                    .peripheral()
+                    .disconnect()
+                    .await
+                    .map_err(AranetError::from)?;
+            }
+            if handler(reading).await?.is_break() {
+                return Ok(());
+            }
+            time::sleep(period.unwrap_or_else(|| reading.interval())).await;
+        }
+    }
+
+    /// Download the history of a sensor after one-based index `last_index`,
+    /// resuming after disconnects.
+    ///
+    /// Warns about samples that could not be downloaded.
+    pub async fn read_history_since(
+        &mut self,
+        sensor: Sensor,
+        last_index: u16,
+    ) -> Result<HistoryResult, AranetError> {
+        let first_index = last_index.saturating_add(1);
+        let mut attempts = 0;
+        let mut download = loop {
+            match self.client.begin_history_at(sensor, first_index).await {
+                Ok(download) => break download,
+                Err(error) if attempts < RECONNECT_ATTEMPTS => self.recover(error).await?,
+                Err(error) => return Err(error),
+            }
+            attempts += 1;
+        };
+        while let Err(error) = self.client.continue_history(&mut download).await {
+            if attempts >= RECONNECT_ATTEMPTS {
+                return Err(error);
+            }
+            self.recover(error).await?;
+            attempts += 1;
+        }
+        let history = download.into_result();
+        if !history.is_complete() {
+            warn!(
+                "Missing {} of {} {} samples: {:?}",
+                history.missing.len(),
+                history.samples.len(),
+                sensor.name(),
+                history.missing
+            );
+        }
+        Ok(history)
+    }
+
+    /// Download the history of `sensors`, resuming after disconnects.
+    pub async fn read_history_of(
+        &mut self,
+        sensors: &[Sensor],
+    ) -> Result<HashMap<Sensor, HistoryResult>, AranetError> {
+        let mut attempts = 0;
+        let mut downloads = loop {
+            match self.client.begin_history_of(sensors).await {
+                Ok(downloads) => break downloads,
+                Err(error) if attempts < RECONNECT_ATTEMPTS => self.recover(error).await?,
+                Err(error) => return Err(error),
+            }
+            attempts += 1;
+        };
+        while let Err(error) = self.client.continue_history_of(&mut downloads).await {
+            if attempts >= RECONNECT_ATTEMPTS {
+                return Err(error);
+            }
+            self.recover(error).await?;
+            attempts += 1;
+        }
+        Ok(downloads
+            .into_iter()
+            .map(|download| (download.sensor(), download.into_result()))
+            .collect())
+    }
+}
+
+impl<C: Central> Drop for AranetSession<'_, C>
+where
+    C::Peripheral: 'static,
+{
+    fn drop(&mut self) {
+        // Without a runtime there is nothing to disconnect with.
+        if let Ok(runtime) = Handle::try_current() {
+            let p = self.client.peripheral().clone();
+            runtime.spawn(async move {
+                if let Err(error) = disconnect(&p).await {
+                    debug!(%error, "failed to disconnect");
+                }
+            });
+        }
+    }
+}
+
+async fn disconnect(p: &impl Peripheral) -> Result<(), AranetError> {
+    if !p.is_connected().await? {
+        return Ok(());
+    }
+    for characteristic in [&HISTORY_NOTIFIER, &CURRENT_READING_NOTIFY] {
+        // Fails if not subscribed, which is fine.
+        if let Err(error) = p.unsubscribe(characteristic).await {
+            debug!(%error, uuid = %characteristic.uuid, "failed to unsubscribe");
+        }
+    }
+    p.disconnect().await?;
+    Ok(())
+}
@@ -0,0 +1,105 @@
+//! Moving averages of readings, to smooth out jitter and the steps in which
+//! the device updates values such as the CO2 concentration.
+
+use crate::{CurrentReading, Sensor};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Average of the last values pushed, at most `window` of them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RollingAverage {
+    window: usize,
+    values: VecDeque<f32>,
+}
+
+impl RollingAverage {
+    /// A window of zero is treated as one, which averages nothing.
+    #[must_use]
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            window,
+            values: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Add a value, dropping the oldest when the window is full, and return
+    /// the new average.
+    pub fn push(&mut self, value: f32) -> f32 {
+        if self.values.len() == self.window {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+        self.average().unwrap_or(value)
+    }
+
+    /// The average of the values in the window, `None` before the first.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn average(&self) -> Option<f32> {
+        if self.values.is_empty() {
+            return None;
+        }
+        Some(self.values.iter().sum::<f32>() / self.values.len() as f32)
+    }
+
+    /// Number of values in the window.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// A [`RollingAverage`] per sensor of consecutive readings of a device.
+///
+/// Each sensor has its own window, which only gets the readings with a value
+/// for it. So readings during the warmup of the CO2 sensor do not shorten the
+/// window of the CO2 average, and do not hold back the other sensors.
+#[derive(Clone, Debug)]
+pub struct ReadingSmoother {
+    window:   usize,
+    averages: BTreeMap<Sensor, RollingAverage>,
+}
+
+/// The moving averages after a reading, in [`Sensor::unit`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Smoothed {
+    /// Number of readings averaged at most.
+    pub window:   usize,
+    /// Sensors without a value in any reading so far are omitted.
+    pub averages: BTreeMap<Sensor, f32>,
+}
+
+impl ReadingSmoother {
+    #[must_use]
+    pub const fn new(window: usize) -> Self {
+        Self {
+            window,
+            averages: BTreeMap::new(),
+        }
+    }
+
+    /// Add the values of a reading and return the averages.
+    pub fn push(&mut self, reading: &CurrentReading) -> Smoothed {
+        for sensor in Sensor::ALL {
+            if let Some(value) = reading.value(sensor) {
+                self.averages
+                    .entry(sensor)
+                    .or_insert_with(|| RollingAverage::new(self.window))
+                    .push(value);
+            }
+        }
+        Smoothed {
+            window:   self.window,
+            averages: self
+                .averages
+                .iter()
+                .filter_map(|(&sensor, average)| Some((sensor, average.average()?)))
+                .collect(),
+        }
+    }
+}
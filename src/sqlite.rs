@@ -0,0 +1,155 @@
+//! Storing readings and history in a `SQLite` database.
+
+use crate::{history::TimedSamples, CurrentReading, Sensor};
+use btleplug::api::BDAddr;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+use thiserror::Error;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS readings (
+        timestamp   TEXT NOT NULL,
+        address     TEXT NOT NULL,
+        co2         INTEGER,
+        temperature REAL NOT NULL,
+        humidity    INTEGER NOT NULL,
+        pressure    REAL,
+        battery     INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS history (
+        timestamp TEXT NOT NULL,
+        address   TEXT NOT NULL,
+        sensor    TEXT NOT NULL,
+        value     REAL NOT NULL
+    );
+    CREATE UNIQUE INDEX IF NOT EXISTS history_sample
+        ON history (address, timestamp, sensor);
+";
+
+/// Failure of opening or writing to a [`Database`].
+#[derive(Debug, Error)]
+pub enum DatabaseError {
+    /// Opening the database at `path` or creating its tables failed.
+    #[error("failed to open database {}", path.display())]
+    Open {
+        path:   PathBuf,
+        source: rusqlite::Error,
+    },
+    /// A statement failed.
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    /// A panic while holding the connection left it in an unknown state.
+    #[error("database connection poisoned by a panic")]
+    Poisoned,
+}
+
+/// Database with a `readings` table of current readings and a `history` table
+/// of downloaded samples, one row per sensor.
+///
+/// Timestamps are stored as ISO-8601 text and values in the units of the
+/// device.
+#[derive(Debug)]
+pub struct Database {
+    connection: Mutex<Connection>,
+}
+
+impl Database {
+    /// Open or create the database at `path`, creating the tables if needed.
+    pub fn open(path: &Path) -> Result<Self, DatabaseError> {
+        let open = |source| DatabaseError::Open {
+            path: path.to_owned(),
+            source,
+        };
+        let connection = Connection::open(path).map_err(open)?;
+        connection.execute_batch(SCHEMA).map_err(open)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Append a reading of the device with `address` measured at `timestamp`.
+    pub fn insert_reading(
+        &self,
+        address: BDAddr,
+        timestamp: DateTime<Utc>,
+        reading: &CurrentReading,
+    ) -> Result<(), DatabaseError> {
+        self.connection()?.execute(
+            "INSERT INTO readings (timestamp, address, co2, temperature, humidity, pressure, \
+             battery) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                format_timestamp(timestamp),
+                address.to_string(),
+                reading.valid_co2(),
+                reading.temperature,
+                reading.humidity,
+                reading.pressure,
+                reading.battery,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Insert history samples of the device with `address` in a single
+    /// transaction, skipping missing samples and samples already stored.
+    ///
+    /// Timestamps of the same sample can differ between downloads by the
+    /// rounding of its age, so a sample within half an interval of a stored
+    /// one of the same sensor counts as stored. The interval is taken from the
+    /// samples, a single sample is only skipped if its timestamp matches.
+    ///
+    /// Returns the number of samples inserted.
+    pub fn insert_history(
+        &self,
+        address: BDAddr,
+        readings: &[(Sensor, TimedSamples)],
+    ) -> Result<usize, DatabaseError> {
+        let mut connection = self.connection()?;
+        let transaction = connection.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut insert = transaction.prepare(
+                "INSERT OR IGNORE INTO history (timestamp, address, sensor, value) SELECT ?1, ?2, \
+                 ?3, ?4 WHERE NOT EXISTS (SELECT 1 FROM history WHERE address = ?2 AND sensor = \
+                 ?3 AND timestamp > ?5 AND timestamp < ?6)",
+            )?;
+            let address = address.to_string();
+            for (sensor, samples) in readings {
+                let tolerance = samples
+                    .windows(2)
+                    .map(|pair| pair[1].0 - pair[0].0)
+                    .min()
+                    .unwrap_or_default()
+                    / 2;
+                for &(timestamp, value) in samples {
+                    if value.is_nan() {
+                        continue;
+                    }
+                    inserted += insert.execute(params![
+                        format_timestamp(timestamp),
+                        address,
+                        sensor.name(),
+                        value,
+                        format_timestamp(timestamp - tolerance),
+                        format_timestamp(timestamp + tolerance),
+                    ])?;
+                }
+            }
+        }
+        transaction.commit()?;
+        drop(connection);
+        Ok(inserted)
+    }
+
+    fn connection(&self) -> Result<MutexGuard<'_, Connection>, DatabaseError> {
+        self.connection.lock().map_err(|_| DatabaseError::Poisoned)
+    }
+}
+
+fn format_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
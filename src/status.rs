@@ -0,0 +1,59 @@
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+
+/// CO2 traffic-light indicator as shown on the device display.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Green,
+    Amber,
+    Red,
+    /// Status byte not known to this version of the crate.
+    Unknown(u8),
+}
+
+impl Status {
+    #[must_use]
+    pub const fn from_byte(byte: u8) -> Self {
+        match byte {
+            1 => Self::Green,
+            2 => Self::Amber,
+            3 => Self::Red,
+            byte => Self::Unknown(byte),
+        }
+    }
+
+    /// The raw status byte as reported by the device.
+    #[must_use]
+    pub const fn to_byte(self) -> u8 {
+        match self {
+            Self::Green => 1,
+            Self::Amber => 2,
+            Self::Red => 3,
+            Self::Unknown(byte) => byte,
+        }
+    }
+}
+
+impl From<u8> for Status {
+    fn from(byte: u8) -> Self {
+        Self::from_byte(byte)
+    }
+}
+
+impl From<Status> for u8 {
+    fn from(status: Status) -> Self {
+        status.to_byte()
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Green => write!(f, "Green"),
+            Self::Amber => write!(f, "Amber"),
+            Self::Red => write!(f, "Red"),
+            Self::Unknown(byte) => write!(f, "Unknown ({byte})"),
+        }
+    }
+}
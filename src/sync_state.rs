@@ -0,0 +1,129 @@
+//! Progress of incremental history syncs, persisted between runs.
+
+use crate::{history::sample_index, Sensor};
+use btleplug::api::BDAddr;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// The last sample synced for a sensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SensorSync {
+    /// One-based index of the last synced sample.
+    pub last_index:     u16,
+    /// Time the last synced sample was measured.
+    pub last_timestamp: DateTime<Utc>,
+    /// Measurement interval in seconds at the time of the sync, `None` in
+    /// state files written before it was recorded.
+    #[serde(default)]
+    pub interval:       Option<u16>,
+}
+
+impl SensorSync {
+    /// One-based index of the last synced sample on a device storing `stored`
+    /// samples, the newest measured at `newest` and each `interval` apart, or
+    /// `None` if the device no longer holds it.
+    ///
+    /// The sample is found by its timestamp, see [`sample_index`], as its
+    /// index shifts once the history is full. Fails when the device was reset
+    /// or its history cleared since the last sync, so that it stores fewer
+    /// samples or the sample moved to a later index, when the sample dropped
+    /// out of the history, or when the interval changed.
+    #[must_use]
+    pub fn synced_index(
+        &self,
+        stored: u16,
+        newest: DateTime<Utc>,
+        interval: Duration,
+    ) -> Option<u16> {
+        if self
+            .interval
+            .is_some_and(|synced| Duration::seconds(synced.into()) != interval)
+            || stored < self.last_index
+        {
+            return None;
+        }
+        sample_index(newest, interval, self.last_timestamp, stored)
+            .filter(|&index| index <= self.last_index)
+    }
+}
+
+/// The last sample synced for each sensor of each device, stored as JSON.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SyncState {
+    /// Keyed by device address.
+    pub devices: BTreeMap<String, BTreeMap<Sensor, SensorSync>>,
+}
+
+/// Failure of loading or saving a state file.
+#[derive(Debug, Error)]
+pub enum SyncStateError {
+    /// Reading the file at `path` failed.
+    #[error("failed to read {}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+    /// The file at `path` is not a valid state file.
+    #[error("invalid state file {}", path.display())]
+    Invalid {
+        path:   PathBuf,
+        source: serde_json::Error,
+    },
+    /// Writing the file at `path` failed.
+    #[error("failed to write {}", path.display())]
+    Write { path: PathBuf, source: io::Error },
+}
+
+impl SyncState {
+    /// Load the state from `path`, or start empty if it does not exist yet.
+    pub fn load(path: &Path) -> Result<Self, SyncStateError> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).map_err(|source| SyncStateError::Invalid {
+                path: path.to_owned(),
+                source,
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(SyncStateError::Read {
+                path: path.to_owned(),
+                source,
+            }),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SyncStateError> {
+        serde_json::to_string_pretty(self)
+            .map_err(io::Error::from)
+            .and_then(|json| fs::write(path, json))
+            .map_err(|source| SyncStateError::Write {
+                path: path.to_owned(),
+                source,
+            })
+    }
+
+    /// The last sample synced for `sensor` of the device with `address`.
+    #[must_use]
+    pub fn get(&self, address: BDAddr, sensor: Sensor) -> Option<SensorSync> {
+        self.devices
+            .get(&address.to_string())?
+            .get(&sensor)
+            .copied()
+    }
+
+    pub fn insert(&mut self, address: BDAddr, sensor: Sensor, sync: SensorSync) {
+        self.devices
+            .entry(address.to_string())
+            .or_default()
+            .insert(sensor, sync);
+    }
+
+    /// Forget the samples synced for `sensor` of the device with `address`.
+    pub fn remove(&mut self, address: BDAddr, sensor: Sensor) {
+        if let Some(sensors) = self.devices.get_mut(&address.to_string()) {
+            sensors.remove(&sensor);
+        }
+    }
+}
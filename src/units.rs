@@ -0,0 +1,156 @@
+//! Conversion of readings to the units chosen for output.
+//!
+//! Readings are always parsed in the units of the device, conversion happens
+//! only when formatting.
+
+use crate::Sensor;
+use serde::Serialize;
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+impl TemperatureUnit {
+    /// Convert a temperature in °C to this unit.
+    #[must_use]
+    pub fn from_celsius(self, celsius: f32) -> f32 {
+        match self {
+            Self::Celsius => celsius,
+            Self::Fahrenheit => celsius.mul_add(1.8, 32.0),
+            Self::Kelvin => celsius + 273.15,
+        }
+    }
+
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Celsius => "°C",
+            Self::Fahrenheit => "°F",
+            Self::Kelvin => "K",
+        }
+    }
+}
+
+impl Display for TemperatureUnit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+/// Parses `c`, `f` or `k`, or the full unit name.
+impl FromStr for TemperatureUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "c" | "celsius" => Ok(Self::Celsius),
+            "f" | "fahrenheit" => Ok(Self::Fahrenheit),
+            "k" | "kelvin" => Ok(Self::Kelvin),
+            _ => Err(format!(
+                "unknown temperature unit {s:?}, expected c, f or k"
+            )),
+        }
+    }
+}
+
+/// Convert a pressure in hPa to millimeters of mercury.
+#[must_use]
+pub fn hpa_to_mmhg(hpa: f32) -> f32 {
+    hpa * 0.750_062
+}
+
+/// Convert a pressure in hPa to inches of mercury.
+#[must_use]
+pub fn hpa_to_inhg(hpa: f32) -> f32 {
+    hpa * 0.029_530
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PressureUnit {
+    #[default]
+    Hpa,
+    Mmhg,
+    Inhg,
+}
+
+impl PressureUnit {
+    /// Convert a pressure in hPa to this unit.
+    #[must_use]
+    pub fn from_hpa(self, hpa: f32) -> f32 {
+        match self {
+            Self::Hpa => hpa,
+            Self::Mmhg => hpa_to_mmhg(hpa),
+            Self::Inhg => hpa_to_inhg(hpa),
+        }
+    }
+
+    #[must_use]
+    pub const fn symbol(self) -> &'static str {
+        match self {
+            Self::Hpa => "hPa",
+            Self::Mmhg => "mmHg",
+            Self::Inhg => "inHg",
+        }
+    }
+}
+
+impl Display for PressureUnit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+/// Parses `hpa`, `mmhg` or `inhg`, ignoring case.
+impl FromStr for PressureUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "hpa" => Ok(Self::Hpa),
+            "mmhg" => Ok(Self::Mmhg),
+            "inhg" => Ok(Self::Inhg),
+            _ => Err(format!(
+                "unknown pressure unit {s:?}, expected hpa, mmhg or inhg"
+            )),
+        }
+    }
+}
+
+/// Units to output readings in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Units {
+    pub temperature: TemperatureUnit,
+    pub pressure:    PressureUnit,
+}
+
+impl Units {
+    /// Convert a value of `sensor` from the unit of the device.
+    #[must_use]
+    pub fn convert(self, sensor: Sensor, value: f32) -> f32 {
+        match sensor {
+            Sensor::Temperature => self.temperature.from_celsius(value),
+            Sensor::Pressure => self.pressure.from_hpa(value),
+            Sensor::Humidity | Sensor::CO2 => value,
+        }
+    }
+
+    /// Symbol of the unit [`Self::convert`] converts values of `sensor` to.
+    #[must_use]
+    pub const fn symbol(self, sensor: Sensor) -> &'static str {
+        match sensor {
+            Sensor::Temperature => self.temperature.symbol(),
+            Sensor::Pressure => self.pressure.symbol(),
+            Sensor::Humidity | Sensor::CO2 => sensor.unit(),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Posting alerts to a webhook, such as a Slack or Discord incoming webhook.
+
+use crate::{alert::AlertEvent, retry::with_retry, CurrentReading, HttpError, Sensor};
+use btleplug::api::BDAddr;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Number of attempts made to post an alert.
+const POST_ATTEMPTS: u32 = 3;
+
+/// Timeout of a single attempt.
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// JSON body posted for an alert.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AlertPayload<'a> {
+    pub event:     AlertEvent,
+    /// Address of the device.
+    pub device:    String,
+    pub sensor:    Sensor,
+    pub value:     f32,
+    pub threshold: f32,
+    pub timestamp: DateTime<Utc>,
+    /// Summary of the alert, shown by chat services.
+    pub text:      String,
+    pub reading:   &'a CurrentReading,
+}
+
+impl<'a> AlertPayload<'a> {
+    /// Payload for a CO2 alert of the device with `address`.
+    #[must_use]
+    pub fn co2(
+        event: AlertEvent,
+        address: BDAddr,
+        threshold: u16,
+        reading: &'a CurrentReading,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        let co2 = reading.co2.unwrap_or_default();
+        let text = match event {
+            AlertEvent::Raised => format!("CO2 of {address} is high: {co2} ppm"),
+            AlertEvent::Cleared => format!("CO2 of {address} is back to normal: {co2} ppm"),
+        };
+        Self {
+            event,
+            device: address.to_string(),
+            sensor: Sensor::CO2,
+            value: co2.into(),
+            threshold: threshold.into(),
+            timestamp,
+            text,
+            reading,
+        }
+    }
+}
+
+/// Client posting alerts as JSON to a URL.
+#[derive(Clone, Debug)]
+pub struct Webhook {
+    client: Client,
+    url:    String,
+}
+
+impl Webhook {
+    pub fn new(url: &str) -> Result<Self, HttpError> {
+        let client = Client::builder()
+            .timeout(POST_TIMEOUT)
+            .build()
+            .map_err(|source| HttpError::Request {
+                url: url.to_owned(),
+                source,
+            })?;
+        Ok(Self {
+            client,
+            url: url.to_owned(),
+        })
+    }
+
+    /// Post `payload`, retrying with backoff if the request or the server
+    /// fails.
+    pub async fn send(&self, payload: &AlertPayload<'_>) -> Result<(), HttpError> {
+        with_retry(POST_ATTEMPTS, || async {
+            self.client
+                .post(&self.url)
+                .json(payload)
+                .send()
+                .await?
+                .error_for_status()
+        })
+        .await
+        .map_err(|source| HttpError::Request {
+            url: self.url.clone(),
+            source,
+        })?;
+        Ok(())
+    }
+}
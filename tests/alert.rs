@@ -0,0 +1,24 @@
+use cotracker::alert::{AlertEvent, Co2Alert};
+
+#[test]
+fn hysteresis() {
+    let mut alert = Co2Alert::new(1000, 800);
+    let events = [900, 1100, 950, 1200, 850, 750, 1001].map(|co2| alert.update(co2));
+    assert_eq!(events, [
+        None,
+        Some(AlertEvent::Raised),
+        None,
+        None,
+        None,
+        Some(AlertEvent::Cleared),
+        Some(AlertEvent::Raised),
+    ]);
+}
+
+#[test]
+fn clear_above_threshold() {
+    let mut alert = Co2Alert::new(1000, 1200);
+    assert_eq!(alert.update(1001), Some(AlertEvent::Raised));
+    assert_eq!(alert.update(1000), None);
+    assert_eq!(alert.update(999), Some(AlertEvent::Cleared));
+}
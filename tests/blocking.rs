@@ -0,0 +1,33 @@
+#![cfg(feature = "blocking")]
+
+mod common;
+
+use common::{packet, packets, MockPeripheral};
+use cotracker::{
+    blocking::BlockingClient,
+    characteristics::{CURRENT_READING_FULL, STORED_READINGS},
+    AranetClient, Sensor,
+};
+
+#[test]
+fn read_without_runtime() {
+    let p = MockPeripheral::new()
+        .with_read(
+            &CURRENT_READING_FULL,
+            packet(include_str!("fixtures/aranet4_current_reading_full.hex")),
+        )
+        .with_read(
+            &STORED_READINGS,
+            packet(include_str!("fixtures/aranet4_stored_readings.hex")),
+        )
+        .with_history(
+            Sensor::CO2.id(),
+            packets(include_str!("fixtures/aranet4_history_co2.hex")),
+        );
+    let client = BlockingClient::new(AranetClient::new(p)).unwrap();
+    assert_eq!(client.read_once().unwrap().co2, Some(850));
+    assert_eq!(client.stored_reading_count().unwrap(), 5);
+    let history = client.history(Sensor::CO2).unwrap();
+    assert_eq!(history.samples.len(), 5);
+    assert!(history.is_complete());
+}
@@ -0,0 +1,655 @@
+mod common;
+
+use btleplug::api::{Characteristic, ValueNotification, WriteType};
+use chrono::{Local, TimeZone, Utc};
+use common::{packet, packets, MockPeripheral};
+use cotracker::{
+    characteristics::{
+        BATTERY_LEVEL, COMMAND, CURRENT_READING, CURRENT_READING_ARANET2, CURRENT_READING_FULL,
+        CURRENT_READING_NOTIFY, CURRENT_TIME, DEVICE_NAME, FIRMWARE_REVISION, HISTORY_NOTIFIER,
+        HISTORY_RANGE, INTERVAL, MANUFACTURER_NAME, MODEL_NUMBER, SECONDS_SINCE_UPDATE,
+        SERIAL_NUMBER, STORED_READINGS,
+    },
+    history::{
+        aranet_notifications, sample_index, HistoryChunk, HistoryDownload, HistoryRange,
+        HistoryResult,
+    },
+    parse_current_time, AranetClient, AranetError, Capabilities, CurrentReading, DeviceType,
+    Sensor, Status,
+};
+use futures::{stream, StreamExt};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+fn aranet4() -> MockPeripheral {
+    MockPeripheral::new()
+        .with_read(
+            &CURRENT_READING_FULL,
+            packet(include_str!("fixtures/aranet4_current_reading_full.hex")),
+        )
+        .with_read(
+            &STORED_READINGS,
+            packet(include_str!("fixtures/aranet4_stored_readings.hex")),
+        )
+        .with_history(
+            Sensor::Temperature.id(),
+            packets(include_str!("fixtures/aranet4_history_temperature.hex")),
+        )
+        .with_history(
+            Sensor::Humidity.id(),
+            packets(include_str!("fixtures/aranet4_history_humidity.hex")),
+        )
+        .with_history(
+            Sensor::Pressure.id(),
+            packets(include_str!("fixtures/aranet4_history_pressure.hex")),
+        )
+        .with_history(
+            Sensor::CO2.id(),
+            packets(include_str!("fixtures/aranet4_history_co2.hex")),
+        )
+}
+
+#[tokio::test]
+async fn current_reading() {
+    let client = AranetClient::new(aranet4());
+    let reading = client.current_reading().await.unwrap();
+    assert_eq!(reading.co2, Some(850));
+    assert!(reading.co2_valid);
+    assert!((reading.temperature - 21.35).abs() < 1e-4);
+    assert_eq!(reading.pressure, Some(1013.2));
+    assert_eq!(reading.humidity, 45);
+    assert_eq!(reading.battery, 87);
+    assert_eq!(reading.status, Status::Green);
+    assert_eq!(reading.interval, 300);
+    assert_eq!(reading.age, 42);
+}
+
+#[tokio::test]
+async fn short_current_reading() {
+    let p = MockPeripheral::new()
+        .with_characteristic(CURRENT_READING)
+        .with_read(&CURRENT_READING, vec![
+            0x52, 0x03, 0xab, 0x01, 0x94, 0x27, 0x2d,
+        ])
+        .with_read(&INTERVAL, vec![0x2c, 0x01])
+        .with_read(&SECONDS_SINCE_UPDATE, vec![0x2a, 0x00])
+        .with_read(&BATTERY_LEVEL, vec![87]);
+    let reading = AranetClient::new(p).current_reading().await.unwrap();
+    assert_eq!(reading.co2, Some(850));
+    assert_eq!(reading.pressure, Some(1013.2));
+    assert_eq!(reading.humidity, 45);
+    assert_eq!(reading.battery, 87);
+    assert_eq!(reading.status, Status::Unknown(0));
+    assert_eq!(reading.interval, 300);
+    assert_eq!(reading.age, 42);
+}
+
+#[tokio::test]
+async fn warming_up_current_reading() {
+    let mut data = packet(include_str!("fixtures/aranet4_current_reading_full.hex"));
+    data[..2].copy_from_slice(&[0xff, 0xff]);
+    let p = aranet4().with_read(&CURRENT_READING_FULL, data);
+    let reading = AranetClient::new(p).current_reading().await.unwrap();
+    assert_eq!(reading.co2, Some(0xffff));
+    assert!(!reading.co2_valid);
+    assert_eq!(reading.valid_co2(), None);
+    assert!(reading.to_string().starts_with("CO2          warming up\n"));
+}
+
+#[tokio::test]
+async fn from_peripheral() {
+    let p = aranet4().with_read(&MODEL_NUMBER, b"Aranet2".to_vec());
+    let client = AranetClient::from_peripheral(p).await.unwrap();
+    assert_eq!(client.device_type(), DeviceType::Aranet2);
+}
+
+#[tokio::test]
+async fn radon_reading() {
+    let p = MockPeripheral::new().with_read(&CURRENT_READING_ARANET2, vec![
+        0x03, 0x00, 0x58, 0x02, 0x0a, 0x00, 0x5a, 0xae, 0x01, 0x94, 0x27, 0xc5, 0x01, 0x7b, 0x00,
+        0x00, 0x00, 0x01,
+    ]);
+    let client = AranetClient::new(p).with_device_type(DeviceType::AranetRadon);
+    let radon = client.radon_reading().await.unwrap();
+    assert_eq!(radon.radon, 123);
+    assert!((radon.temperature - 21.5).abs() < 1e-3);
+    assert!((radon.humidity - 45.3).abs() < 1e-3);
+    assert_eq!(radon.status, Status::Green);
+    assert!(radon.to_string().starts_with("Radon           123 Bq/m³\n"));
+    let reading = client.current_reading().await.unwrap();
+    assert_eq!(reading.co2, None);
+    assert_eq!(reading.humidity, 45);
+    assert_eq!(reading.pressure, Some(1013.2));
+    assert_eq!(reading.interval, 600);
+    assert_eq!(CurrentReading::from(radon), reading);
+    assert!(matches!(
+        client.radiation_reading().await,
+        Err(AranetError::Unsupported(_))
+    ));
+}
+
+#[tokio::test]
+async fn radiation_reading() {
+    let p = MockPeripheral::new().with_read(&CURRENT_READING_ARANET2, vec![
+        0x04, 0x00, 0x3c, 0x00, 0x05, 0x00, 0x50, 0x78, 0x00, 0x00, 0x00, 0xa0, 0x25, 0x26, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x80, 0x51, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    ]);
+    let client = AranetClient::new(p).with_device_type(DeviceType::AranetRadiation);
+    let radiation = client.radiation_reading().await.unwrap();
+    assert!((radiation.dose_rate - 0.12).abs() < 1e-9);
+    assert!((radiation.total_dose - 2.5).abs() < 1e-9);
+    assert_eq!(radiation.duration, 86_400);
+    assert_eq!(radiation.battery, 80);
+    assert_eq!(radiation.age, 5);
+    assert!(matches!(
+        client.current_reading().await,
+        Err(AranetError::Unsupported(_))
+    ));
+}
+
+#[test]
+fn device_type_from_name() {
+    assert_eq!(
+        DeviceType::from_name("Aranet4 1A2B3"),
+        Some(DeviceType::Aranet4)
+    );
+    assert_eq!(
+        DeviceType::from_name("AranetRn+ 0C3F1"),
+        Some(DeviceType::AranetRadon)
+    );
+    assert_eq!(
+        DeviceType::from_name("Aranet☢ 2D4E6"),
+        Some(DeviceType::AranetRadiation)
+    );
+    assert_eq!(DeviceType::from_name("Aranet Home"), None);
+    assert!(!DeviceType::AranetRadon.supports(Sensor::Temperature));
+}
+
+#[tokio::test]
+async fn truncated_current_reading() {
+    let p = MockPeripheral::new().with_read(&CURRENT_READING_FULL, vec![0x52, 0x03, 0xab]);
+    assert!(matches!(
+        AranetClient::new(p).current_reading().await,
+        Err(AranetError::Parse(_))
+    ));
+}
+
+#[tokio::test]
+async fn reading_stream() {
+    let client = AranetClient::new(aranet4());
+    let readings = client
+        .reading_stream(Duration::from_millis(1))
+        .take(2)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(readings.len(), 2);
+    assert_eq!(readings[1].as_ref().unwrap().co2, Some(850));
+}
+
+#[tokio::test]
+async fn reading_stream_continues_after_errors() {
+    let client = AranetClient::new(MockPeripheral::new());
+    let readings = client
+        .reading_stream(Duration::from_millis(1))
+        .take(2)
+        .collect::<Vec<_>>()
+        .await;
+    assert!(readings.iter().all(Result::is_err));
+}
+
+#[tokio::test]
+async fn subscribe_current() {
+    let p = aranet4().with_characteristic(CURRENT_READING_NOTIFY);
+    let client = AranetClient::new(p.clone());
+    let mut readings = client
+        .subscribe_current(Duration::from_secs(3600))
+        .await
+        .unwrap();
+    let mut reading = packet(include_str!("fixtures/aranet4_current_reading_full.hex"));
+    reading[0] = 0x20; // 800 ppm
+    p.notify(CURRENT_READING_NOTIFY.uuid, reading);
+    let reading = readings.next().await.unwrap().unwrap();
+    assert_eq!(reading.co2, Some(800));
+}
+
+#[tokio::test]
+async fn subscribe_current_falls_back_to_polling() {
+    let client = AranetClient::new(aranet4());
+    let mut readings = client
+        .subscribe_current(Duration::from_secs(3600))
+        .await
+        .unwrap();
+    let reading = readings.next().await.unwrap().unwrap();
+    assert_eq!(reading.co2, Some(850));
+}
+
+#[test]
+fn capabilities() {
+    let p = aranet4()
+        .with_characteristic(CURRENT_READING_FULL)
+        .with_characteristic(STORED_READINGS)
+        .with_characteristic(HISTORY_NOTIFIER)
+        .with_characteristic(COMMAND);
+    assert_eq!(AranetClient::new(p).capabilities(), Capabilities {
+        history:        true,
+        calibration:    true,
+        notify_current: false,
+        set_interval:   true,
+    });
+}
+
+#[tokio::test]
+async fn unsupported_commands_are_not_written() {
+    let p = aranet4();
+    let client = AranetClient::new(p.clone());
+    assert!(matches!(
+        client.set_interval(300).await,
+        Err(AranetError::Unsupported(_))
+    ));
+    assert!(matches!(
+        client.calibrate().await,
+        Err(AranetError::Unsupported(_))
+    ));
+    assert!(p.writes().is_empty());
+}
+
+#[tokio::test]
+async fn battery_level() {
+    let client = AranetClient::new(aranet4().with_read(&BATTERY_LEVEL, vec![87]));
+    assert_eq!(client.battery_level().await.unwrap(), 87);
+    assert!(AranetClient::new(aranet4()).battery_level().await.is_err());
+}
+
+#[tokio::test]
+async fn device_info() {
+    let p = aranet4()
+        .with_read(&DEVICE_NAME, b"Aranet4 1A2B3".to_vec())
+        .with_read(&MODEL_NUMBER, b"Aranet4".to_vec())
+        .with_read(&MANUFACTURER_NAME, b"SAF Tehnika".to_vec())
+        .with_read(&SERIAL_NUMBER, b"1234567".to_vec())
+        .with_read(&FIRMWARE_REVISION, b"v1.4.19".to_vec());
+    let info = AranetClient::new(p).device_info().await.unwrap();
+    assert_eq!(
+        info.to_string(),
+        "Aranet4 1A2B3 (Aranet4 by SAF Tehnika, serial 1234567, firmware v1.4.19)"
+    );
+    assert_eq!((info.battery, info.interval, info.stored), (87, 300, 5));
+    assert!(info
+        .table()
+        .to_string()
+        .starts_with("Device name      Aranet4 1A2B3\nModel            Aranet4\n"));
+}
+
+#[tokio::test]
+async fn stored_reading_count() {
+    let client = AranetClient::new(aranet4());
+    assert_eq!(client.stored_reading_count().await.unwrap(), 5);
+}
+
+#[tokio::test]
+async fn history_progress() {
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let client = AranetClient::new(aranet4()).with_progress({
+        let progress = progress.clone();
+        move |download| {
+            let mut progress = progress.lock().unwrap();
+            progress.push((download.received(), download.expected()));
+        }
+    });
+    client.read_history(Sensor::Temperature).await.unwrap();
+    assert_eq!(progress.lock().unwrap().last(), Some(&(5, 5)));
+}
+
+#[tokio::test]
+async fn history_request() {
+    let p = aranet4();
+    let client = AranetClient::new(p.clone());
+    client.read_history(Sensor::CO2).await.unwrap();
+    assert_eq!(p.writes(), vec![(HISTORY_RANGE.uuid, vec![
+        0x82, 0x04, 0x00, 0x00, 0x01, 0x00, 0x05, 0x00
+    ])]);
+}
+
+#[tokio::test]
+async fn history_range() {
+    let p = aranet4().with_read(&HISTORY_RANGE, vec![
+        0x82, 0x01, 0x00, 0x00, 0x02, 0x00, 0x05, 0x00,
+    ]);
+    let client = AranetClient::new(p);
+    let range = client.read_history_range().await.unwrap();
+    assert_eq!(range, HistoryRange::new(Sensor::Temperature, 2, 5));
+    assert_eq!(range.to_bytes(), [
+        0x82, 0x01, 0x00, 0x00, 0x02, 0x00, 0x05, 0x00
+    ]);
+    // The request sent, up to `u16::MAX`, is limited to the five stored.
+    let p = aranet4().with_read(&HISTORY_RANGE, vec![
+        0x82, 0x04, 0x00, 0x00, 0x03, 0x00, 0xff, 0xff,
+    ]);
+    let range = AranetClient::new(p).read_history_range().await.unwrap();
+    assert_eq!(range, HistoryRange::new(Sensor::CO2, 3, 5));
+    let clamped = HistoryRange {
+        start: 0,
+        end: u16::MAX,
+        ..range
+    }
+    .clamp(3);
+    assert_eq!((clamped.start, clamped.end), (1, 3));
+    assert!(HistoryRange::parse(&[0x90, 0x01]).is_err());
+}
+
+#[test]
+fn history_range_newest() {
+    let range = HistoryRange::newest(Sensor::CO2, 2016, 12);
+    assert_eq!((range.start, range.end), (2005, 2016));
+    let range = HistoryRange::newest(Sensor::CO2, 5, 12);
+    assert_eq!((range.start, range.end), (1, 5));
+    let range = HistoryRange::newest(Sensor::CO2, 5, 0);
+    assert_eq!((range.start, range.end), (6, 5));
+}
+
+#[test]
+fn history_range_round_trip() {
+    let range = HistoryRange {
+        param: 0x0102,
+        ..HistoryRange::new(Sensor::Humidity, 10, 2016)
+    };
+    let bytes = range.to_bytes();
+    assert_eq!(bytes, [0x82, 0x02, 0x02, 0x01, 0x0a, 0x00, 0xe0, 0x07]);
+    assert_eq!(HistoryRange::parse(&bytes).unwrap(), range);
+}
+
+#[tokio::test]
+async fn history_across_notifications() {
+    let client = AranetClient::new(aranet4());
+    let temperature = client
+        .read_history(Sensor::Temperature)
+        .await
+        .unwrap()
+        .samples;
+    let expected = [21.0, 21.05, 21.1, 21.2, 21.35];
+    assert_eq!(temperature.len(), expected.len());
+    for (actual, expected) in temperature.iter().zip(expected) {
+        assert!((actual - expected).abs() < 1e-4, "{actual} != {expected}");
+    }
+    let co2 = client.read_history(Sensor::CO2).await.unwrap().samples;
+    assert_eq!(co2, vec![600.0, 650.0, 700.0, 800.0, 850.0]);
+}
+
+#[tokio::test]
+async fn history_ignores_other_characteristics() {
+    let p = aranet4();
+    p.notify(CURRENT_READING_FULL.uuid, vec![0xff; 4]);
+    let client = AranetClient::new(p);
+    let co2 = client.read_history(Sensor::CO2).await.unwrap().samples;
+    assert_eq!(co2, vec![600.0, 650.0, 700.0, 800.0, 850.0]);
+}
+
+#[tokio::test]
+async fn history_ignores_other_sensors() {
+    let p = aranet4();
+    p.notify(HISTORY_NOTIFIER.uuid, vec![
+        0x01, 0x01, 0x00, 0x02, 0xa4, 0x01, 0xa5, 0x01,
+    ]);
+    let client = AranetClient::new(p);
+    let co2 = client.read_history(Sensor::CO2).await.unwrap().samples;
+    assert_eq!(co2, vec![600.0, 650.0, 700.0, 800.0, 850.0]);
+}
+
+#[tokio::test]
+async fn history_truncated_notification() {
+    let p = aranet4();
+    p.notify(HISTORY_NOTIFIER.uuid, vec![0x04, 0x01, 0x00, 0x02, 0x58]);
+    let client = AranetClient::new(p);
+    let error = client.read_history(Sensor::CO2).await.unwrap_err();
+    assert!(error.to_string().contains("too short"), "{error}");
+}
+
+#[tokio::test]
+async fn history_stall_times_out() {
+    let p = MockPeripheral::new()
+        .with_read(&STORED_READINGS, vec![0x05, 0x00])
+        .with_history(Sensor::CO2.id(), vec![vec![
+            0x04, 0x01, 0x00, 0x02, 0x58, 0x02, 0x8a, 0x02,
+        ]]);
+    let client = AranetClient::new(p).with_timeout(Duration::from_millis(10));
+    let mut download = client.begin_history(Sensor::CO2).await.unwrap();
+    let error = client.continue_history(&mut download).await.unwrap_err();
+    assert!(error.to_string().contains("received 2 of 5"), "{error}");
+    assert_eq!(download.next_index(), 3);
+    assert_eq!(&download.samples()[..2], &[600.0, 650.0]);
+}
+
+#[tokio::test]
+async fn history_of_resumes() {
+    let p = aranet4().with_lost_packet(Sensor::Temperature.id(), 4, 1);
+    let client = AranetClient::new(p.clone()).with_timeout(Duration::from_millis(10));
+    let mut downloads = client
+        .begin_history_of(&[Sensor::Temperature, Sensor::CO2])
+        .await
+        .unwrap();
+    assert!(client.continue_history_of(&mut downloads).await.is_err());
+    assert_eq!(downloads[0].next_index(), 4);
+    client.continue_history_of(&mut downloads).await.unwrap();
+    assert!(downloads.iter().all(HistoryDownload::is_complete));
+    assert_eq!(downloads[1].samples(), [600.0, 650.0, 700.0, 800.0, 850.0]);
+    assert_eq!(p.writes()[1].1, vec![
+        0x82, 0x01, 0x00, 0x00, 0x04, 0x00, 0x05, 0x00
+    ]);
+}
+
+#[tokio::test]
+async fn history_refetches_lost_packet() {
+    let p = aranet4().with_lost_packet(Sensor::Temperature.id(), 1, 1);
+    let client = AranetClient::new(p.clone()).with_timeout(Duration::from_millis(10));
+    let temperature = client.read_history(Sensor::Temperature).await.unwrap();
+    assert!(temperature.is_complete());
+    assert!((temperature.samples[0] - 21.0).abs() < 1e-4);
+    assert_eq!(p.writes()[1].1, vec![
+        0x82, 0x01, 0x00, 0x00, 0x01, 0x00, 0x03, 0x00
+    ]);
+}
+
+#[tokio::test]
+async fn history_retries_setup() {
+    let p = aranet4()
+        .with_failing_subscribes(2)
+        .with_ignored_requests(2);
+    let client = AranetClient::new(p.clone()).with_timeout(Duration::from_millis(10));
+    let temperature = client.read_history(Sensor::Temperature).await.unwrap();
+    assert!(temperature.is_complete());
+    assert_eq!(p.write_types(), vec![
+        WriteType::WithoutResponse,
+        WriteType::WithResponse,
+        WriteType::WithResponse
+    ]);
+}
+
+#[tokio::test]
+async fn history_write_type() {
+    let p = aranet4().with_characteristic(COMMAND);
+    let client = AranetClient::new(p.clone());
+    client.read_history(Sensor::Temperature).await.unwrap();
+    assert_eq!(p.write_types(), vec![WriteType::WithResponse]);
+}
+
+#[tokio::test]
+async fn history_reports_missing() {
+    let p = aranet4().with_lost_packet(Sensor::Temperature.id(), 1, usize::MAX);
+    let client = AranetClient::new(p.clone()).with_timeout(Duration::from_millis(10));
+    let temperature = client.read_history(Sensor::Temperature).await.unwrap();
+    assert_eq!(temperature.missing, vec![1, 2, 3]);
+    assert!(temperature.samples[..3]
+        .iter()
+        .all(|sample| sample.is_nan()));
+    assert_eq!(
+        p.writes().len(),
+        1 + AranetClient::<MockPeripheral>::GAP_ATTEMPTS
+    );
+}
+
+#[tokio::test]
+async fn all_history() {
+    let p = aranet4();
+    let client = AranetClient::new(p.clone());
+    let history = client.read_all_history().await.unwrap();
+    assert_eq!(history.len(), 4);
+    assert!(history.values().all(HistoryResult::is_complete));
+    assert_eq!(history[&Sensor::Humidity].samples, vec![
+        45.0, 46.0, 47.0, 48.0, 49.0
+    ]);
+    assert_eq!(history[&Sensor::CO2].samples, vec![
+        600.0, 650.0, 700.0, 800.0, 850.0
+    ]);
+    let sensor_ids = p
+        .writes()
+        .iter()
+        .map(|(_, data)| data[1])
+        .collect::<Vec<_>>();
+    assert_eq!(sensor_ids, vec![1, 2, 3, 4]);
+}
+
+#[tokio::test]
+async fn history_of_sensors() {
+    let p = aranet4();
+    let client = AranetClient::new(p.clone());
+    let history = client
+        .read_history_of(&[Sensor::CO2, Sensor::Temperature, Sensor::CO2])
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 2);
+    let sensor_ids = p
+        .writes()
+        .iter()
+        .map(|(_, data)| data[1])
+        .collect::<Vec<_>>();
+    assert_eq!(sensor_ids, vec![1, 4]);
+}
+
+#[tokio::test]
+async fn history_timed() {
+    let client = AranetClient::new(aranet4());
+    let co2 = client.read_history_timed(Sensor::CO2).await.unwrap();
+    let steps = co2
+        .windows(2)
+        .map(|pair| (pair[1].0 - pair[0].0).num_seconds())
+        .collect::<Vec<_>>();
+    assert_eq!(steps, vec![300; 4]);
+}
+
+#[tokio::test]
+async fn history_timeline() {
+    let client = AranetClient::new(aranet4());
+    let before = Utc::now();
+    let co2 = client.read_history_timeline(Sensor::CO2).await.unwrap();
+    assert_eq!(co2.interval, Duration::from_secs(300));
+    assert_eq!(co2.samples.len(), 5);
+    let newest = co2.last_sample_time + chrono::Duration::seconds(42);
+    assert!(newest >= before && newest <= Utc::now());
+    assert_eq!(co2.times().last(), Some(co2.last_sample_time));
+}
+
+#[tokio::test]
+async fn history_timed_uses_device_clock() {
+    // 2024-01-02 03:04:05 local time, a Tuesday
+    let clock = vec![0xe8, 0x07, 0x01, 0x02, 0x03, 0x04, 0x05, 0x02, 0x00, 0x00];
+    let p = aranet4()
+        .with_characteristic(CURRENT_TIME)
+        .with_read(&CURRENT_TIME, clock);
+    let client = AranetClient::new(p);
+    let co2 = client.read_history_timed(Sensor::CO2).await.unwrap();
+    let clock = Local.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+    assert_eq!(co2.last().unwrap().0, clock - chrono::Duration::seconds(42));
+}
+
+#[test]
+fn current_time() {
+    assert_eq!(parse_current_time(&mut &[0; 10][..]), Ok(None));
+    assert!(parse_current_time(&mut &[0xe8, 0x07, 0x01][..]).is_err());
+}
+
+#[tokio::test]
+async fn history_since() {
+    let p = aranet4();
+    let client = AranetClient::new(p.clone());
+    let temperature = client
+        .read_history_since(Sensor::Temperature, 3)
+        .await
+        .unwrap()
+        .samples;
+    assert_eq!(temperature, vec![21.2, 21.35]);
+    assert_eq!(p.writes()[0].1, vec![
+        0x82, 0x01, 0x00, 0x00, 0x04, 0x00, 0x05, 0x00
+    ]);
+}
+
+#[tokio::test]
+async fn history_after() {
+    let p = aranet4();
+    let client = AranetClient::new(p.clone());
+    // The third of five samples, 300s apart with the newest 42s old.
+    let last_timestamp = Utc::now() - chrono::Duration::seconds(42 + 2 * 300);
+    let temperature = client
+        .read_history_after(Sensor::Temperature, last_timestamp)
+        .await
+        .unwrap()
+        .samples;
+    assert_eq!(temperature, vec![21.2, 21.35]);
+    assert_eq!(p.writes()[0].1, vec![
+        0x82, 0x01, 0x00, 0x00, 0x04, 0x00, 0x05, 0x00
+    ]);
+}
+
+#[test]
+fn sample_index_of_full_history() {
+    let interval = chrono::Duration::seconds(300);
+    let newest = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+    let time = newest - interval * 3 + chrono::Duration::seconds(20);
+    assert_eq!(sample_index(newest, interval, time, 2016), Some(2013));
+    // Two samples later the history is still full, the sample moved down.
+    let later = newest + interval * 2;
+    assert_eq!(sample_index(later, interval, time, 2016), Some(2011));
+    // Dropped from the history, or newer than the newest sample.
+    assert_eq!(sample_index(later, interval, time, 5), None);
+    assert_eq!(sample_index(newest, interval, later, 2016), None);
+}
+
+#[tokio::test]
+async fn demultiplex_notifications() {
+    let notification = |characteristic: &Characteristic, value: Vec<u8>| ValueNotification {
+        uuid: characteristic.uuid,
+        value,
+    };
+    let notifications = stream::iter([
+        notification(
+            &HISTORY_NOTIFIER,
+            packet(include_str!("fixtures/aranet4_history_co2.hex")),
+        ),
+        notification(
+            &CURRENT_READING_NOTIFY,
+            packet(include_str!("fixtures/aranet4_current_reading_full.hex")),
+        ),
+        // Unknown sensor.
+        notification(&HISTORY_NOTIFIER, vec![0x09, 0x01, 0x00, 0x01, 0x00]),
+        // Truncated.
+        notification(&HISTORY_NOTIFIER, vec![0x02, 0x06, 0x00, 0x02, 0x2d]),
+        notification(
+            &HISTORY_NOTIFIER,
+            packet(include_str!("fixtures/aranet4_history_humidity.hex")),
+        ),
+    ]);
+    let chunks = aranet_notifications(notifications)
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks[0].as_ref().unwrap(), &HistoryChunk {
+        sensor: Sensor::CO2,
+        index:  1,
+        values: vec![600.0, 650.0, 700.0, 800.0, 850.0],
+    });
+    assert!(matches!(chunks[1], Err(AranetError::InvalidData(_))));
+    let humidity = chunks[2].as_ref().unwrap();
+    assert_eq!(humidity.sensor, Sensor::Humidity);
+    assert_eq!(humidity.indices(), 1..6);
+}
@@ -0,0 +1,386 @@
+//! A fake [`Peripheral`] replaying canned characteristic values.
+
+#![allow(dead_code)]
+
+use async_trait::async_trait;
+use btleplug::{
+    api::{
+        BDAddr, Central, CentralEvent, Characteristic, Manager, Peripheral, PeripheralProperties,
+        ScanFilter, Service, ValueNotification, WriteType,
+    },
+    platform::PeripheralId,
+    Result,
+};
+use cotracker::{
+    characteristics::{HISTORY_NOTIFIER, HISTORY_RANGE},
+    CurrentReading, Status,
+};
+use futures::{
+    channel::mpsc::{self, UnboundedSender},
+    stream::{self, Stream, StreamExt},
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
+
+/// Parse a fixture of whitespace separated hex bytes, one packet per line.
+/// Lines starting with `#` are comments.
+pub fn packets(fixture: &str) -> Vec<Vec<u8>> {
+    fixture
+        .lines()
+        .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+        .map(|line| {
+            line.split_whitespace()
+                .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+/// Parse a fixture holding a single packet.
+pub fn packet(fixture: &str) -> Vec<u8> {
+    packets(fixture).concat()
+}
+
+/// A reading of an Aranet4 without pressure, override fields with struct
+/// update syntax.
+pub const fn reading() -> CurrentReading {
+    CurrentReading {
+        co2:         Some(850),
+        co2_valid:   true,
+        temperature: 21.5,
+        humidity:    45,
+        pressure:    None,
+        battery:     87,
+        status:      Status::Green,
+        interval:    300,
+        age:         42,
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Discovered services.
+    services:    BTreeSet<Service>,
+    /// Values returned by reads.
+    reads:       HashMap<Uuid, Vec<u8>>,
+    /// Notifications sent after a history request for a sensor id.
+    history:     HashMap<u8, Vec<Vec<u8>>>,
+    /// Number of times to drop the history packet for a sensor id and start
+    /// index instead of sending it.
+    lost:        HashMap<(u8, u16), usize>,
+    /// Number of history requests to ignore before sending notifications.
+    ignored:     usize,
+    /// Number of subscribes to fail before succeeding.
+    failing:     usize,
+    /// Notifications to deliver on the next call to `notifications`.
+    pending:     Vec<ValueNotification>,
+    /// Streams returned by `notifications`.
+    subscribers: Vec<UnboundedSender<ValueNotification>>,
+    /// All writes received.
+    writes:      Vec<(Uuid, Vec<u8>)>,
+    /// Write type of each write.
+    write_types: Vec<WriteType>,
+}
+
+impl State {
+    fn send(&mut self, notification: ValueNotification) {
+        self.subscribers
+            .retain(|subscriber| !subscriber.is_closed());
+        if self.subscribers.is_empty() {
+            self.pending.push(notification);
+            return;
+        }
+        for subscriber in &self.subscribers {
+            subscriber.unbounded_send(notification.clone()).unwrap();
+        }
+    }
+}
+
+/// Peripheral serving canned characteristic values.
+///
+/// Writes to [`HISTORY_RANGE`] send the history notifications registered
+/// for the requested sensor that start within the requested range.
+#[derive(Clone, Debug, Default)]
+pub struct MockPeripheral {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockPeripheral {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_read(self, characteristic: &Characteristic, value: Vec<u8>) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .reads
+            .insert(characteristic.uuid, value);
+        self
+    }
+
+    /// Add a characteristic to the discovered services.
+    #[must_use]
+    pub fn with_characteristic(self, characteristic: Characteristic) -> Self {
+        let mut state = self.state.lock().unwrap();
+        let mut service = state
+            .services
+            .iter()
+            .find(|service| service.uuid == characteristic.service_uuid)
+            .cloned()
+            .unwrap_or(Service {
+                uuid:            characteristic.service_uuid,
+                primary:         true,
+                characteristics: BTreeSet::new(),
+            });
+        state.services.remove(&service);
+        service.characteristics.insert(characteristic);
+        state.services.insert(service);
+        drop(state);
+        self
+    }
+
+    #[must_use]
+    pub fn with_history(self, sensor_id: u8, packets: Vec<Vec<u8>>) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .history
+            .insert(sensor_id, packets);
+        self
+    }
+
+    /// Drop the history packet starting at `index` the first `times` it is
+    /// requested, as if lost in transit.
+    #[must_use]
+    pub fn with_lost_packet(self, sensor_id: u8, index: u16, times: usize) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .lost
+            .insert((sensor_id, index), times);
+        self
+    }
+
+    /// Ignore the first `times` history requests, as some devices only start
+    /// sending on a repeated request.
+    #[must_use]
+    pub fn with_ignored_requests(self, times: usize) -> Self {
+        self.state.lock().unwrap().ignored = times;
+        self
+    }
+
+    /// Fail the first `times` subscribes.
+    #[must_use]
+    pub fn with_failing_subscribes(self, times: usize) -> Self {
+        self.state.lock().unwrap().failing = times;
+        self
+    }
+
+    /// Send a notification regardless of requests.
+    pub fn notify(&self, uuid: Uuid, value: Vec<u8>) {
+        self.state
+            .lock()
+            .unwrap()
+            .send(ValueNotification { uuid, value });
+    }
+
+    pub fn writes(&self) -> Vec<(Uuid, Vec<u8>)> {
+        self.state.lock().unwrap().writes.clone()
+    }
+
+    pub fn write_types(&self) -> Vec<WriteType> {
+        self.state.lock().unwrap().write_types.clone()
+    }
+}
+
+#[async_trait]
+impl Peripheral for MockPeripheral {
+    fn id(&self) -> PeripheralId {
+        // Peripheral ids can not be constructed outside btleplug.
+        panic!("MockPeripheral has no id; callers must use address()")
+    }
+
+    fn address(&self) -> BDAddr {
+        BDAddr::default()
+    }
+
+    async fn properties(&self) -> Result<Option<PeripheralProperties>> {
+        Ok(Some(PeripheralProperties::default()))
+    }
+
+    fn services(&self) -> BTreeSet<Service> {
+        self.state.lock().unwrap().services.clone()
+    }
+
+    async fn is_connected(&self) -> Result<bool> {
+        Ok(true)
+    }
+
+    async fn connect(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn discover_services(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn write(
+        &self,
+        characteristic: &Characteristic,
+        data: &[u8],
+        write_type: WriteType,
+    ) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.writes.push((characteristic.uuid, data.to_vec()));
+        state.write_types.push(write_type);
+        if characteristic.uuid == HISTORY_RANGE.uuid && data[0] == 0x82 {
+            if state.ignored > 0 {
+                state.ignored -= 1;
+                return Ok(());
+            }
+            let sensor_id = data[1];
+            let start = u16::from_le_bytes([data[4], data[5]]);
+            let end = u16::from_le_bytes([data[6], data[7]]);
+            let packets = state.history.get(&sensor_id).cloned().unwrap_or_default();
+            for value in packets {
+                let index = u16::from_le_bytes([value[1], value[2]]);
+                if !(start..=end).contains(&index) {
+                    continue;
+                }
+                if let Some(times) = state.lost.get_mut(&(sensor_id, index)) {
+                    if *times > 0 {
+                        *times -= 1;
+                        continue;
+                    }
+                }
+                state.send(ValueNotification {
+                    uuid: HISTORY_NOTIFIER.uuid,
+                    value,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    async fn read(&self, characteristic: &Characteristic) -> Result<Vec<u8>> {
+        let state = self.state.lock().unwrap();
+        state
+            .reads
+            .get(&characteristic.uuid)
+            .cloned()
+            .ok_or_else(|| btleplug::Error::NotSupported(characteristic.uuid.to_string()))
+    }
+
+    async fn subscribe(&self, _characteristic: &Characteristic) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.failing > 0 {
+            state.failing -= 1;
+            return Err(btleplug::Error::Other("subscribe failed".into()));
+        }
+        Ok(())
+    }
+
+    async fn unsubscribe(&self, _characteristic: &Characteristic) -> Result<()> {
+        Ok(())
+    }
+
+    async fn notifications(&self) -> Result<Pin<Box<dyn Stream<Item = ValueNotification> + Send>>> {
+        let mut state = self.state.lock().unwrap();
+        let (sender, receiver) = mpsc::unbounded();
+        for notification in std::mem::take(&mut state.pending) {
+            sender.unbounded_send(notification).unwrap();
+        }
+        // Like a real device, the stream stays open after the last notification.
+        state.subscribers.push(sender);
+        Ok(receiver.boxed())
+    }
+}
+
+/// Adapter that has already found some peripherals and never finds more.
+#[derive(Clone, Debug, Default)]
+pub struct MockCentral {
+    peripherals: Vec<MockPeripheral>,
+    reset:       bool,
+}
+
+impl MockCentral {
+    pub fn new(peripherals: Vec<MockPeripheral>) -> Self {
+        Self {
+            peripherals,
+            reset: false,
+        }
+    }
+
+    /// Fail like the handle of an adapter that was reset.
+    pub fn reset(self) -> Self {
+        Self {
+            reset: true,
+            ..self
+        }
+    }
+
+    fn check_reset(&self) -> Result<()> {
+        if self.reset {
+            return Err(btleplug::Error::Other("adapter was reset".into()));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Central for MockCentral {
+    type Peripheral = MockPeripheral;
+
+    async fn events(&self) -> Result<Pin<Box<dyn Stream<Item = CentralEvent> + Send>>> {
+        self.check_reset()?;
+        Ok(stream::empty().boxed())
+    }
+
+    async fn start_scan(&self, _filter: ScanFilter) -> Result<()> {
+        Ok(())
+    }
+
+    async fn stop_scan(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn peripherals(&self) -> Result<Vec<MockPeripheral>> {
+        Ok(self.peripherals.clone())
+    }
+
+    async fn peripheral(&self, _id: &PeripheralId) -> Result<MockPeripheral> {
+        Err(btleplug::Error::NotSupported("peripheral".to_owned()))
+    }
+
+    async fn add_peripheral(&self, _id: &PeripheralId) -> Result<MockPeripheral> {
+        Err(btleplug::Error::NotSupported("add_peripheral".to_owned()))
+    }
+
+    async fn adapter_info(&self) -> Result<String> {
+        self.check_reset()?;
+        Ok("mock".to_owned())
+    }
+}
+
+/// Bluetooth stack with a fixed list of adapters.
+#[derive(Clone, Debug, Default)]
+pub struct MockManager(pub Vec<MockCentral>);
+
+#[async_trait]
+impl Manager for MockManager {
+    type Adapter = MockCentral;
+
+    async fn adapters(&self) -> Result<Vec<MockCentral>> {
+        Ok(self.0.clone())
+    }
+}
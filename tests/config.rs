@@ -0,0 +1,44 @@
+use cotracker::{
+    config::Config,
+    units::{PressureUnit, TemperatureUnit},
+};
+
+#[test]
+fn parse() {
+    let config = Config::parse(
+        r#"
+        address = "EB:12:34:56:78:9A"
+        units = "f"
+        pressure-units = "mmhg"
+        format = "json"
+        scan-timeout = 5.0
+        co2-threshold = 1400
+
+        [devices]
+        "eb:12:34:56:78:9b" = "living_room"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.address, Some("EB:12:34:56:78:9A".parse().unwrap()));
+    assert_eq!(config.units, Some(TemperatureUnit::Fahrenheit));
+    assert_eq!(config.pressure_units, Some(PressureUnit::Mmhg));
+    assert_eq!(config.format.as_deref(), Some("json"));
+    assert_eq!(config.scan_timeout, Some(5.0));
+    assert_eq!(config.co2_threshold, Some(1400));
+    assert_eq!(config.adapter, None);
+    let living_room = "EB:12:34:56:78:9B".parse().unwrap();
+    assert_eq!(config.device_name(living_room), Some("living_room"));
+    assert_eq!(config.device_name(config.address.unwrap()), None);
+}
+
+#[test]
+fn empty() {
+    assert_eq!(Config::parse("").unwrap(), Config::default());
+}
+
+#[test]
+fn invalid() {
+    assert!(Config::parse("units = \"x\"").is_err());
+    assert!(Config::parse("unknown = 1").is_err());
+    assert!(Config::parse("[devices]\nkitchen = \"kitchen\"").is_err());
+}
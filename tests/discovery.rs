@@ -0,0 +1,98 @@
+mod common;
+
+use btleplug::api::{BDAddr, PeripheralProperties};
+use common::{MockCentral, MockManager};
+use cotracker::{
+    discovery::{
+        adapter_matches, assign_adapters, dedup_devices, select_adapter, DiscoveredDevice,
+        DEFAULT_NAME_PREFIX,
+    },
+    AranetError, DeviceType,
+};
+use std::collections::BTreeSet;
+
+fn device(address: &str, rssi: Option<i16>) -> DiscoveredDevice {
+    DiscoveredDevice {
+        address: address.parse().unwrap(),
+        name: "Aranet4 1A2B3".to_owned(),
+        alias: None,
+        device_type: DeviceType::Aranet4,
+        rssi,
+        tx_power_level: None,
+    }
+}
+
+#[test]
+fn dedup() {
+    let devices = dedup_devices([
+        device("01:02:03:04:05:06", Some(-60)),
+        device("01:02:03:04:05:07", None),
+        device("01:02:03:04:05:06", Some(-80)),
+        device("01:02:03:04:05:07", Some(-70)),
+    ]);
+    assert_eq!(devices, vec![
+        device("01:02:03:04:05:06", Some(-60)),
+        device("01:02:03:04:05:07", Some(-70)),
+    ]);
+}
+
+#[test]
+fn name_prefix() {
+    let props = |name: &str| PeripheralProperties {
+        local_name: Some(name.to_owned()),
+        ..PeripheralProperties::default()
+    };
+    let aranet2 = DiscoveredDevice::from_properties(&props("Aranet2 1A2B3"), DEFAULT_NAME_PREFIX);
+    assert_eq!(aranet2.unwrap().device_type, DeviceType::Aranet2);
+    assert!(DiscoveredDevice::from_properties(&props("Kitchen"), DEFAULT_NAME_PREFIX).is_none());
+    let renamed = DiscoveredDevice::from_properties(&props("Kitchen"), "").unwrap();
+    assert_eq!(renamed.device_type, DeviceType::Aranet4);
+    assert!(DiscoveredDevice::from_properties(&props("Aranet2 1A2B3"), "Aranet4").is_none());
+}
+
+#[test]
+fn min_rssi() {
+    assert!(device("01:02:03:04:05:06", Some(-70)).has_signal(Some(-80)));
+    assert!(device("01:02:03:04:05:06", Some(-80)).has_signal(Some(-80)));
+    assert!(!device("01:02:03:04:05:06", Some(-90)).has_signal(Some(-80)));
+    assert!(device("01:02:03:04:05:06", None).has_signal(Some(-80)));
+    assert!(device("01:02:03:04:05:06", Some(-90)).has_signal(None));
+}
+
+#[test]
+fn assign_round_robin() {
+    let address = |i| format!("01:02:03:04:05:0{i}").parse::<BDAddr>().unwrap();
+    let both = (1..=4).map(address).collect::<BTreeSet<_>>();
+    let first_only = [address(5)].into_iter().collect::<BTreeSet<_>>();
+    let sightings = [both.iter().copied().chain(first_only).collect(), both];
+    assert_eq!(assign_adapters(&sightings), vec![
+        vec![address(1), address(3), address(5)],
+        vec![address(2), address(4)],
+    ]);
+    assert_eq!(assign_adapters(&[]), Vec::<Vec<BDAddr>>::new());
+}
+
+#[test]
+fn adapter_by_address() {
+    let info = "hci0 (usb:v1D6Bp0246d0540)";
+    let address = Some("00:1A:7D:DA:71:13".parse().unwrap());
+    assert!(adapter_matches(info, address, info));
+    assert!(adapter_matches(info, address, "hci0"));
+    assert!(adapter_matches(info, address, "usb:v1d6bp0246d0540"));
+    assert!(adapter_matches(info, address, "00:1a:7d:da:71:13"));
+    assert!(!adapter_matches(info, address, "hci1"));
+    assert!(!adapter_matches(info, None, "00:1A:7D:DA:71:13"));
+    assert!(adapter_matches("CoreBluetooth", None, "corebluetooth"));
+}
+
+#[tokio::test]
+async fn adapter_index_out_of_range() {
+    let manager = MockManager(vec![MockCentral::default(), MockCentral::default()]);
+    assert!(select_adapter(&manager, Some("1")).await.is_ok());
+    let error = select_adapter(&manager, Some("5")).await.unwrap_err();
+    assert!(matches!(error, AranetError::NoAdapter { .. }));
+    assert_eq!(
+        error.to_string(),
+        "no bluetooth adapter 5, available adapters:\n  0: mock\n  1: mock"
+    );
+}
@@ -0,0 +1,14 @@
+use cotracker::dump::write_hex_dump;
+
+#[test]
+fn hex_dump() {
+    let data = b"\x52\x03\xab\x08Aranet4 \x00\x01\x02\x03\x04\x05\x06\x07\xff";
+    let mut dump = Vec::new();
+    write_hex_dump(&mut dump, data).unwrap();
+    assert_eq!(
+        String::from_utf8(dump).unwrap(),
+        "00000000  52 03 ab 08 41 72 61 6e  65 74 34 20 00 01 02 03  |R...Aranet4 ....|\n\
+         00000010  04 05 06 07 ff                                    |.....|\n\
+         00000015\n"
+    );
+}
@@ -0,0 +1,194 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use cotracker::{
+    export::{
+        write_history_csv, write_history_csv_wide, write_history_influx, write_reading_influx,
+        ExportWriter,
+    },
+    history::{
+        align_history, derived_history, parse_duration, resample, samples_since, Aggregation,
+        Since, Summary,
+    },
+    CurrentReading, Sensor,
+};
+use flate2::read::GzDecoder;
+use std::io::{Read, Write};
+
+#[test]
+fn history_csv() {
+    let t0 = Utc.with_ymd_and_hms(2022, 8, 20, 12, 0, 0).unwrap();
+    let t1 = Utc.with_ymd_and_hms(2022, 8, 20, 12, 5, 0).unwrap();
+    let readings = [
+        (Sensor::Temperature, vec![(t0, 21.0), (t1, f32::NAN)]),
+        (Sensor::CO2, vec![(t0, 600.0)]),
+    ];
+    let mut csv = Vec::new();
+    write_history_csv(&mut csv, &readings, &[]).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "timestamp,sensor,value\n2022-08-20T12:00:00Z,temperature,21\n2022-08-20T12:00:00Z,co2,\
+         600\n2022-08-20T12:05:00Z,temperature,\n"
+    );
+}
+
+#[test]
+fn history_csv_derived() {
+    let t0 = Utc.with_ymd_and_hms(2022, 8, 20, 12, 0, 0).unwrap();
+    let readings = [
+        (Sensor::Temperature, vec![(t0, 0.0)]),
+        (Sensor::Humidity, vec![(t0, 100.0)]),
+    ];
+    let derived = derived_history(&readings);
+    assert_eq!(derived[0].0, "dew_point");
+    assert_eq!(derived[1].0, "absolute_humidity");
+    let mut csv = Vec::new();
+    write_history_csv(&mut csv, &readings[..1], &derived[..1]).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "timestamp,sensor,value\n2022-08-20T12:00:00Z,temperature,0\n2022-08-20T12:00:00Z,\
+         dew_point,0\n"
+    );
+}
+
+#[test]
+fn history_csv_wide() {
+    let t0 = Utc.with_ymd_and_hms(2022, 8, 20, 12, 0, 0).unwrap();
+    let t1 = Utc.with_ymd_and_hms(2022, 8, 20, 12, 5, 0).unwrap();
+    let readings = [
+        (Sensor::Temperature, vec![(t0, 21.0), (t1, f32::NAN)]),
+        (Sensor::CO2, vec![(t1, 600.0)]),
+    ];
+    let mut csv = Vec::new();
+    write_history_csv_wide(&mut csv, &readings, &[]).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "timestamp,temperature,co2
+2022-08-20T12:00:00Z,21,
+2022-08-20T12:05:00Z,,600
+"
+    );
+}
+
+#[test]
+fn align_on_newest() {
+    let t = |minutes| Utc.with_ymd_and_hms(2022, 8, 20, 12, minutes, 0).unwrap();
+    let humidity = vec![(t(0), 40.0), (t(5), 41.0), (t(10), 42.0)];
+    let co2 = vec![(t(5), 600.0), (t(10), 610.0)];
+    let rows = align_history(&[&humidity, &co2]);
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0].0, t(0));
+    assert!(rows[0].1[1].is_nan());
+    assert_eq!(rows[2], (t(10), vec![42.0, 610.0]));
+}
+
+#[test]
+fn reading_influx() {
+    let reading = CurrentReading {
+        temperature: 21.35,
+        pressure: Some(1013.2),
+        ..common::reading()
+    };
+    let timestamp = Utc.timestamp_opt(1_661_000_000, 0).unwrap();
+    let mut line = Vec::new();
+    let address = "01:02:03:04:05:06".parse().unwrap();
+    write_reading_influx(&mut line, "air quality", address, &reading, timestamp).unwrap();
+    assert_eq!(
+        String::from_utf8(line).unwrap(),
+        "air\\ quality,address=01:02:03:04:05:06 \
+         co2=850i,temperature=21.35,humidity=45i,pressure=1013.2,battery=87i 1661000000000000000\n"
+    );
+}
+
+#[test]
+fn history_influx() {
+    let timestamp = Utc.timestamp_opt(1_661_000_000, 0).unwrap();
+    let readings = [(Sensor::CO2, vec![(timestamp, 600.0), (timestamp, f32::NAN)])];
+    let mut lines = Vec::new();
+    let address = "01:02:03:04:05:06".parse().unwrap();
+    write_history_influx(&mut lines, "aranet", address, &readings).unwrap();
+    assert_eq!(
+        String::from_utf8(lines).unwrap(),
+        "aranet,address=01:02:03:04:05:06 co2=600 1661000000000000000\n"
+    );
+}
+
+#[test]
+fn resample_history() {
+    let at = |minute| Utc.with_ymd_and_hms(2022, 8, 20, 12, minute, 0).unwrap();
+    let samples = [
+        (at(0), 600.0),
+        (at(5), 700.0),
+        (at(10), f32::NAN),
+        (at(15), 900.0),
+        (at(20), 1000.0),
+    ];
+    let width = parse_duration("15m").unwrap();
+    assert_eq!(resample(&samples, width, Aggregation::Mean), vec![
+        (at(0), 650.0),
+        (at(15), 950.0)
+    ]);
+    assert_eq!(resample(&samples, width, Aggregation::Max), vec![
+        (at(0), 700.0),
+        (at(15), 1000.0)
+    ]);
+    assert_eq!(resample(&samples, width, "last".parse().unwrap()), vec![
+        (at(0), 700.0),
+        (at(15), 1000.0)
+    ]);
+}
+
+#[test]
+fn since() {
+    let now = Utc.with_ymd_and_hms(2024, 1, 2, 12, 0, 0).unwrap();
+    let since = "24h".parse::<Since>().unwrap();
+    assert_eq!(since.start(now), now - chrono::Duration::hours(24));
+    let since = "2024-01-02T10:00:00+01:00".parse::<Since>().unwrap();
+    assert_eq!(
+        since,
+        Since::At(Utc.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap())
+    );
+    assert!("2024-01-01".parse::<Since>().is_ok());
+    assert!("yesterday".parse::<Since>().is_err());
+
+    let interval = chrono::Duration::minutes(5);
+    let start = |minutes| now - chrono::Duration::minutes(minutes);
+    assert_eq!(samples_since(now, interval, start(0), 100), 1);
+    assert_eq!(samples_since(now, interval, start(14), 100), 3);
+    assert_eq!(samples_since(now, interval, start(15), 100), 4);
+    assert_eq!(samples_since(now, interval, start(24 * 60), 100), 100);
+    assert_eq!(samples_since(now, interval, start(-1), 100), 0);
+}
+
+#[test]
+fn export_writer_gzip() {
+    let mut writer = ExportWriter::new(Vec::new(), true);
+    writeln!(writer, "timestamp,sensor,value").unwrap();
+    let compressed = writer.finish().unwrap();
+    let mut decompressed = String::new();
+    GzDecoder::new(compressed.as_slice())
+        .read_to_string(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, "timestamp,sensor,value\n");
+
+    let mut writer = ExportWriter::new(Vec::new(), false);
+    writeln!(writer, "plain").unwrap();
+    assert_eq!(writer.finish().unwrap(), b"plain\n");
+}
+
+#[test]
+fn summary() {
+    let summary = Summary::of([f32::NAN, 600.0, 900.0, f32::NAN, 750.0, f32::NAN]).unwrap();
+    assert_eq!(summary, Summary {
+        min:    600.0,
+        max:    900.0,
+        mean:   750.0,
+        latest: 750.0,
+        count:  3,
+    });
+    assert_eq!(Summary::of([f32::NAN]), None);
+    assert_eq!(
+        serde_json::to_string(&summary).unwrap(),
+        r#"{"min":600.0,"max":900.0,"mean":750.0,"latest":750.0,"count":3}"#
+    );
+}
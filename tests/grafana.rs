@@ -0,0 +1,77 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use cotracker::{
+    grafana::{Query, RecentReadings},
+    CurrentReading,
+};
+
+fn reading(co2: u16) -> CurrentReading {
+    CurrentReading {
+        co2: Some(co2),
+        age: 0,
+        ..common::reading()
+    }
+}
+
+fn recent() -> RecentReadings {
+    let address = "01:02:03:04:05:06".parse().unwrap();
+    let start = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+    let mut recent = RecentReadings::new(3);
+    for (i, co2) in [800, 850, 900, 950].into_iter().enumerate() {
+        let now = start + chrono::Duration::minutes(5 * i64::try_from(i).unwrap());
+        recent.push(address, "Office", now, reading(co2));
+    }
+    // The same measurement read again.
+    let mut again = reading(950);
+    again.age = 60;
+    recent.push(
+        address,
+        "Office",
+        start + chrono::Duration::minutes(16),
+        again,
+    );
+    recent
+}
+
+#[test]
+fn ring_buffer() {
+    let series = recent().query(&Query::default());
+    assert_eq!(series.len(), 5);
+    assert_eq!(series[0].target, "Office.co2");
+    assert_eq!(series[0].datapoints, [
+        (850.0, 1_714_565_100_000),
+        (900.0, 1_714_565_400_000),
+        (950.0, 1_714_565_700_000),
+    ]);
+    assert_eq!(series[3].target, "Office.pressure");
+    assert!(series[3].datapoints.is_empty());
+}
+
+#[test]
+fn query() {
+    let query: Query = serde_json::from_str(
+        r#"{
+            "range": {"from": "2024-05-01T12:04:00Z", "to": "2024-05-01T12:11:00Z"},
+            "targets": [{"target": "Office.co2"}, {"target": "Kitchen.co2"}],
+            "maxDataPoints": 1
+        }"#,
+    )
+    .unwrap();
+    let series = recent().query(&query);
+    assert_eq!(
+        serde_json::to_string(&series).unwrap(),
+        r#"[{"target":"Office.co2","datapoints":[[900.0,1714565400000]]}]"#
+    );
+}
+
+#[test]
+fn targets() {
+    assert_eq!(recent().targets(), [
+        "Office.co2",
+        "Office.temperature",
+        "Office.humidity",
+        "Office.pressure",
+        "Office.battery",
+    ]);
+}
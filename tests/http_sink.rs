@@ -0,0 +1,93 @@
+mod common;
+
+use axum::{
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use chrono::{TimeZone, Utc};
+use cotracker::http_sink::{parse_header, HttpSink, HttpSinkConfig};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::net::TcpListener;
+
+/// Start an endpoint that fails the first request, returning its url and the
+/// bodies it received.
+async fn endpoint() -> (String, Arc<Mutex<Vec<String>>>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let app = Router::new().route(
+        "/ingest",
+        post({
+            let received = Arc::clone(&received);
+            move |headers: HeaderMap, body: String| async move {
+                assert_eq!(headers["x-api-key"], "secret");
+                assert_eq!(headers["content-type"], "application/json");
+                let mut received = received.lock().unwrap();
+                received.push(body);
+                if received.len() == 1 {
+                    StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    StatusCode::NO_CONTENT
+                }
+            }
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/ingest", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (url, received)
+}
+
+#[tokio::test]
+async fn post_each_reading() {
+    let (url, received) = endpoint().await;
+    let config = HttpSinkConfig::new(&url, &["X-Api-Key: secret".to_owned()]).unwrap();
+    let sink = HttpSink::connect(config).unwrap();
+    let address = "01:02:03:04:05:06".parse().unwrap();
+    let t0 = Utc.with_ymd_and_hms(2022, 8, 20, 12, 0, 0).unwrap();
+    sink.write_reading(address, Some("office"), &common::reading(), t0)
+        .unwrap();
+    sink.close(Duration::from_secs(10)).await.unwrap();
+
+    let received = received.lock().unwrap();
+    // The first attempt failed and was retried.
+    assert_eq!(received.len(), 2);
+    assert_eq!(
+        received[1],
+        r#"{"address":"01:02:03:04:05:06","name":"office","timestamp":"2022-08-20T12:00:00Z","co2":850,"co2_valid":true,"temperature_c":21.5,"humidity":45,"pressure_hpa":null,"battery":87,"status":"green","interval":300,"age":42}"#
+    );
+}
+
+#[tokio::test]
+async fn post_batches() {
+    let (url, received) = endpoint().await;
+    let config = HttpSinkConfig::new(&url, &["X-Api-Key: secret".to_owned()])
+        .unwrap()
+        .with_batch_size(2);
+    let sink = HttpSink::connect(config).unwrap();
+    let address = "01:02:03:04:05:06".parse().unwrap();
+    let t0 = Utc.with_ymd_and_hms(2022, 8, 20, 12, 0, 0).unwrap();
+    for _ in 0..3 {
+        sink.write_reading(address, None, &common::reading(), t0)
+            .unwrap();
+    }
+    sink.close(Duration::from_secs(10)).await.unwrap();
+
+    let received = received.lock().unwrap();
+    let batches = received[1..]
+        .iter()
+        .map(|body| serde_json::from_str::<Vec<serde_json::Value>>(body).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), [2, 1]);
+    assert!(batches[0][0].get("name").is_none());
+}
+
+#[test]
+fn headers() {
+    let (name, value) = parse_header("Authorization: Bearer abc").unwrap();
+    assert_eq!(name, "authorization");
+    assert_eq!(value, "Bearer abc");
+    assert!(parse_header("Authorization").is_err());
+}
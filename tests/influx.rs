@@ -0,0 +1,67 @@
+mod common;
+
+use axum::{
+    http::{HeaderMap, StatusCode, Uri},
+    routing::post,
+    Router,
+};
+use chrono::{TimeZone, Utc};
+use cotracker::{
+    influx::{InfluxConfig, InfluxWriter},
+    Sensor,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::net::TcpListener;
+
+#[tokio::test]
+async fn write_batches() {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let app = Router::new().route(
+        "/api/v2/write",
+        post({
+            let received = Arc::clone(&received);
+            move |uri: Uri, headers: HeaderMap, body: String| async move {
+                assert_eq!(uri.query(), Some("org=home&bucket=air"));
+                assert_eq!(headers["authorization"], "Token secret");
+                let mut received = received.lock().unwrap();
+                received.push(body);
+                // Fail the first attempt to check it is retried.
+                if received.len() == 1 {
+                    StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    StatusCode::NO_CONTENT
+                }
+            }
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let config = InfluxConfig {
+        url,
+        org: "home".to_owned(),
+        bucket: "air".to_owned(),
+        token: "secret".to_owned(),
+    };
+    let writer = InfluxWriter::connect(config, "aranet").unwrap();
+    let address = "01:02:03:04:05:06".parse().unwrap();
+    let t0 = Utc.with_ymd_and_hms(2022, 8, 20, 12, 0, 0).unwrap();
+    let reading = common::reading();
+    writer.write_reading(address, &reading, t0).unwrap();
+    writer
+        .write_history(address, &[(Sensor::CO2, vec![(t0, 600.0)])])
+        .unwrap();
+    writer.close(Duration::from_secs(10)).await.unwrap();
+
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    assert_eq!(
+        received[1],
+        "aranet,address=01:02:03:04:05:06 co2=850i,temperature=21.5,humidity=45i,battery=87i \
+         1660996800000000000\naranet,address=01:02:03:04:05:06 co2=600 1660996800000000000\n"
+    );
+}
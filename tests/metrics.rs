@@ -0,0 +1,28 @@
+mod common;
+
+use cotracker::{
+    metrics::{write_metrics, DeviceReading},
+    CurrentReading,
+};
+
+#[test]
+fn metrics() {
+    let reading = CurrentReading {
+        co2: None,
+        co2_valid: false,
+        ..common::reading()
+    };
+    let devices = [DeviceReading {
+        address: "01:02:03:04:05:06".parse().unwrap(),
+        name: "Aranet2 \"office\"".to_owned(),
+        reading,
+    }];
+    let mut metrics = String::new();
+    write_metrics(&mut metrics, &devices).unwrap();
+    assert!(metrics.contains("# TYPE aranet_co2_ppm gauge\n# HELP"));
+    assert!(metrics.contains(
+        "aranet_temperature_celsius{address=\"01:02:03:04:05:06\",name=\"Aranet2 \
+         \\\"office\\\"\"} 21.5\n"
+    ));
+    assert!(!metrics.contains("aranet_pressure_hpa{"));
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use cotracker::{
+    mqtt::{reading_topics, MqttError, MqttPublisher, QUEUE_CAPACITY},
+    CurrentReading, Status,
+};
+use rumqttc::QoS;
+use serde_json::json;
+use std::time::Duration;
+
+#[test]
+fn topics() {
+    let reading = CurrentReading {
+        status: Status::Amber,
+        ..common::reading()
+    };
+    let topics = reading_topics("home/aranet", "01:02:03:04:05:06", &reading);
+    assert_eq!(topics, vec![
+        (
+            "home/aranet/01:02:03:04:05:06/co2".to_owned(),
+            json!({ "value": 850, "unit": "ppm" })
+        ),
+        (
+            "home/aranet/01:02:03:04:05:06/temperature".to_owned(),
+            json!({ "value": 21.5, "unit": "°C" })
+        ),
+        (
+            "home/aranet/01:02:03:04:05:06/humidity".to_owned(),
+            json!({ "value": 45, "unit": "%" })
+        ),
+        (
+            "home/aranet/01:02:03:04:05:06/battery".to_owned(),
+            json!({ "value": 87, "unit": "%" })
+        ),
+        (
+            "home/aranet/01:02:03:04:05:06/status".to_owned(),
+            json!({ "value": "amber" })
+        ),
+    ]);
+}
+
+#[tokio::test]
+async fn publish_without_broker() {
+    let reading = CurrentReading {
+        status: Status::Amber,
+        ..common::reading()
+    };
+    // Nothing listens on port 1, once the queue is full readings are dropped.
+    let mqtt = MqttPublisher::connect("mqtt://127.0.0.1:1", "aranet", QoS::AtLeastOnce).unwrap();
+    for _ in 0..QUEUE_CAPACITY {
+        mqtt.publish("office", &reading);
+    }
+}
+
+#[tokio::test]
+async fn close_without_broker() {
+    let mqtt = MqttPublisher::connect("mqtt://127.0.0.1:1", "aranet", QoS::AtLeastOnce).unwrap();
+    for _ in 0..QUEUE_CAPACITY {
+        mqtt.publish("office", &common::reading());
+    }
+    let closed = tokio::time::timeout(
+        Duration::from_secs(5),
+        mqtt.close(Duration::from_millis(100)),
+    )
+    .await
+    .expect("close should give up after its timeout");
+    assert!(matches!(closed, Err(MqttError::Timeout(_))));
+}
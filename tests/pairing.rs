@@ -0,0 +1,32 @@
+use cotracker::{AranetError, PairingRequired};
+
+#[test]
+fn pairing_errors() {
+    let not_authorized = btleplug::Error::Other("org.bluez.Error.NotAuthorized".into());
+    assert!(PairingRequired::is_pairing_error(&not_authorized));
+    assert!(PairingRequired::is_pairing_error(
+        &btleplug::Error::PermissionDenied
+    ));
+    assert!(!PairingRequired::is_pairing_error(
+        &btleplug::Error::NotConnected
+    ));
+}
+
+#[test]
+fn check() {
+    let address = "EB:12:34:56:78:9A".parse().unwrap();
+    let error = PairingRequired::check(address, btleplug::Error::PermissionDenied);
+    assert!(matches!(
+        error,
+        AranetError::PairingRequired(PairingRequired { address: a }) if a == address
+    ));
+    assert!(error
+        .to_string()
+        .contains("bluetoothctl pair EB:12:34:56:78:9A"));
+
+    let error = PairingRequired::check(address, btleplug::Error::NotConnected);
+    assert!(matches!(
+        error,
+        AranetError::Bluetooth(btleplug::Error::NotConnected)
+    ));
+}
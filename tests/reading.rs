@@ -0,0 +1,87 @@
+mod common;
+
+use cotracker::{
+    parse_current_reading,
+    units::{TemperatureUnit, Units},
+    CurrentReading, DeviceType, Status,
+};
+use std::time::Duration;
+
+fn reading() -> CurrentReading {
+    CurrentReading {
+        temperature: 21.35,
+        pressure: Some(1013.2),
+        status: Status::Amber,
+        ..common::reading()
+    }
+}
+
+#[test]
+fn display_table() {
+    assert_eq!(
+        reading().to_string(),
+        [
+            "CO2             850 ppm",
+            "Temperature   21.35 °C",
+            "Humidity         45 %",
+            "Pressure     1013.2 hPa",
+            "Battery          87 %",
+            "Status        Amber",
+            "Interval        300 s",
+            "Age              42 s",
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn display_color() {
+    let units = Units {
+        temperature: TemperatureUnit::Fahrenheit,
+        ..Units::default()
+    };
+    let reading = reading();
+    let table = reading.display(units).with_color(true).to_string();
+    assert!(table.starts_with("\x1b[33mCO2             850 ppm\x1b[0m\n"));
+    assert!(table.contains("Temperature   70.43 °F\n"));
+}
+
+#[test]
+fn battery_percent() {
+    let mut reading = reading();
+    assert_eq!(reading.battery_percent(), 87);
+    reading.battery = 0xff;
+    assert_eq!(reading.battery_percent(), 100);
+}
+
+#[test]
+fn staleness() {
+    let mut reading = reading();
+    assert_eq!(reading.seconds_until_next_measurement(), 258);
+    assert!(!reading.is_stale());
+    reading.age = 320;
+    assert_eq!(reading.seconds_until_next_measurement(), 0);
+    assert!(!reading.is_stale());
+    reading.age = 400;
+    assert!(reading.is_stale());
+}
+
+#[test]
+fn durations() {
+    let reading = reading();
+    assert_eq!(reading.interval(), Duration::from_secs(300));
+    assert_eq!(reading.age(), Duration::from_secs(42));
+}
+
+#[test]
+fn aranet2_humidity() {
+    let packet = |humidity: u16| {
+        let mut packet = vec![0x02, 0x00, 0x2c, 0x01, 0x2a, 0x00, 87, 0xab, 0x01];
+        packet.extend(humidity.to_le_bytes());
+        packet.push(1);
+        packet
+    };
+    let parse = |packet: Vec<u8>| parse_current_reading(DeviceType::Aranet2, &mut &packet[..]);
+    assert_eq!(parse(packet(455)).unwrap().humidity, 46);
+    assert!(parse(packet(u16::MAX)).is_err());
+}
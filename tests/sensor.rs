@@ -0,0 +1,85 @@
+use cotracker::{absolute_humidity, dew_point, ParseError, Sensor};
+
+#[test]
+fn read() {
+    assert_eq!(Sensor::Temperature.read(&mut &[0xab, 0x01][..]), Ok(21.35));
+    assert_eq!(Sensor::Humidity.read(&mut &[0x2d][..]), Ok(45.0));
+    assert_eq!(Sensor::Pressure.read(&mut &[0x94, 0x27][..]), Ok(1013.2));
+    assert_eq!(Sensor::CO2.read(&mut &[0x52, 0x03][..]), Ok(850.0));
+}
+
+#[test]
+fn read_truncated() {
+    assert_eq!(
+        Sensor::CO2.read(&mut &[0x52][..]),
+        Err(ParseError {
+            expected:  2,
+            remaining: 1,
+        })
+    );
+    assert_eq!(Sensor::Humidity.read_checked(&mut &[0_u8; 0][..]), None);
+}
+
+#[test]
+fn read_consumes_value() {
+    let mut reader = &[0xab, 0x01, 0x2d, 0x52, 0x03][..];
+    Sensor::Temperature.read(&mut reader).unwrap();
+    Sensor::Humidity.read(&mut reader).unwrap();
+    Sensor::CO2.read(&mut reader).unwrap();
+    assert!(reader.is_empty());
+}
+
+#[test]
+fn co2_warmup() {
+    assert!(Sensor::CO2.read(&mut &[0xff, 0xff][..]).unwrap().is_nan());
+    assert_eq!(Sensor::CO2.read_checked(&mut &[0xff, 0xff][..]), None);
+}
+
+#[test]
+fn read_checked() {
+    assert_eq!(
+        Sensor::Temperature.read_checked(&mut &[0xab, 0x01][..]),
+        Some(21.35)
+    );
+    // 3000 °C
+    assert_eq!(
+        Sensor::Temperature.read_checked(&mut &[0x60, 0xea][..]),
+        None
+    );
+    assert_eq!(Sensor::Humidity.read_checked(&mut &[0x65][..]), None);
+    assert_eq!(Sensor::Pressure.read_checked(&mut &[0x00, 0x00][..]), None);
+    assert_eq!(
+        Sensor::CO2.read_checked(&mut &[0x10, 0x27][..]),
+        Some(10_000.0)
+    );
+}
+
+#[test]
+fn derived() {
+    // Reference values from psychrometric tables.
+    assert!((dew_point(20.0, 50.0) - 9.3).abs() < 0.05);
+    assert!((dew_point(0.0, 100.0)).abs() < 1e-4);
+    assert!((dew_point(30.0, 80.0) - 26.2).abs() < 0.05);
+    assert!((absolute_humidity(20.0, 50.0) - 8.65).abs() < 0.05);
+    assert!((absolute_humidity(0.0, 100.0) - 4.85).abs() < 0.05);
+}
+
+#[test]
+fn parse() {
+    for sensor in Sensor::ALL {
+        assert_eq!(sensor.to_string().parse(), Ok(sensor));
+    }
+    assert_eq!("CO2".parse(), Ok(Sensor::CO2));
+    assert_eq!("temp".parse(), Ok(Sensor::Temperature));
+    assert!("radon".parse::<Sensor>().is_err());
+}
+
+#[test]
+fn units_and_ranges() {
+    for sensor in Sensor::ALL {
+        assert!(!sensor.unit().is_empty(), "{sensor}");
+        assert!(!sensor.plausible_range().is_empty(), "{sensor}");
+    }
+    assert_eq!(Sensor::CO2.unit(), "ppm");
+    assert!(Sensor::Pressure.plausible_range().contains(&1013.2));
+}
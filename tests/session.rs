@@ -0,0 +1,86 @@
+mod common;
+
+use btleplug::api::{Central, Peripheral};
+use common::{packet, MockCentral, MockPeripheral};
+use cotracker::{
+    characteristics::{CURRENT_READING_FULL, MODEL_NUMBER},
+    AranetClient, AranetError, AranetSession, DeviceType,
+};
+use futures::future;
+use std::{error::Error, ops::ControlFlow, time::Duration};
+
+fn device(model: &str) -> MockPeripheral {
+    MockPeripheral::new()
+        .with_read(&MODEL_NUMBER, model.as_bytes().to_vec())
+        .with_read(
+            &CURRENT_READING_FULL,
+            packet(include_str!("fixtures/aranet4_current_reading_full.hex")),
+        )
+}
+
+async fn connect(central: &MockCentral, p: MockPeripheral) -> AranetSession<'_, MockCentral> {
+    AranetSession::connect(central, AranetClient::new(p), Duration::ZERO)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn connect_detects_device_type() {
+    let central = MockCentral::default();
+    let session = connect(&central, device("Aranet2")).await;
+    assert_eq!(session.client().device_type(), DeviceType::Aranet2);
+}
+
+#[tokio::test]
+async fn read_once() {
+    let central = MockCentral::default();
+    let mut session = connect(&central, device("Aranet4")).await;
+    assert_eq!(session.read_once().await.unwrap().co2, Some(850));
+}
+
+#[tokio::test]
+async fn poll_until_break() {
+    let central = MockCentral::default();
+    let mut session = connect(&central, device("Aranet4")).await;
+    let mut readings = Vec::new();
+    session
+        .poll(Some(Duration::from_millis(1)), |reading| {
+            readings.push(reading);
+            future::ok::<_, AranetError>(if readings.len() < 3 {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            })
+        })
+        .await
+        .unwrap();
+    assert_eq!(readings.len(), 3);
+}
+
+#[tokio::test]
+async fn read_once_fails_when_connected() {
+    let p = MockPeripheral::new().with_read(&MODEL_NUMBER, b"Aranet4".to_vec());
+    let central = MockCentral::new(vec![p.clone()]);
+    let mut session = connect(&central, p).await;
+    // The device is still connected, so the failed read is not retried.
+    assert!(session.read_once().await.is_err());
+}
+
+#[tokio::test]
+async fn recovers_reset_adapter() {
+    let p = device("Aranet4");
+    let central = MockCentral::new(vec![p.clone()]).reset();
+    let mut session = connect(&central, p.clone()).await;
+    p.disconnect().await.unwrap();
+    let error = session.reconnect().await.unwrap_err();
+    assert!(matches!(error, AranetError::Connect { .. }));
+    // The cause is reported as the source only, not repeated in the message.
+    let source = error.source().unwrap().to_string();
+    assert!(!error.to_string().contains(&source), "{error}: {source}");
+
+    let recovered = MockCentral::new(vec![p.clone()]);
+    session.set_adapter_recovery(move || Box::pin(future::ok(recovered.clone())));
+    session.reconnect().await.unwrap();
+    assert_eq!(session.central().adapter_info().await.unwrap(), "mock");
+    assert_eq!(session.read_once().await.unwrap().co2, Some(850));
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use cotracker::{
+    smoothing::{ReadingSmoother, RollingAverage},
+    CurrentReading, Sensor,
+};
+
+fn reading(co2: u16, co2_valid: bool) -> CurrentReading {
+    CurrentReading {
+        co2: Some(co2),
+        co2_valid,
+        temperature: 21.0,
+        ..common::reading()
+    }
+}
+
+#[test]
+fn rolling_average() {
+    let mut average = RollingAverage::new(3);
+    assert_eq!(average.average(), None);
+    assert!((average.push(800.0) - 800.0).abs() < 1e-3);
+    average.push(900.0);
+    assert!((average.push(1000.0) - 900.0).abs() < 1e-3);
+    assert!((average.push(1300.0) - 1066.667).abs() < 1e-3);
+    assert_eq!(average.len(), 3);
+}
+
+#[test]
+fn window_per_sensor() {
+    let mut smoother = ReadingSmoother::new(2);
+    let smoothed = smoother.push(&reading(0xffff, false));
+    assert_eq!(smoothed.averages.get(&Sensor::CO2), None);
+    assert_eq!(smoothed.averages.get(&Sensor::Pressure), None);
+    smoother.push(&reading(800, true));
+    let mut warming_up = reading(0xffff, false);
+    warming_up.temperature = 23.0;
+    smoother.push(&warming_up);
+    let smoothed = smoother.push(&reading(900, true));
+    assert_eq!(smoothed.window, 2);
+    assert_eq!(smoothed.averages[&Sensor::CO2], 850.0);
+    assert_eq!(smoothed.averages[&Sensor::Temperature], 22.0);
+    assert_eq!(smoothed.averages[&Sensor::Humidity], 45.0);
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use cotracker::{sqlite::Database, CurrentReading, Sensor};
+use std::path::Path;
+
+#[test]
+fn history_skips_duplicates() {
+    let database = Database::open(Path::new(":memory:")).unwrap();
+    let address = "01:02:03:04:05:06".parse().unwrap();
+    let t0 = Utc.with_ymd_and_hms(2022, 8, 20, 12, 0, 0).unwrap();
+    let t1 = Utc.with_ymd_and_hms(2022, 8, 20, 12, 5, 0).unwrap();
+    let readings = [
+        (Sensor::Temperature, vec![(t0, 21.0), (t1, f32::NAN)]),
+        (Sensor::CO2, vec![(t0, 600.0), (t1, 650.0)]),
+    ];
+    assert_eq!(database.insert_history(address, &readings).unwrap(), 3);
+    assert_eq!(database.insert_history(address, &readings).unwrap(), 0);
+
+    // The same samples a second off, and one new sample.
+    let second = chrono::Duration::seconds(1);
+    let t2 = Utc.with_ymd_and_hms(2022, 8, 20, 12, 10, 0).unwrap();
+    let readings = [(Sensor::CO2, vec![
+        (t0 + second, 600.0),
+        (t1 + second, 650.0),
+        (t2 + second, 700.0),
+    ])];
+    assert_eq!(database.insert_history(address, &readings).unwrap(), 1);
+}
+
+#[test]
+fn reading() {
+    let database = Database::open(Path::new(":memory:")).unwrap();
+    let reading = CurrentReading {
+        co2: None,
+        co2_valid: false,
+        ..common::reading()
+    };
+    let address = "01:02:03:04:05:06".parse().unwrap();
+    database
+        .insert_reading(address, Utc::now(), &reading)
+        .unwrap();
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Duration, Utc};
+use cotracker::{
+    sync_state::{SensorSync, SyncState},
+    Sensor,
+};
+
+fn time(seconds: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(seconds, 0).unwrap()
+}
+
+#[test]
+fn synced_index() {
+    let interval = Duration::seconds(300);
+    let sync = SensorSync {
+        last_index:     10,
+        last_timestamp: time(3000),
+        interval:       Some(300),
+    };
+    // Two new samples, with a few seconds of clock drift.
+    assert_eq!(sync.synced_index(12, time(3605), interval), Some(10));
+    assert_eq!(sync.synced_index(10, time(3000), interval), Some(10));
+    // Full history of ten samples, two were dropped for the new ones.
+    assert_eq!(sync.synced_index(10, time(3595), interval), Some(8));
+    // Dropped out of the full history.
+    assert_eq!(sync.synced_index(10, time(6000), interval), None);
+    // Fewer samples than synced.
+    assert_eq!(sync.synced_index(5, time(3000), interval), None);
+    // Reset and recorded more samples since.
+    assert_eq!(sync.synced_index(12, time(9000), interval), None);
+    assert_eq!(sync.synced_index(12, time(3000), interval), None);
+    // Interval changed.
+    assert_eq!(
+        sync.synced_index(10, time(3000), Duration::seconds(60)),
+        None
+    );
+    let legacy = SensorSync {
+        interval: None,
+        ..sync
+    };
+    assert_eq!(legacy.synced_index(10, time(3000), interval), Some(10));
+}
+
+#[test]
+fn per_device() {
+    let a = "EB:12:34:56:78:9A".parse().unwrap();
+    let b = "EB:12:34:56:78:9B".parse().unwrap();
+    let sync = SensorSync {
+        last_index:     3,
+        last_timestamp: time(0),
+        interval:       None,
+    };
+    let mut state = SyncState::default();
+    state.insert(a, Sensor::CO2, sync);
+    assert_eq!(state.get(a, Sensor::CO2), Some(sync));
+    assert_eq!(state.get(b, Sensor::CO2), None);
+    assert_eq!(state.get(a, Sensor::Humidity), None);
+
+    let json = serde_json::to_string(&state).unwrap();
+    assert_eq!(serde_json::from_str::<SyncState>(&json).unwrap(), state);
+
+    state.remove(a, Sensor::CO2);
+    assert_eq!(state.get(a, Sensor::CO2), None);
+}
@@ -0,0 +1,36 @@
+use cotracker::{
+    units::{hpa_to_inhg, hpa_to_mmhg, PressureUnit, TemperatureUnit, Units},
+    Sensor,
+};
+
+#[test]
+fn temperature() {
+    assert!((TemperatureUnit::Celsius.from_celsius(21.5) - 21.5).abs() < 1e-4);
+    assert!((TemperatureUnit::Fahrenheit.from_celsius(21.5) - 70.7).abs() < 1e-4);
+    assert!((TemperatureUnit::Kelvin.from_celsius(21.5) - 294.65).abs() < 1e-4);
+}
+
+#[test]
+fn parse_temperature_unit() {
+    assert_eq!("F".parse(), Ok(TemperatureUnit::Fahrenheit));
+    assert_eq!("kelvin".parse(), Ok(TemperatureUnit::Kelvin));
+    assert!("x".parse::<TemperatureUnit>().is_err());
+}
+
+#[test]
+fn pressure() {
+    assert!((hpa_to_mmhg(1013.25) - 760.0).abs() < 0.01);
+    assert!((hpa_to_inhg(1013.25) - 29.92).abs() < 0.01);
+    assert_eq!("inHg".parse(), Ok(PressureUnit::Inhg));
+}
+
+#[test]
+fn convert() {
+    let units = Units {
+        temperature: TemperatureUnit::Fahrenheit,
+        pressure:    PressureUnit::Hpa,
+    };
+    assert!((units.convert(Sensor::Temperature, 0.0) - 32.0).abs() < 1e-4);
+    assert!((units.convert(Sensor::Pressure, 1013.2) - 1013.2).abs() < 1e-4);
+    assert!((units.convert(Sensor::CO2, 800.0) - 800.0).abs() < 1e-4);
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use axum::{http::StatusCode, routing::post, Router};
+use chrono::{TimeZone, Utc};
+use cotracker::{
+    alert::AlertEvent,
+    webhook::{AlertPayload, Webhook},
+    CurrentReading, Status,
+};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+fn reading() -> CurrentReading {
+    CurrentReading {
+        co2: Some(1250),
+        pressure: Some(1013.2),
+        status: Status::Amber,
+        ..common::reading()
+    }
+}
+
+#[test]
+fn payload() {
+    let reading = reading();
+    let address = "01:02:03:04:05:06".parse().unwrap();
+    let timestamp = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+    let payload = AlertPayload::co2(AlertEvent::Raised, address, 1000, &reading, timestamp);
+    let json = serde_json::to_value(&payload).unwrap();
+    assert_eq!(json["event"], "raised");
+    assert_eq!(json["device"], "01:02:03:04:05:06");
+    assert_eq!(json["sensor"], "co2");
+    assert_eq!(json["value"], 1250.0);
+    assert_eq!(json["threshold"], 1000.0);
+    assert_eq!(json["timestamp"], "2024-01-02T03:04:05Z");
+    assert_eq!(json["reading"], serde_json::to_value(reading).unwrap());
+}
+
+#[tokio::test]
+async fn send_retries() {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let app = Router::new().route(
+        "/",
+        post({
+            let received = Arc::clone(&received);
+            move |body: String| async move {
+                let mut received = received.lock().unwrap();
+                received.push(serde_json::from_str::<Value>(&body).unwrap());
+                if received.len() == 1 {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                } else {
+                    StatusCode::OK
+                }
+            }
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let reading = reading();
+    let address = "01:02:03:04:05:06".parse().unwrap();
+    let payload = AlertPayload::co2(AlertEvent::Cleared, address, 1000, &reading, Utc::now());
+    Webhook::new(&url).unwrap().send(&payload).await.unwrap();
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[1]["event"], json!("cleared"));
+}